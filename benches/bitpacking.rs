@@ -0,0 +1,60 @@
+/// Benchmarks for bit packing engine.
+///
+/// Payloads are hidden into and extracted from in memory pixel buffers, so measured time is
+/// spent splitting payload into chunks and packing chunks back into bytes rather than decoding
+/// or encoding image files.
+///
+/// # Usage example:
+/// ```ignore
+/// cargo bench --bench bitpacking
+/// ```
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+
+use steganer::{extract_bytes_from_carrier, hide_bytes_into_carrier, PixelBuffer};
+
+/// Bytes hidden at every benchmark iteration.
+const PAYLOAD_SIZE: usize = 256 * 1024;
+/// Steganer header pixels.
+const HEADER_PIXELS: usize = 32;
+/// Bits per pixel levels to measure.
+const BITS_PER_PIXEL: [usize; 4] = [1, 3, 12, 24];
+
+/// Create an RGB pixel buffer just big enough to hide payload at given bits per pixel.
+fn pixel_buffer(bits_per_pixel: usize)-> PixelBuffer {
+    let width = 1024;
+    let pixels = HEADER_PIXELS + (PAYLOAD_SIZE * 8).div_ceil(bits_per_pixel);
+    let height = pixels.div_ceil(width);
+    let content: Vec<u8> = (0..width * height * 3).map(|i| (i * 31) as u8).collect();
+    PixelBuffer::new(content, width as u32, height as u32, 3)
+        .expect("Error creating pixel buffer")
+}
+
+fn bench_hide(c: &mut Criterion) {
+    let payload: Vec<u8> = (0..PAYLOAD_SIZE).map(|i| (i * 7) as u8).collect();
+    let mut group = c.benchmark_group("hide");
+    group.throughput(Throughput::Bytes(PAYLOAD_SIZE as u64));
+    for &bits_per_pixel in BITS_PER_PIXEL.iter() {
+        let mut carrier = pixel_buffer(bits_per_pixel);
+        group.bench_with_input(BenchmarkId::from_parameter(bits_per_pixel), &payload, |b, payload| {
+            b.iter(|| hide_bytes_into_carrier(payload, &mut carrier).expect("Error hiding payload"))
+        });
+    }
+    group.finish();
+}
+
+fn bench_extract(c: &mut Criterion) {
+    let payload: Vec<u8> = (0..PAYLOAD_SIZE).map(|i| (i * 7) as u8).collect();
+    let mut group = c.benchmark_group("extract");
+    group.throughput(Throughput::Bytes(PAYLOAD_SIZE as u64));
+    for &bits_per_pixel in BITS_PER_PIXEL.iter() {
+        let mut carrier = pixel_buffer(bits_per_pixel);
+        hide_bytes_into_carrier(&payload, &mut carrier).expect("Error hiding payload");
+        group.bench_with_input(BenchmarkId::from_parameter(bits_per_pixel), &carrier, |b, carrier| {
+            b.iter(|| extract_bytes_from_carrier(carrier).expect("Error extracting payload"))
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, bench_hide, bench_extract);
+criterion_main!(benches);
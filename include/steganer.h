@@ -0,0 +1,149 @@
+/* Steganer C API. Generated with cbindgen, do not edit by hand. */
+
+#ifndef STEGANER_H
+#define STEGANER_H
+
+#include <stdarg.h>
+#include <stdbool.h>
+#include <stdint.h>
+#include <stdlib.h>
+
+/**
+ * Function succeeded.
+ */
+#define STEGANER_OK 0
+
+/**
+ * Function failed for any reason not covered by other status codes.
+ */
+#define STEGANER_ERROR 1
+
+/**
+ * A null pointer or a non UTF-8 path was given.
+ */
+#define STEGANER_INVALID_ARGUMENT 2
+
+/**
+ * Data to hide does not fit into host.
+ */
+#define STEGANER_PAYLOAD_TOO_LARGE 3
+
+/**
+ * Host type is not supported.
+ */
+#define STEGANER_UNSUPPORTED_FORMAT 4
+
+/**
+ * Host has no steganer hidden data.
+ */
+#define STEGANER_NO_PAYLOAD 5
+
+#ifdef __cplusplus
+extern "C" {
+#endif // __cplusplus
+
+/**
+ * Hide a file into an image. C version of *hide_into_image()*.
+ *
+ * # Safety
+ * Paths must be valid NUL terminated strings.
+ */
+int32_t steganer_hide_into_image(const char *file_to_hide, const char *host_file);
+
+/**
+ * Extract a file hidden into an image. C version of *extract_from_image()*.
+ *
+ * # Safety
+ * Paths must be valid NUL terminated strings.
+ */
+int32_t steganer_extract_from_image(const char *hidden_file, const char *host_file);
+
+/**
+ * Hide a memory buffer into an image. C version of *hide_bytes_into_image()*.
+ *
+ * # Safety
+ * Data must point to length readable bytes and path must be a valid NUL terminated string.
+ */
+int32_t steganer_hide_bytes_into_image(const uint8_t *data,
+                                       uintptr_t length,
+                                       const char *host_file);
+
+/**
+ * Extract data hidden into an image to memory. C version of *extract_bytes_from_image()*.
+ *
+ * Extracted bytes must be released with *steganer_free_bytes()*.
+ *
+ * # Safety
+ * Path must be a valid NUL terminated string and output pointers must be valid for writes.
+ */
+int32_t steganer_extract_bytes_from_image(const char *host_file, uint8_t **data, uintptr_t *length);
+
+/**
+ * Hide a memory buffer into raw RGB or RGBA pixels, modifying them in place.
+ *
+ * Pixels are laid out row by row, as *PixelBuffer* expects.
+ *
+ * # Safety
+ * Data must point to length readable bytes and pixels to width * height * channels writable
+ * bytes.
+ */
+int32_t steganer_hide_bytes_into_pixels(const uint8_t *data,
+                                        uintptr_t length,
+                                        uint8_t *pixels,
+                                        uint32_t width,
+                                        uint32_t height,
+                                        uint8_t channels);
+
+/**
+ * Extract data hidden into raw RGB or RGBA pixels to memory.
+ *
+ * Extracted bytes must be released with *steganer_free_bytes()*.
+ *
+ * # Safety
+ * Pixels must point to width * height * channels readable bytes and output pointers must be
+ * valid for writes.
+ */
+int32_t steganer_extract_bytes_from_pixels(const uint8_t *pixels,
+                                           uint32_t width,
+                                           uint32_t height,
+                                           uint8_t channels,
+                                           uint8_t **data,
+                                           uintptr_t *length);
+
+/**
+ * Get how many bytes can be hidden into an image. C version of *image_capacity()*.
+ *
+ * # Parameters:
+ * * host_file: Path to image file to analyze.
+ * * bits_per_pixel: Bits per pixel level to get capacity for, or 0 for the biggest level.
+ * * max_payload_bytes: Where maximum payload size is written.
+ *
+ * # Safety
+ * Path must be a valid NUL terminated string and output pointer must be valid for writes.
+ */
+int32_t steganer_image_capacity(const char *host_file,
+                                uint8_t bits_per_pixel,
+                                uint64_t *max_payload_bytes);
+
+/**
+ * Get description of last error at calling thread.
+ *
+ * # Returns:
+ * * NUL terminated string, owned by steganer and valid until next steganer call at this
+ *   thread, or null if last call succeeded.
+ */
+const char *steganer_last_error_message(void);
+
+/**
+ * Release bytes returned by steganer.
+ *
+ * # Safety
+ * Data and length must be exactly those returned by steganer, or data must be null.
+ */
+void steganer_free_bytes(uint8_t *data, uintptr_t length);
+
+#ifdef __cplusplus
+}  // extern "C"
+#endif  // __cplusplus
+
+#endif  /* STEGANER_H */
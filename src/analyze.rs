@@ -0,0 +1,534 @@
+/// Module to detect data hidden at least significant bits of an image.
+///
+/// Three classic steganalysis detectors are run independently over red, green and blue channels:
+/// * Chi-square attack: LSB replacement tends to equalize frequencies of values that only differ
+///   at their least significant bit. Probability of embedding is checked over growing portions of
+///   the channel to estimate how far sequential embedding reaches.
+/// * RS analysis: counts regular and singular pixel groups before and after flipping LSBs.
+/// * Sample pair analysis: counts trace sets of adjacent sample pairs.
+///
+/// RS and sample pair analysis estimate the fraction of channel samples carrying hidden bits.
+/// These detectors work with any LSB replacement tool, not only steganer. Host image is never
+/// modified.
+///
+/// # Usage example:
+/// ```ignore
+/// let report = analyze_image("suspicious.png")
+///                 .expect("Error analyzing image");
+/// println!("{}", report);
+/// ```
+use std::fmt;
+
+use crate::*;
+
+const CHANNEL_NAMES: [&str; 3] = ["red", "green", "blue"];
+/// Amount of growing channel portions chi-square attack is evaluated over.
+const CHI_SQUARE_STEPS: usize = 100;
+/// Chi-square probability over which a channel portion is considered to carry hidden data.
+const CHI_SQUARE_THRESHOLD: f64 = 0.5;
+/// Chi-square probability over which a whole channel is considered to be filled with hidden data.
+const CHI_SQUARE_FULL_EMBEDDING: f64 = 0.95;
+/// Mask applied to every group of pixels in RS analysis.
+const RS_MASK: [bool; 4] = [false, true, true, false];
+/// Estimated embedding rate over which an image is considered suspicious.
+const SUSPICIOUS_RATE: f64 = 0.05;
+/// Estimated embedding rate over which an image is considered to have hidden data.
+const DETECTED_RATE: f64 = 0.15;
+
+/// Conclusion about an analyzed image.
+#[derive(Debug, PartialEq, Copy, Clone)]
+pub enum Verdict {
+    /// No sign of hidden data was found.
+    Clean,
+    /// Some detectors found weak signs of hidden data. Natural images with lots of noise can
+    /// get this verdict too.
+    Suspicious,
+    /// Hidden data was detected.
+    HiddenDataDetected,
+}
+
+impl fmt::Display for Verdict {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let text = match self {
+            Verdict::Clean=> "clean",
+            Verdict::Suspicious=> "suspicious",
+            Verdict::HiddenDataDetected=> "hidden data detected",
+        };
+        write!(f, "{}", text)
+    }
+}
+
+/// Detector results for a single channel.
+#[derive(Debug, PartialEq, Clone)]
+pub struct ChannelAnalysis {
+    /// Channel name: red, green or blue.
+    pub channel: String,
+    /// Chi-square attack probability of embedding over the whole channel, between 0 and 1.
+    pub chi_square_probability: f64,
+    /// Fraction of the channel, from image start, where chi-square attack detects embedding.
+    pub chi_square_rate: f64,
+    /// Fraction of samples carrying hidden bits estimated by RS analysis.
+    pub rs_rate: f64,
+    /// Fraction of samples carrying hidden bits estimated by sample pair analysis.
+    pub sample_pairs_rate: f64,
+}
+
+impl ChannelAnalysis {
+    /// Check if chi-square attack detects embedding over the whole channel.
+    pub fn chi_square_detected(&self)-> bool {
+        self.chi_square_probability > CHI_SQUARE_FULL_EMBEDDING
+    }
+
+    /// Get this channel embedding rate estimation.
+    ///
+    /// RS and sample pair analysis estimations are averaged. Chi-square rate saturates long
+    /// before every sample carries hidden bits, so chi-square attack is only used to detect
+    /// embedding. When it does, any of the other detectors may have broken down and estimated no
+    /// embedding at all, so those estimations are left out. Chi-square rate is only used if both
+    /// broke down.
+    pub fn estimated_rate(&self)-> f64 {
+        if !self.chi_square_detected() {
+            return (self.rs_rate + self.sample_pairs_rate) / 2.0;
+        }
+        let rates: Vec<f64> = [self.rs_rate, self.sample_pairs_rate].iter()
+            .cloned()
+            .filter(|&rate| rate > 0.0)
+            .collect();
+        if rates.is_empty() {
+            self.chi_square_rate
+        } else {
+            rates.iter().sum::<f64>() / rates.len() as f64
+        }
+    }
+}
+
+/// Result of analyzing an image looking for hidden data.
+#[derive(Debug, PartialEq, Clone)]
+pub struct AnalysisReport {
+    /// Analyzed image.
+    pub host_file: String,
+    /// Detector results for red, green and blue channels.
+    pub channels: Vec<ChannelAnalysis>,
+    /// Highest embedding rate estimated for any channel.
+    pub estimated_rate: f64,
+    /// Conclusion about this image.
+    pub verdict: Verdict,
+}
+
+impl AnalysisReport {
+    /// Render this report as a JSON object.
+    pub fn to_json(&self)-> String {
+        let channels: Vec<String> = self.channels.iter()
+            .map(|channel| format!("{{\"channel\": \"{}\", \"chi_square_probability\": {}, \
+                                    \"chi_square_rate\": {}, \"rs_rate\": {}, \
+                                    \"sample_pairs_rate\": {}, \"estimated_rate\": {}}}",
+                                   channel.channel, channel.chi_square_probability,
+                                   channel.chi_square_rate, channel.rs_rate,
+                                   channel.sample_pairs_rate, channel.estimated_rate()))
+            .collect();
+        format!("{{\"host_file\": \"{}\", \"verdict\": \"{}\", \"estimated_rate\": {}, \
+                 \"channels\": [{}]}}",
+                probe::escape_json(&self.host_file), self.verdict, self.estimated_rate,
+                channels.join(", "))
+    }
+}
+
+impl fmt::Display for AnalysisReport {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "{}: {}.", self.host_file, self.verdict)?;
+        writeln!(f, "Channel    Chi-square    Chi-square rate    RS rate    Sample pairs rate")?;
+        for channel in self.channels.iter() {
+            writeln!(f, "{:<7}    {:>10.4}    {:>15.4}    {:>7.4}    {:>17.4}",
+                     channel.channel, channel.chi_square_probability, channel.chi_square_rate,
+                     channel.rs_rate, channel.sample_pairs_rate)?;
+        }
+        write!(f, "Estimated embedding rate: {:.4}", self.estimated_rate)
+    }
+}
+
+/// Get natural logarithm of gamma function using Lanczos approximation.
+fn ln_gamma(x: f64)-> f64 {
+    const COEFFICIENTS: [f64; 6] = [76.180_091_729_471_46, -86.505_320_329_416_77,
+                                    24.014_098_240_830_91, -1.231_739_572_450_155,
+                                    0.001_208_650_973_866_179, -0.000_005_395_239_384_953];
+    let tmp = x + 5.5 - (x + 0.5) * (x + 5.5).ln();
+    let series = COEFFICIENTS.iter().enumerate()
+        .fold(1.000_000_000_190_015, |sum, (i, coefficient)| sum + coefficient / (x + 1.0 + i as f64));
+    -tmp + (2.506_628_274_631_000_5 * series / x).ln()
+}
+
+/// Get regularized upper incomplete gamma function Q(a, x).
+fn upper_incomplete_gamma(a: f64, x: f64)-> f64 {
+    const ITERATIONS: usize = 500;
+    const EPSILON: f64 = 1e-14;
+    if x <= 0.0 { return 1.0; }
+    let ln_prefix = -x + a * x.ln() - ln_gamma(a);
+    if x < a + 1.0 {
+        // Series representation for lower incomplete gamma.
+        let mut term = 1.0 / a;
+        let mut sum = term;
+        for n in 1..ITERATIONS {
+            term *= x / (a + n as f64);
+            sum += term;
+            if term.abs() < sum.abs() * EPSILON { break; }
+        }
+        1.0 - sum * ln_prefix.exp()
+    } else {
+        // Lentz continued fraction for upper incomplete gamma.
+        let tiny = 1e-300;
+        let mut b = x + 1.0 - a;
+        let mut c = 1.0 / tiny;
+        let mut d = 1.0 / b;
+        let mut h = d;
+        for n in 1..ITERATIONS {
+            let an = -(n as f64) * (n as f64 - a);
+            b += 2.0;
+            d = an * d + b;
+            if d.abs() < tiny { d = tiny; }
+            c = b + an / c;
+            if c.abs() < tiny { c = tiny; }
+            d = 1.0 / d;
+            let delta = d * c;
+            h *= delta;
+            if (delta - 1.0).abs() < EPSILON { break; }
+        }
+        ln_prefix.exp() * h
+    }
+}
+
+/// Get chi-square attack probability of embedding for a value histogram.
+///
+/// # Parameters:
+/// * histogram: How many times every value from 0 to 255 appears.
+///
+/// # Returns:
+/// * Probability, between 0 and 1, that values were shuffled by LSB replacement.
+fn chi_square_probability(histogram: &[u64; 256])-> f64 {
+    let mut chi_square = 0_f64;
+    let mut categories = 0_u32;
+    for pair in histogram.chunks(2) {
+        let expected = (pair[0] + pair[1]) as f64 / 2.0;
+        if expected > 0.0 {
+            chi_square += (pair[0] as f64 - expected).powi(2) / expected;
+            categories += 1;
+        }
+    }
+    if categories < 2 { return 0.0; }
+    let degrees_of_freedom = (categories - 1) as f64;
+    upper_incomplete_gamma(degrees_of_freedom / 2.0, chi_square / 2.0)
+}
+
+/// Run chi-square attack over a channel.
+///
+/// # Parameters:
+/// * samples: Channel samples in the same order steganer hides data, row by row.
+///
+/// # Returns:
+/// * Probability of embedding over the whole channel.
+/// * Fraction of the channel, from its start, where probability of embedding stays high.
+fn chi_square_attack(samples: &[u8])-> (f64, f64) {
+    let mut histogram = [0_u64; 256];
+    let mut detected_steps = 0;
+    let mut still_detected = true;
+    let mut probability = 0.0;
+    let mut counted = 0;
+    for step in 1..=CHI_SQUARE_STEPS {
+        let portion_end = samples.len() * step / CHI_SQUARE_STEPS;
+        for &sample in samples[counted..portion_end].iter() {
+            histogram[sample as usize] += 1;
+        }
+        counted = portion_end;
+        probability = chi_square_probability(&histogram);
+        if still_detected && probability > CHI_SQUARE_THRESHOLD {
+            detected_steps += 1;
+        } else {
+            still_detected = false;
+        }
+    }
+    (probability, detected_steps as f64 / CHI_SQUARE_STEPS as f64)
+}
+
+/// Get how noisy a group of samples is, adding absolute differences between neighbours.
+fn discrimination(group: &[i16])-> i32 {
+    group.windows(2)
+        .map(|pair| (pair[1] - pair[0]).abs() as i32)
+        .sum()
+}
+
+/// Count regular and singular groups after applying a flipping mask.
+///
+/// # Parameters:
+/// * samples: Channel samples row by row.
+/// * width: Samples per row. Groups never cross rows.
+/// * negative: Use shifted flipping (-1 <-> 0, 1 <-> 2...) instead of LSB flipping.
+///
+/// # Returns:
+/// * Fraction of regular groups and fraction of singular groups.
+fn rs_groups(samples: &[u8], width: usize, negative: bool)-> (f64, f64) {
+    let (mut regular, mut singular, mut total) = (0_u64, 0_u64, 0_u64);
+    let mut group = [0_i16; 4];
+    let mut flipped = [0_i16; 4];
+    for row in samples.chunks(width) {
+        for row_group in row.chunks_exact(RS_MASK.len()) {
+            for (i, &sample) in row_group.iter().enumerate() {
+                group[i] = sample as i16;
+                flipped[i] = match (RS_MASK[i], negative) {
+                    (false, _)=> group[i],
+                    (true, false)=> group[i] ^ 1,
+                    (true, true)=> ((group[i] + 1) ^ 1) - 1,
+                };
+            }
+            let original = discrimination(&group);
+            let modified = discrimination(&flipped);
+            if modified > original { regular += 1; } else if modified < original { singular += 1; }
+            total += 1;
+        }
+    }
+    if total == 0 { return (0.0, 0.0); }
+    (regular as f64 / total as f64, singular as f64 / total as f64)
+}
+
+/// Estimate embedding rate of a channel using RS analysis.
+///
+/// # Parameters:
+/// * samples: Channel samples row by row.
+/// * width: Samples per row.
+///
+/// # Returns:
+/// * Estimated fraction of samples carrying hidden bits, between 0 and 1.
+fn rs_analysis(samples: &[u8], width: usize)-> f64 {
+    let flipped_samples: Vec<u8> = samples.iter().map(|sample| sample ^ 1).collect();
+    let (regular, singular) = rs_groups(samples, width, false);
+    let (negative_regular, negative_singular) = rs_groups(samples, width, true);
+    let (flipped_regular, flipped_singular) = rs_groups(&flipped_samples, width, false);
+    let (flipped_negative_regular, flipped_negative_singular) =
+        rs_groups(&flipped_samples, width, true);
+    let d0 = regular - singular;
+    let d1 = flipped_regular - flipped_singular;
+    let negative_d0 = negative_regular - negative_singular;
+    let negative_d1 = flipped_negative_regular - flipped_negative_singular;
+    let a = 2.0 * (d1 + d0);
+    let b = negative_d0 - negative_d1 - d1 - 3.0 * d0;
+    let c = d0 - negative_d0;
+    let x = match smallest_root(a, b, c) {
+        Some(x)=> x,
+        None=> return 0.0,
+    };
+    clamp_rate(x / (x - 0.5))
+}
+
+/// Estimate embedding rate of a channel using sample pair analysis.
+///
+/// Sample pairs are classified by their difference and by the difference of their values once
+/// least significant bit is dropped. Natural images have about the same amount of pairs with a
+/// given odd difference at both possible classes, but LSB replacement breaks that balance.
+///
+/// # Parameters:
+/// * samples: Channel samples row by row.
+/// * width: Samples per row. Pairs are made of horizontal neighbours.
+///
+/// # Returns:
+/// * Estimated fraction of samples carrying hidden bits, between 0 and 1.
+fn sample_pair_analysis(samples: &[u8], width: usize)-> f64 {
+    // Pairs whose values only differ at their least significant bit, or not at all.
+    let mut same_closure = 0_u64;
+    let mut equal_pairs = 0_u64;
+    // Pairs with an odd difference 2m+1 whose values without LSB differ by m minus those
+    // whose values without LSB differ by m+1.
+    let mut odd_difference_balance = 0_i64;
+    for row in samples.chunks(width) {
+        for pair in row.windows(2) {
+            let difference = (pair[0] as i16 - pair[1] as i16).abs();
+            let closure_difference = ((pair[0] >> 1) as i16 - (pair[1] >> 1) as i16).abs();
+            if closure_difference == 0 { same_closure += 1; }
+            if difference == 0 { equal_pairs += 1; }
+            if difference % 2 == 1 {
+                if closure_difference == difference / 2 {
+                    odd_difference_balance += 1;
+                } else {
+                    odd_difference_balance -= 1;
+                }
+            }
+        }
+    }
+    let a = same_closure as f64 / 2.0;
+    let b = -(equal_pairs as f64 + odd_difference_balance as f64);
+    let c = odd_difference_balance as f64;
+    match smallest_root(a, b, c) {
+        Some(rate)=> clamp_rate(rate),
+        None=> 0.0,
+    }
+}
+
+/// Get the root with the smallest absolute value of a x^2 + b x + c = 0.
+///
+/// # Returns:
+/// * Root or None if there is no real root.
+fn smallest_root(a: f64, b: f64, c: f64)-> Option<f64> {
+    if a.abs() < f64::EPSILON {
+        return if b.abs() < f64::EPSILON { None } else { Some(-c / b) };
+    }
+    let discriminant = b * b - 4.0 * a * c;
+    if discriminant < 0.0 { return None; }
+    let first = (-b + discriminant.sqrt()) / (2.0 * a);
+    let second = (-b - discriminant.sqrt()) / (2.0 * a);
+    Some(if first.abs() < second.abs() { first } else { second })
+}
+
+/// Keep an estimated rate between 0 and 1.
+fn clamp_rate(rate: f64)-> f64 {
+    if rate.is_nan() { 0.0 } else { rate.clamp(0.0, 1.0) }
+}
+
+/// Get samples of every channel, row by row.
+fn channel_samples(host_image: &ContainerImage)-> [Vec<u8>; 3] {
+    let (width, height) = host_image.dimensions();
+    let total_pixels = width as usize * height as usize;
+    let mut channels = [Vec::with_capacity(total_pixels), Vec::with_capacity(total_pixels),
+                        Vec::with_capacity(total_pixels)];
+    for y in 0..height {
+        for x in 0..width {
+            let rgb = host_image.get_rgb(x, y);
+            for (channel, samples) in channels.iter_mut().enumerate() {
+                samples.push(rgb[channel]);
+            }
+        }
+    }
+    channels
+}
+
+/// Run every detector over a channel.
+fn analyze_channel(channel: &str, samples: &[u8], width: usize)-> ChannelAnalysis {
+    let (chi_square_probability, chi_square_rate) = chi_square_attack(samples);
+    ChannelAnalysis {
+        channel: String::from(channel),
+        chi_square_probability,
+        chi_square_rate,
+        rs_rate: rs_analysis(samples, width),
+        sample_pairs_rate: sample_pair_analysis(samples, width),
+    }
+}
+
+/// Get a verdict from an estimated embedding rate.
+fn verdict_for(estimated_rate: f64)-> Verdict {
+    if estimated_rate >= DETECTED_RATE {
+        Verdict::HiddenDataDetected
+    } else if estimated_rate >= SUSPICIOUS_RATE {
+        Verdict::Suspicious
+    } else {
+        Verdict::Clean
+    }
+}
+
+/// Analyze an image looking for data hidden at its least significant bits.
+///
+/// Image is not modified.
+///
+/// # Parameters:
+/// * host_file: Absolute path to image file to analyze.
+///
+/// # Returns:
+/// * Report with every detector results and a verdict.
+pub fn analyze_image(host_file: &str)-> Result<AnalysisReport> {
+    let host_image = ContainerImage::new(host_file)?;
+    let width = host_image.dimensions().0 as usize;
+    let channels: Vec<ChannelAnalysis> = CHANNEL_NAMES.iter()
+        .zip(channel_samples(&host_image).iter())
+        .map(|(channel, samples)| analyze_channel(channel, samples, width))
+        .collect();
+    let estimated_rate = channels.iter()
+        .map(|channel| channel.estimated_rate())
+        .fold(0.0, f64::max);
+    let verdict = if channels.iter().any(|channel| channel.chi_square_detected()) {
+        Verdict::HiddenDataDetected
+    } else {
+        verdict_for(estimated_rate)
+    };
+    Ok(AnalysisReport {
+        host_file: String::from(host_file),
+        channels,
+        estimated_rate,
+        verdict,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::GenericImageView;
+    use rand::{Rng, SeedableRng};
+    use rand::rngs::StdRng;
+
+    /// Get Lenna blue channel, replacing LSBs of a leading fraction of samples with random bits.
+    fn lenna_blue_channel(rate: f64)-> (Vec<u8>, usize) {
+        let image = image::open("tests/resources/Lenna_(test_image).png")
+            .expect("Error opening test image");
+        let width = image.width() as usize;
+        let embedded_samples = (rate * (width * image.height() as usize) as f64) as usize;
+        let mut rng = StdRng::seed_from_u64(31);
+        let samples: Vec<u8> = image.to_rgb().pixels()
+            .enumerate()
+            .map(|(i, pixel)| {
+                if i < embedded_samples { (pixel[2] & 0b_1111_1110) | rng.gen_range(0, 2) }
+                else { pixel[2] }
+            })
+            .collect();
+        (samples, width)
+    }
+
+    #[test]
+    fn test_upper_incomplete_gamma() {
+        // For a = 1 upper incomplete gamma is exp(-x).
+        for &x in [0.5, 1.0, 3.0, 10.0].iter() {
+            assert!((upper_incomplete_gamma(1.0, x) - (-x).exp()).abs() < 1e-9,
+                    "Wrong upper incomplete gamma for x = {}", x);
+        }
+        // Chi-square with 2 degrees of freedom: P(X > 5.991) = 0.05.
+        assert!((upper_incomplete_gamma(1.0, 5.991 / 2.0) - 0.05).abs() < 1e-4);
+    }
+
+    #[test]
+    fn test_chi_square_attack() {
+        let (clean, _) = lenna_blue_channel(0.0);
+        let (clean_probability, clean_rate) = chi_square_attack(&clean);
+        assert!(clean_probability < 0.05, "Clean channel got {} probability.", clean_probability);
+        assert_eq!(0.0, clean_rate);
+        let (full, _) = lenna_blue_channel(1.0);
+        let (full_probability, full_rate) = chi_square_attack(&full);
+        assert!(full_probability > CHI_SQUARE_FULL_EMBEDDING,
+                "Fully embedded channel got {} probability.", full_probability);
+        assert_eq!(1.0, full_rate);
+    }
+
+    #[test]
+    fn test_estimated_rate() {
+        // Chi-square attack detects embedding over the whole channel from about 0.75 onwards,
+        // and RS and sample pair analysis break down at 0.9 and 1.0 respectively.
+        for &rate in [0.3, 0.61, 0.75, 0.9, 1.0].iter() {
+            let (samples, width) = lenna_blue_channel(rate);
+            let analysis = analyze_channel("blue", &samples, width);
+            assert!((analysis.estimated_rate() - rate).abs() < 0.05,
+                    "Estimated {} rate but real one was {}.", analysis.estimated_rate(), rate);
+        }
+    }
+
+    #[test]
+    fn test_rs_analysis() {
+        for &rate in [0.0, 0.25, 0.5].iter() {
+            let (samples, width) = lenna_blue_channel(rate);
+            let estimated_rate = rs_analysis(&samples, width);
+            assert!((estimated_rate - rate).abs() < 0.05,
+                    "RS analysis estimated {} rate but real one was {}.", estimated_rate, rate);
+        }
+    }
+
+    #[test]
+    fn test_sample_pair_analysis() {
+        for &rate in [0.0, 0.25, 0.5].iter() {
+            let (samples, width) = lenna_blue_channel(rate);
+            let estimated_rate = sample_pair_analysis(&samples, width);
+            assert!((estimated_rate - rate).abs() < 0.05,
+                    "Sample pair analysis estimated {} rate but real one was {}.", estimated_rate, rate);
+        }
+    }
+}
@@ -0,0 +1,586 @@
+/// Module to hide data inside animated PNG (APNG) images.
+///
+/// Only 8 bits truecolor APNG images, with or without alpha channel, are supported. Every pixel
+/// of every frame is a carrier sample, with its RGB channels packed just like at static images,
+/// so alpha channel is never modified. Frames are used in the same order they are stored at
+/// file, starting with default image.
+///
+/// Frame image data is decompressed to modify its pixels and compressed again when saving,
+/// choosing a filter for every scanline. Any other chunk, like frame control ones with frame
+/// delays, disposal and blend operations, or animation control one with loop count, is kept.
+/// As every frame is stored again in a single data chunk, sequence numbers of frame chunks are
+/// renumbered.
+///
+/// Hidden data uses the same 32 samples header every carrier uses, which only stores hidden data
+/// size. No frame/offset mapping is recorded there, as frames and samples are always found again
+/// following the fixed order above. Header has no version or carrier type field either, so
+/// extracting from a host of a different type than the one used to hide is not detected: it
+/// just fails with a NoPayload error or gets garbage.
+///
+/// # Usage example:
+/// ```ignore
+/// let host_apng = ContainerApng::new("loading.png")
+///                         .expect("Error opening host APNG");
+/// println!("{} bytes can be hidden", max_payload_bytes(&host_apng));
+/// ```
+use std::fs;
+use std::path::Path;
+
+use crate::*;
+use crate::bytetools::{bytes_to_u24, u24_to_bytes};
+use crate::carrier::Carrier;
+
+const PNG_SIGNATURE: [u8; 8] = [0x89, b'P', b'N', b'G', 0x0D, 0x0A, 0x1A, 0x0A];
+/// Chunk length, chunk type and chunk CRC.
+const CHUNK_OVERHEAD: usize = 12;
+const COLOR_TYPE_RGB: u8 = 2;
+const COLOR_TYPE_RGBA: u8 = 6;
+const CRC_POLYNOMIAL: u32 = 0xEDB8_8320;
+
+/// Check if a PNG file content has an animation control chunk before its image data.
+fn is_animated_png(content: &[u8])-> bool {
+    match read_chunks(content) {
+        Ok(chunks)=> chunks.iter()
+            .take_while(|chunk| &chunk.kind != b"IDAT")
+            .any(|chunk| &chunk.kind == b"acTL"),
+        Err(_)=> false,
+    }
+}
+
+/// Check if this file is supported as a valid host APNG image.
+///
+/// Files with apng extension are always taken as APNG images, but png ones are only taken as
+/// such if they are animated, as static PNG images are handled as any other image.
+///
+/// # Parameters:
+/// * filename: Host image filename.
+///
+/// # Returns:
+/// * True if this file is an APNG image and false if not.
+pub(crate) fn supported_apng(filename: &str)-> bool {
+    match Path::new(filename).extension().and_then(|extension| extension.to_str()) {
+        Some(extension)=> match extension.to_lowercase().as_str() {
+            "apng"=> true,
+            "png"=> fs::read(filename).map(|content| is_animated_png(&content)).unwrap_or(false),
+            _=> false,
+        },
+        None=> false,
+    }
+}
+
+/// A PNG chunk, without its length and CRC.
+struct PngChunk {
+    kind: [u8; 4],
+    data: Vec<u8>,
+}
+
+/// An APNG frame, with its pixels decompressed and unfiltered.
+struct ApngFrame {
+    width: usize,
+    height: usize,
+    /// Position at chunk list of every chunk with frame image data.
+    chunk_indexes: Vec<usize>,
+    /// Pixels bytes, row after row, without filter type bytes.
+    pixels: Vec<u8>,
+}
+
+/// Read a big endian u32 at given position.
+fn read_u32(content: &[u8], position: usize)-> u32 {
+    u32::from_be_bytes([content[position], content[position + 1],
+                        content[position + 2], content[position + 3]])
+}
+
+/// Get CRC-32 of a chunk, calculated over its type and data.
+fn crc32(kind: &[u8], data: &[u8])-> u32 {
+    let mut crc = 0xFFFF_FFFF_u32;
+    for &byte in kind.iter().chain(data) {
+        crc ^= byte as u32;
+        for _ in 0..8 {
+            crc = if crc & 1 == 1 { (crc >> 1) ^ CRC_POLYNOMIAL } else { crc >> 1 };
+        }
+    }
+    !crc
+}
+
+/// Split PNG file content in chunks.
+fn read_chunks(content: &[u8])-> Result<Vec<PngChunk>> {
+    if !content.starts_with(&PNG_SIGNATURE) {
+        bail!("File is not a valid PNG file.");
+    }
+    let mut chunks = Vec::new();
+    let mut position = PNG_SIGNATURE.len();
+    while position + CHUNK_OVERHEAD <= content.len() {
+        let length = read_u32(content, position) as usize;
+        let data_start = position + 8;
+        if data_start + length + 4 > content.len() {
+            bail!("PNG chunk {} is truncated.", chunks.len());
+        }
+        let mut kind = [0; 4];
+        kind.copy_from_slice(&content[position + 4..data_start]);
+        chunks.push(PngChunk{kind, data: content[data_start..data_start + length].to_vec()});
+        position = data_start + length + 4;
+        if &kind == b"IEND" {
+            break;
+        }
+    }
+    Ok(chunks)
+}
+
+/// Paeth predictor used by PNG filter type 4.
+fn paeth(left: u8, up: u8, up_left: u8)-> u8 {
+    let estimate = left as i16 + up as i16 - up_left as i16;
+    let left_distance = (estimate - left as i16).abs();
+    let up_distance = (estimate - up as i16).abs();
+    let up_left_distance = (estimate - up_left as i16).abs();
+    if left_distance <= up_distance && left_distance <= up_left_distance {
+        left
+    } else if up_distance <= up_left_distance {
+        up
+    } else {
+        up_left
+    }
+}
+
+/// Get value predicted for a byte by a PNG filter type.
+///
+/// # Parameters:
+/// * filter_type: PNG filter type, from 0 to 4.
+/// * left: Byte at same channel of previous pixel.
+/// * up: Byte at same position of previous row.
+/// * up_left: Byte at same channel of previous pixel of previous row.
+fn predict(filter_type: u8, left: u8, up: u8, up_left: u8)-> u8 {
+    match filter_type {
+        1=> left,
+        2=> up,
+        3=> ((left as u16 + up as u16) / 2) as u8,
+        4=> paeth(left, up, up_left),
+        _=> 0,
+    }
+}
+
+/// Remove PNG filters from decompressed image data.
+///
+/// # Parameters:
+/// * data: Decompressed image data, with a filter type byte before every row.
+/// * height: Rows of image.
+/// * stride: Bytes of every row, without filter type byte.
+/// * bytes_per_pixel: Bytes of every pixel.
+///
+/// # Returns:
+/// * Pixels bytes, row after row.
+/// * An error if data is truncated, has unknown filters or if image does not fit in memory.
+fn unfilter(data: &[u8], height: usize, stride: usize, bytes_per_pixel: usize)-> Result<Vec<u8>> {
+    // Image size comes from file chunks, so it can be as big as anyone wants.
+    let filtered_length = match stride.checked_add(1).and_then(|row| row.checked_mul(height)) {
+        Some(filtered_length)=> filtered_length,
+        None=> bail!("PNG image size is too big."),
+    };
+    if data.len() < filtered_length {
+        bail!("PNG image data is truncated.");
+    }
+    let mut pixels = vec![0_u8; height * stride];
+    for row in 0..height {
+        let filter_type = data[row * (stride + 1)];
+        if filter_type > 4 {
+            bail!("PNG image data has an unknown filter type {}.", filter_type);
+        }
+        let filtered = &data[row * (stride + 1) + 1..(row + 1) * (stride + 1)];
+        for (column, &filtered_byte) in filtered.iter().enumerate() {
+            let position = row * stride + column;
+            let left = if column >= bytes_per_pixel { pixels[position - bytes_per_pixel] } else { 0 };
+            let up = if row > 0 { pixels[position - stride] } else { 0 };
+            let up_left = if row > 0 && column >= bytes_per_pixel {
+                pixels[position - stride - bytes_per_pixel]
+            } else { 0 };
+            pixels[position] = filtered_byte.wrapping_add(predict(filter_type, left, up, up_left));
+        }
+    }
+    Ok(pixels)
+}
+
+/// Apply a PNG filter type to a row of pixels.
+///
+/// # Parameters:
+/// * pixels: Pixels bytes, row after row.
+/// * row: Row to filter.
+/// * stride: Bytes of every row.
+/// * bytes_per_pixel: Bytes of every pixel.
+/// * filter_type: PNG filter type, from 0 to 4.
+///
+/// # Returns:
+/// * Filtered row, without filter type byte.
+fn filter_row(pixels: &[u8], row: usize, stride: usize, bytes_per_pixel: usize, filter_type: u8)-> Vec<u8> {
+    (0..stride).map(|column| {
+        let position = row * stride + column;
+        let left = if column >= bytes_per_pixel { pixels[position - bytes_per_pixel] } else { 0 };
+        let up = if row > 0 { pixels[position - stride] } else { 0 };
+        let up_left = if row > 0 && column >= bytes_per_pixel {
+            pixels[position - stride - bytes_per_pixel]
+        } else { 0 };
+        pixels[position].wrapping_sub(predict(filter_type, left, up, up_left))
+    }).collect()
+}
+
+/// Apply PNG filters to pixels before compressing them.
+///
+/// Every row uses the filter type whose output has the lowest sum of absolute values, as usual
+/// PNG encoders do.
+///
+/// # Parameters:
+/// * pixels: Pixels bytes, row after row.
+/// * height: Rows of image.
+/// * stride: Bytes of every row.
+/// * bytes_per_pixel: Bytes of every pixel.
+///
+/// # Returns:
+/// * Image data, with a filter type byte before every row.
+fn filter(pixels: &[u8], height: usize, stride: usize, bytes_per_pixel: usize)-> Vec<u8> {
+    let mut data = Vec::with_capacity(height * (stride + 1));
+    for row in 0..height {
+        let (filter_type, filtered) = (0..5_u8)
+            .map(|filter_type| (filter_type, filter_row(pixels, row, stride, bytes_per_pixel, filter_type)))
+            .min_by_key(|(_, filtered)| filtered.iter()
+                .map(|&byte| (byte as i8).unsigned_abs() as u32)
+                .sum::<u32>())
+            .expect("There is always a filter type");
+        data.push(filter_type);
+        data.extend(filtered);
+    }
+    data
+}
+
+/// Parse APNG chunks looking for its frames.
+///
+/// # Parameters:
+/// * chunks: Every chunk of APNG file.
+///
+/// # Returns:
+/// * Every frame found at file, with its pixels decompressed, and bytes of every pixel.
+/// * An error if file is not an APNG file or if its pixel format is not supported.
+fn parse_apng(chunks: &[PngChunk])-> Result<(Vec<ApngFrame>, usize)> {
+    let header = match chunks.first() {
+        Some(chunk) if &chunk.kind == b"IHDR" && chunk.data.len() >= 13=> &chunk.data,
+        _=> bail!("PNG file has no valid header chunk."),
+    };
+    let (bit_depth, color_type, interlace) = (header[8], header[9], header[12]);
+    let bytes_per_pixel = match (bit_depth, color_type) {
+        (8, COLOR_TYPE_RGB)=> 3,
+        (8, COLOR_TYPE_RGBA)=> 4,
+        _=> bail!("Only 8 bits truecolor APNG images are supported."),
+    };
+    if interlace != 0 {
+        bail!("Interlaced APNG images are not supported.");
+    }
+    if !chunks.iter().any(|chunk| &chunk.kind == b"acTL") {
+        bail!("PNG image is not animated.");
+    }
+    let mut frames: Vec<ApngFrame> = Vec::new();
+    let canvas_size = (read_u32(header, 0) as usize, read_u32(header, 4) as usize);
+    let mut frame_size = canvas_size;
+    let mut frame_data: Vec<Vec<u8>> = Vec::new();
+    for (index, chunk) in chunks.iter().enumerate() {
+        let data = match &chunk.kind {
+            b"IDAT"=> &chunk.data[..],
+            b"fdAT" if chunk.data.len() >= 4=> &chunk.data[4..],
+            b"fcTL"=> {
+                if chunk.data.len() < 26 {
+                    bail!("APNG frame control chunk is truncated.");
+                }
+                frame_size = (read_u32(&chunk.data, 4) as usize, read_u32(&chunk.data, 8) as usize);
+                let offset = (read_u32(&chunk.data, 12) as usize, read_u32(&chunk.data, 16) as usize);
+                let fits = |size: usize, offset: usize, canvas: usize| size as u64 + offset as u64 <= canvas as u64;
+                if !fits(frame_size.0, offset.0, canvas_size.0) || !fits(frame_size.1, offset.1, canvas_size.1) {
+                    bail!("APNG frame of {}x{} at {}x{} does not fit in {}x{} image.",
+                          frame_size.0, frame_size.1, offset.0, offset.1, canvas_size.0, canvas_size.1);
+                }
+                continue;
+            },
+            _=> continue,
+        };
+        // Consecutive image data chunks of the same type belong to the same frame.
+        let continues_frame = index > 0 && chunks[index - 1].kind == chunk.kind && !frames.is_empty();
+        if continues_frame {
+            frames.last_mut().unwrap().chunk_indexes.push(index);
+            frame_data.last_mut().unwrap().extend_from_slice(data);
+        } else {
+            let (width, height) = frame_size;
+            frames.push(ApngFrame{width, height, chunk_indexes: vec![index], pixels: Vec::new()});
+            frame_data.push(data.to_vec());
+        }
+    }
+    for (frame_index, (frame, data)) in frames.iter_mut().zip(frame_data).enumerate() {
+        let decompressed = match inflate::inflate_bytes_zlib(&data) {
+            Ok(decompressed)=> decompressed,
+            Err(error)=> bail!("Error decompressing APNG frame {}: {}", frame_index, error),
+        };
+        frame.pixels = unfilter(&decompressed, frame.height, frame.width * bytes_per_pixel,
+                                bytes_per_pixel)
+            .chain_err(|| format!("Error reading APNG frame {}", frame_index))?;
+    }
+    Ok((frames, bytes_per_pixel))
+}
+
+/// An APNG image used to hide data at pixels of its frames.
+///
+/// Frames are kept in memory and file is written back when *save()* is called or when this type
+/// is dropped, but only if any pixel was modified.
+pub struct ContainerApng<'a> {
+    chunks: Vec<PngChunk>,
+    frames: Vec<ApngFrame>,
+    bytes_per_pixel: usize,
+    /// Sample where every frame starts.
+    frame_starts: Vec<u64>,
+    file_pathname: &'a str,
+    /// Set when any pixel has been modified, so image must be saved on drop.
+    modified: bool,
+}
+
+impl <'a> ContainerApng<'a> {
+    /// Open an APNG image as a carrier.
+    ///
+    /// # Parameters:
+    /// * file_pathname: Absolute path to APNG image.
+    ///
+    /// # Returns:
+    /// * Image ready to hide data into or extract data from.
+    /// * An error if file could not be read or its frames are not supported.
+    pub fn new(file_pathname: &'a str)-> Result<Self> {
+        let content = fs::read(file_pathname)
+            .chain_err(|| format!("Error reading host image {}", file_pathname))?;
+        let chunks = read_chunks(&content)
+            .chain_err(|| format!("Error parsing host image {}", file_pathname))?;
+        let (frames, bytes_per_pixel) = parse_apng(&chunks)
+            .chain_err(|| format!("Error parsing host image {}", file_pathname))?;
+        let frame_starts = frames.iter()
+            .scan(0_u64, |start, frame| {
+                let frame_start = *start;
+                *start += (frame.width * frame.height) as u64;
+                Some(frame_start)
+            })
+            .collect();
+        Ok(ContainerApng{chunks, frames, bytes_per_pixel, frame_starts, file_pathname,
+                         modified: false})
+    }
+
+    /// Get frame index and position at its pixels bytes of nth sample.
+    fn pixel_offset(&self, position: u64)-> (usize, usize) {
+        let frame_index = self.frame_starts.partition_point(|&start| start <= position) - 1;
+        let pixel = (position - self.frame_starts[frame_index]) as usize;
+        (frame_index, pixel * self.bytes_per_pixel)
+    }
+
+    /// Get whole APNG content, compressing again every frame into a single data chunk.
+    fn to_bytes(&self)-> Vec<u8> {
+        let mut bytes = PNG_SIGNATURE.to_vec();
+        let mut sequence_number = 0_u32;
+        let mut write_chunk = |kind: &[u8], data: &[u8]| {
+            bytes.extend_from_slice(&(data.len() as u32).to_be_bytes());
+            bytes.extend_from_slice(kind);
+            bytes.extend_from_slice(data);
+            bytes.extend_from_slice(&crc32(kind, data).to_be_bytes());
+        };
+        for (index, chunk) in self.chunks.iter().enumerate() {
+            match &chunk.kind {
+                b"fcTL"=> {
+                    let mut data = chunk.data.clone();
+                    data[..4].copy_from_slice(&sequence_number.to_be_bytes());
+                    sequence_number += 1;
+                    write_chunk(b"fcTL", &data);
+                },
+                b"IDAT" | b"fdAT"=> {
+                    let frame = match self.frames.iter().find(|frame| frame.chunk_indexes[0] == index) {
+                        Some(frame)=> frame,
+                        // Rest of frame data chunks are replaced by the first one.
+                        None=> continue,
+                    };
+                    let filtered = filter(&frame.pixels, frame.height,
+                                          frame.width * self.bytes_per_pixel, self.bytes_per_pixel);
+                    let compressed = deflate::deflate_bytes_zlib(&filtered);
+                    if &chunk.kind == b"IDAT" {
+                        write_chunk(b"IDAT", &compressed);
+                    } else {
+                        let mut data = sequence_number.to_be_bytes().to_vec();
+                        sequence_number += 1;
+                        data.extend(compressed);
+                        write_chunk(b"fdAT", &data);
+                    }
+                },
+                _=> write_chunk(&chunk.kind, &chunk.data),
+            }
+        }
+        bytes
+    }
+}
+
+impl <'a> Carrier for ContainerApng<'a> {
+    fn sample_count(&self)-> u64 {
+        self.frames.iter().map(|frame| (frame.width * frame.height) as u64).sum()
+    }
+
+    fn sample_bits(&self)-> u8 {
+        24
+    }
+
+    fn read_sample(&self, position: u64)-> u64 {
+        let (frame_index, offset) = self.pixel_offset(position);
+        let pixels = &self.frames[frame_index].pixels;
+        bytes_to_u24(&[pixels[offset], pixels[offset + 1], pixels[offset + 2]]) as u64
+    }
+
+    fn write_sample(&mut self, position: u64, value: u64) {
+        let (frame_index, offset) = self.pixel_offset(position);
+        self.frames[frame_index].pixels[offset..offset + 3].copy_from_slice(&u24_to_bytes(value as u32));
+        self.modified = true;
+    }
+
+    fn save(&mut self)-> Result<()> {
+        if self.modified {
+            fs::write(self.file_pathname, self.to_bytes())
+                .chain_err(|| format!("Error saving host image {}", self.file_pathname))?;
+            self.modified = false;
+        }
+        Ok(())
+    }
+}
+
+/// Save to file every change done over APNG.
+impl <'a> Drop for ContainerApng<'a> {
+    fn drop(&mut self) {
+        self.save().expect("APNG could not be saved");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use test_common::fs::tmp::TestEnvironment;
+
+    /// Build a chunk as stored at file.
+    fn chunk_bytes(kind: &[u8], data: &[u8])-> Vec<u8> {
+        let mut bytes = (data.len() as u32).to_be_bytes().to_vec();
+        bytes.extend_from_slice(kind);
+        bytes.extend_from_slice(data);
+        bytes.extend_from_slice(&crc32(kind, data).to_be_bytes());
+        bytes
+    }
+
+    /// Build frame control chunk data for a frame.
+    fn frame_control(sequence_number: u32, width: u32, height: u32)-> Vec<u8> {
+        let mut data = Vec::new();
+        for value in [sequence_number, width, height, 0, 0].iter() {
+            data.extend_from_slice(&value.to_be_bytes());
+        }
+        // 1/10 seconds delay, background disposal and over blend.
+        data.extend_from_slice(&[0, 1, 0, 10, 1, 1]);
+        data
+    }
+
+    /// Build a 4x3 RGBA APNG with a 4x3 default image and a 2x2 second frame, whose data is
+    /// split in two chunks.
+    fn apng_content()-> Vec<u8> {
+        let mut header = Vec::new();
+        header.extend_from_slice(&4_u32.to_be_bytes());
+        header.extend_from_slice(&3_u32.to_be_bytes());
+        header.extend_from_slice(&[8, COLOR_TYPE_RGBA, 0, 0, 0]);
+        let first_pixels: Vec<u8> = (0..4 * 3 * 4).map(|i| (i * 5) as u8).collect();
+        let second_pixels: Vec<u8> = (0..2 * 2 * 4).map(|i| (200 - i * 3) as u8).collect();
+        let first_data = deflate::deflate_bytes_zlib(&filter(&first_pixels, 3, 16, 4));
+        let second_data = deflate::deflate_bytes_zlib(&filter(&second_pixels, 2, 8, 4));
+        let (second_start, second_end) = second_data.split_at(second_data.len() / 2);
+        let mut content = PNG_SIGNATURE.to_vec();
+        content.extend(chunk_bytes(b"IHDR", &header));
+        content.extend(chunk_bytes(b"acTL", &[0, 0, 0, 2, 0, 0, 0, 0]));
+        content.extend(chunk_bytes(b"fcTL", &frame_control(0, 4, 3)));
+        content.extend(chunk_bytes(b"IDAT", &first_data));
+        content.extend(chunk_bytes(b"fcTL", &frame_control(1, 2, 2)));
+        let mut fdat = 2_u32.to_be_bytes().to_vec();
+        fdat.extend_from_slice(second_start);
+        content.extend(chunk_bytes(b"fdAT", &fdat));
+        let mut fdat = 3_u32.to_be_bytes().to_vec();
+        fdat.extend_from_slice(second_end);
+        content.extend(chunk_bytes(b"fdAT", &fdat));
+        content.extend(chunk_bytes(b"IEND", &[]));
+        content
+    }
+
+    #[test]
+    fn test_crc32() {
+        // CRC of an empty IEND chunk is always the same.
+        assert_eq!(0xAE42_6082, crc32(b"IEND", &[]));
+    }
+
+    #[test]
+    fn test_filter_and_unfilter() {
+        let pixels: Vec<u8> = (0..5 * 4 * 3).map(|i| ((i * 37) % 251) as u8).collect();
+        let data = filter(&pixels, 4, 15, 3);
+        assert_eq!(4 * 16, data.len());
+        assert_eq!(pixels, unfilter(&data, 4, 15, 3).unwrap());
+        // Every filter type is correctly reverted, not only those chosen by filter().
+        for filter_type in 0..5_u8 {
+            let mut data = Vec::new();
+            for row in 0..4 {
+                data.push(filter_type);
+                data.extend(filter_row(&pixels, row, 15, 3, filter_type));
+            }
+            assert_eq!(pixels, unfilter(&data, 4, 15, 3).unwrap(), "Filter type {} failed.", filter_type);
+        }
+        assert!(unfilter(&data, 4, 16, 3).is_err(), "Truncated data should fail.");
+        assert!(unfilter(&data, usize::MAX / 2, 15, 3).is_err(), "Oversized image should fail.");
+    }
+
+    #[test]
+    fn test_parse_apng() {
+        let content = apng_content();
+        assert!(is_animated_png(&content));
+        let chunks = read_chunks(&content).unwrap();
+        let (frames, bytes_per_pixel) = parse_apng(&chunks).unwrap();
+        assert_eq!(4, bytes_per_pixel);
+        assert_eq!(2, frames.len());
+        assert_eq!((4, 3, vec![3]), (frames[0].width, frames[0].height, frames[0].chunk_indexes.clone()));
+        assert_eq!((2, 2, vec![5, 6]), (frames[1].width, frames[1].height, frames[1].chunk_indexes.clone()));
+        assert_eq!(200 - 3, frames[1].pixels[1]);
+        // Frame control chunks must be complete and their frames must fit in image.
+        let mut truncated_chunks = read_chunks(&content).unwrap();
+        truncated_chunks[4].data.truncate(20);
+        let error = parse_apng(&truncated_chunks).err().expect("Truncated fcTL should fail.");
+        assert!(error.to_string().contains("frame control chunk is truncated"));
+        let mut oversized_chunks = read_chunks(&content).unwrap();
+        oversized_chunks[4].data = frame_control(1, 5, 2);
+        assert!(parse_apng(&oversized_chunks).is_err(), "Frames bigger than image should fail.");
+        let mut displaced_chunks = read_chunks(&content).unwrap();
+        displaced_chunks[4].data[12..16].copy_from_slice(&u32::MAX.to_be_bytes());
+        assert!(parse_apng(&displaced_chunks).is_err(), "Frames out of image should fail.");
+    }
+
+    #[test]
+    fn test_write_samples_keeps_animation() {
+        let test_env = TestEnvironment::new();
+        let apng_path = test_env.path().join("test.png");
+        fs::write(&apng_path, apng_content()).expect("Error writing test APNG");
+        assert!(supported_apng(apng_path.to_str().unwrap()));
+        {
+            let mut apng = ContainerApng::new(apng_path.to_str().unwrap()).expect("Error opening test APNG");
+            assert_eq!(2, apng.frames.len());
+            assert_eq!(4 * 3 + 2 * 2, apng.sample_count());
+            assert_eq!(bytes_to_u24(&[200, 197, 194]) as u64, apng.read_sample(12));
+            apng.write_sample(13, 0x01_02_03);
+        }
+        let modified_content = fs::read(&apng_path).expect("Error reading modified APNG");
+        let chunks = read_chunks(&modified_content).unwrap();
+        let kinds: Vec<&[u8]> = chunks.iter().map(|chunk| &chunk.kind[..]).collect();
+        assert_eq!(vec![&b"IHDR"[..], b"acTL", b"fcTL", b"IDAT", b"fcTL", b"fdAT", b"IEND"], kinds);
+        // Sequence numbers are consecutive again and frame timing is kept.
+        assert_eq!(2, read_u32(&chunks[5].data, 0));
+        assert_eq!(frame_control(1, 2, 2), chunks[4].data);
+        let (frames, _) = parse_apng(&chunks).unwrap();
+        // Alpha channel is kept.
+        assert_eq!(&[1, 2, 3, 200 - 7 * 3], &frames[1].pixels[4..8]);
+        // Without animation chunks, default image is a valid static PNG image.
+        let static_content: Vec<u8> = PNG_SIGNATURE.iter().copied()
+            .chain(chunks.iter()
+                .filter(|chunk| [&b"IHDR"[..], b"IDAT", b"IEND"].contains(&&chunk.kind[..]))
+                .flat_map(|chunk| chunk_bytes(&chunk.kind, &chunk.data)))
+            .collect();
+        assert!(image::load_from_memory(&static_content).is_ok());
+    }
+}
@@ -1,5 +1,9 @@
-use clap::{Arg, App};
-use crate::configuration::Configuration;
+use std::ffi::OsString;
+
+use clap::{Arg, App, AppSettings, ArgMatches, SubCommand};
+use crate::configuration::{Configuration, Operation};
+use crate::stegimage::{BitOrder, DumpOptions, Traversal};
+use crate::visualize::Channel;
 
 fn get_version()-> String {
     format!("{}.{}.{}{}",
@@ -9,34 +13,502 @@ fn get_version()-> String {
             option_env!("CARGO_PKG_VERSION_PRE").unwrap_or(""))
 }
 
+/// Create a positional argument for a subcommand.
+///
+/// # Parameters:
+/// * name: Argument name to get its value from matches.
+/// * value_name: Name shown at usage messages.
+/// * help: Argument help message.
+/// * index: Argument position.
+fn positional_arg<'a, 'b>(name: &'a str, value_name: &'b str, help: &'b str, index: u64)-> Arg<'a, 'b> {
+    Arg::with_name(name)
+        .help(help)
+        .required(true)
+        .value_name(value_name)
+        .index(index)
+        .takes_value(true)
+}
+
+/// Create flag to get reports as JSON.
+fn json_arg<'a, 'b>()-> Arg<'a, 'b> {
+    Arg::with_name("json")
+        .help("Prints report as JSON.")
+        .long("json")
+}
+
+/// Check given value is a valid bits per pixel level.
+fn validate_bits_per_pixel(value: String)-> Result<(), String> {
+    match value.parse::<u8>() {
+        Ok(bits) if (1..=24).contains(&bits) => Ok(()),
+        _ => Err(String::from("Bits per pixel must be a number between 1 and 24.")),
+    }
+}
+
+/// Check given value is a valid wipe depth.
+fn validate_wipe_depth(value: String)-> Result<(), String> {
+    match value.parse::<u8>() {
+        Ok(depth) if (1..=8).contains(&depth) => Ok(()),
+        _ => Err(String::from("Depth must be a number between 1 and 8.")),
+    }
+}
+
+/// Create option to set which file output is written into.
+///
+/// # Parameters:
+/// * help: Argument help message.
+fn output_arg<'a, 'b>(help: &'b str)-> Arg<'a, 'b> {
+    Arg::with_name("output_file")
+        .help(help)
+        .short("o")
+        .long("output")
+        .value_name("OUTPUT_FILE")
+        .takes_value(true)
+        .required(true)
+}
+
+/// Create option to set how data is hidden into text host files.
+fn text_encoding_arg<'a, 'b>()-> Arg<'a, 'b> {
+    Arg::with_name("text_encoding")
+        .help("How data is hidden when host file is a text file: as trailing whitespace at every \
+               line or as zero width characters between words.")
+        .long("text-encoding")
+        .value_name("ENCODING")
+        .takes_value(true)
+        .possible_values(&["whitespace", "zero-width"])
+        .default_value("zero-width")
+}
+
+/// Check given value is a valid thread count.
+fn validate_threads(value: String)-> Result<(), String> {
+    match value.parse::<usize>() {
+        Ok(_) => Ok(()),
+        _ => Err(String::from("Threads must be a number, or 0 to use one thread per CPU core.")),
+    }
+}
+
+/// Create option to set how many threads hide or extract data when host file is an image.
+fn threads_arg<'a, 'b>()-> Arg<'a, 'b> {
+    Arg::with_name("threads")
+        .help("Threads to hide or extract data with when host file is an image. 0 means one \
+               thread per CPU core.")
+        .long("threads")
+        .value_name("THREADS")
+        .takes_value(true)
+        .validator(validate_threads)
+        .default_value("1")
+}
+
+/// Check given value is a valid bit plane index.
+fn validate_bit(value: String)-> Result<(), String> {
+    match value.parse::<u8>() {
+        Ok(bit) if bit <= 7 => Ok(()),
+        _ => Err(String::from("Bit must be a number between 0 and 7.")),
+    }
+}
+
+/// Parse a channel list like "rgb" or "b".
+fn parse_channels(value: &str)-> Result<Vec<Channel>, String> {
+    let channels = value.chars()
+        .map(|channel| channel.to_string().parse::<Channel>())
+        .collect::<Result<Vec<Channel>, String>>()?;
+    if channels.is_empty() {
+        return Err(String::from("At least a channel must be given."));
+    }
+    Ok(channels)
+}
+
+/// Parse a comma separated bit plane list like "0" or "1,0".
+fn parse_bits(value: &str)-> Result<Vec<u8>, String> {
+    value.split(',')
+        .map(|bit| match bit.trim().parse::<u8>() {
+            Ok(bit) if bit <= 7 => Ok(bit),
+            _ => Err(String::from("Bits must be a comma separated list of numbers between 0 and 7.")),
+        })
+        .collect()
+}
+
+/// Check given value is a valid channel list.
+fn validate_channels(value: String)-> Result<(), String> {
+    parse_channels(&value).map(|_| ())
+}
+
+/// Check given value is a valid bit plane list.
+fn validate_bits(value: String)-> Result<(), String> {
+    parse_bits(&value).map(|_| ())
+}
+
 /// Parse console arguments given when launching steganer.
 ///
 /// Parsed arguments are stored in a Configuration struct that is returned.
 pub fn parse_arguments()-> Configuration{
-    let mut configuration =  Configuration::new_default();
+    parse_arguments_from(std::env::args_os())
+}
+
+/// Parse given arguments as if they were console arguments.
+///
+/// First argument is expected to be program name.
+fn parse_arguments_from<I, T>(arguments: I)-> Configuration
+    where
+        I: IntoIterator<Item = T>,
+        T: Into<OsString> + Clone,
+{
     let matches = App::new("steganer")
         .version(get_version().as_str())
         .author("Dante Signal31 <dante.signal31@gmail.com>")
         .about("Hide a file inside another... or recovers it.")
+        .usage("steganer <SUBCOMMAND>")
+        .setting(AppSettings::SubcommandsNegateReqs)
+        .setting(AppSettings::ArgRequiredElseHelp)
+        .setting(AppSettings::VersionlessSubcommands)
+        .after_help("Legacy form \"steganer FILE_HIDDEN HOST_FILE [-x]\" is still accepted but it \
+                     is deprecated. Use hide and extract subcommands instead.")
+        // Legacy positional form. Hidden from help to promote subcommands.
         .arg(Arg::with_name("file_hidden")
             .help("File to hide or to be extracted.")
             .required(true)
+            .hidden(true)
             .value_name("FILE_HIDDEN")
             .index(1)
             .takes_value(true))
         .arg(Arg::with_name("host_file")
             .help("Container file for hidden file.")
             .required(true)
+            .hidden(true)
             .value_name("HOST_FILE")
             .index(2)
             .takes_value(true))
         .arg(Arg::with_name("extraction_mode")
             .help("Extracts hidden file (steganer defaults to hide file)")
+            .hidden(true)
             .short("x")
             .long("extract"))
-        .get_matches();
-    configuration.hidden_file = String::from(matches.value_of("file_hidden").unwrap());
-    configuration.host_file = String::from(matches.value_of("host_file").unwrap());
-    configuration.extract =if matches.is_present("extraction_mode") {true} else {false};
+        .subcommand(SubCommand::with_name("hide")
+            .about("Hides a file inside a host file.")
+            .arg(positional_arg("file_to_hide", "FILE_TO_HIDE", "File to hide.", 1))
+            .arg(positional_arg("host_file", "HOST_FILE", "Container file for hidden file.", 2))
+            .arg(Arg::with_name("report")
+                .help("Prints a report about how much host file was degraded after hiding.")
+                .long("report"))
+            .arg(json_arg().requires("report"))
+            .arg(output_arg("Hides into a copy of host file stored here, leaving host file untouched.")
+                .required(false))
+            .arg(text_encoding_arg())
+            .arg(threads_arg()))
+        .subcommand(SubCommand::with_name("extract")
+            .about("Extracts a file hidden inside a host file.")
+            .arg(positional_arg("host_file", "HOST_FILE", "Container file with hidden file.", 1))
+            .arg(positional_arg("output_file", "OUTPUT_FILE", "File to store extracted data.", 2))
+            .arg(text_encoding_arg())
+            .arg(threads_arg()))
+        .subcommand(SubCommand::with_name("capacity")
+            .about("Reports how many bytes can be hidden inside a host file.")
+            .arg(positional_arg("host_file", "HOST_FILE", "Host file to analyze.", 1))
+            .arg(Arg::with_name("bits_per_pixel")
+                .help("Reports only capacity for this amount of bits per pixel.")
+                .long("bits-per-pixel")
+                .value_name("BITS")
+                .takes_value(true)
+                .validator(validate_bits_per_pixel))
+            .arg(json_arg()))
+        .subcommand(SubCommand::with_name("info")
+            .about("Inspects a host file looking for hidden data. Host file is not modified.")
+            .alias("probe")
+            .after_help("Exit codes: 0 payload found, 1 error, 2 no payload, 3 encrypted payload.")
+            .arg(positional_arg("host_file", "HOST_FILE", "Host file to inspect.", 1))
+            .arg(json_arg()))
+        .subcommand(SubCommand::with_name("verify")
+            .about("Checks a host file has a given file hidden inside. Host file is not modified.")
+            .after_help("Exit codes: 0 hidden data matches file, 1 error, 2 hidden data does not match.")
+            .arg(positional_arg("hidden_file", "HIDDEN_FILE", "File expected to be hidden.", 1))
+            .arg(positional_arg("host_file", "HOST_FILE", "Container file to check.", 2)))
+        .subcommand(SubCommand::with_name("analyze")
+            .about("Runs steganalysis detectors over a host file looking for LSB hidden data. \
+                    Host file is not modified.")
+            .arg(positional_arg("host_file", "HOST_FILE", "Host file to analyze.", 1))
+            .arg(json_arg()))
+        .subcommand(SubCommand::with_name("planes")
+            .about("Renders a single bit plane of a host file as a black and white image.")
+            .arg(positional_arg("host_file", "HOST_FILE", "Host file to get bit plane from.", 1))
+            .arg(Arg::with_name("channel")
+                .help("Channel to get bit plane from.")
+                .long("channel")
+                .value_name("CHANNEL")
+                .takes_value(true)
+                .possible_values(&["r", "g", "b"])
+                .default_value("b"))
+            .arg(Arg::with_name("bit")
+                .help("Bit plane to render, 0 is least significant bit.")
+                .long("bit")
+                .value_name("BIT")
+                .takes_value(true)
+                .default_value("0")
+                .validator(validate_bit))
+            .arg(output_arg("Image file to render into. Its format is guessed from its extension.")))
+        .subcommand(SubCommand::with_name("diff")
+            .about("Renders a heatmap of pixels modified between a cover file and a host file.")
+            .arg(positional_arg("cover_file", "COVER_FILE", "Original image.", 1))
+            .arg(positional_arg("host_file", "HOST_FILE", "Image with hidden data.", 2))
+            .arg(output_arg("Image file to render into. Its format is guessed from its extension.")))
+        .subcommand(SubCommand::with_name("dump")
+            .about("Dumps raw bits of a host file, ignoring steganer header. Host file is not modified.")
+            .arg(positional_arg("host_file", "HOST_FILE", "Host file to dump bits from.", 1))
+            .arg(Arg::with_name("channels")
+                .help("Channels to read at every pixel, in order. For instance: rgb, bgr or b.")
+                .long("channels")
+                .value_name("CHANNELS")
+                .takes_value(true)
+                .default_value("rgb")
+                .validator(validate_channels))
+            .arg(Arg::with_name("bits")
+                .help("Comma separated bit planes to read at every channel, in order. 0 is least \
+                       significant bit.")
+                .long("bits")
+                .value_name("BITS")
+                .takes_value(true)
+                .default_value("0")
+                .validator(validate_bits))
+            .arg(Arg::with_name("bit_order")
+                .help("Whether first extracted bit is the most or the least significant bit of \
+                       its byte.")
+                .long("bit-order")
+                .value_name("ORDER")
+                .takes_value(true)
+                .possible_values(&["msb", "lsb"])
+                .default_value("msb"))
+            .arg(Arg::with_name("traversal")
+                .help("Whether pixels are visited row by row or column by column.")
+                .long("traversal")
+                .value_name("TRAVERSAL")
+                .takes_value(true)
+                .possible_values(&["row", "column"])
+                .default_value("row"))
+            .arg(output_arg("File to store dumped bytes.")))
+        .subcommand(SubCommand::with_name("scan")
+            .about("Tries every usual combination of channels, bit planes and orders looking for \
+                    payloads hidden by any LSB tool. Host file is not modified.")
+            .arg(positional_arg("host_file", "HOST_FILE", "Host file to scan.", 1))
+            .arg(json_arg()))
+        .subcommand(SubCommand::with_name("wipe")
+            .about("Destroys any data hidden at least significant bits of a host file.")
+            .arg(positional_arg("host_file", "HOST_FILE", "Host file to wipe. It is overwritten.", 1))
+            .arg(Arg::with_name("depth")
+                .help("Least significant bit planes to wipe at every channel. Planes used by a \
+                       steganer payload are always wiped.")
+                .long("depth")
+                .value_name("PLANES")
+                .takes_value(true)
+                .default_value("1")
+                .validator(validate_wipe_depth)))
+        .get_matches_from(arguments);
+    configuration_from_matches(&matches)
+}
+
+/// Build a Configuration from parsed arguments.
+fn configuration_from_matches(matches: &ArgMatches)-> Configuration {
+    let mut configuration =  Configuration::new_default();
+    match matches.subcommand() {
+        ("hide", Some(hide_matches))=> {
+            configuration.hidden_file = String::from(hide_matches.value_of("file_to_hide").unwrap());
+            configuration.host_file = String::from(hide_matches.value_of("host_file").unwrap());
+            configuration.operation = Operation::Hide;
+            configuration.report = hide_matches.is_present("report");
+            configuration.json = hide_matches.is_present("json");
+            if let Some(output_file) = hide_matches.value_of("output_file") {
+                configuration.output_file = String::from(output_file);
+            }
+            // Possible values have already been checked by clap.
+            configuration.text_encoding = hide_matches.value_of("text_encoding").unwrap().parse().unwrap();
+            // Validator has already checked this is a valid usize.
+            configuration.threads = hide_matches.value_of("threads").unwrap().parse().unwrap();
+        },
+        ("extract", Some(extract_matches))=> {
+            configuration.hidden_file = String::from(extract_matches.value_of("output_file").unwrap());
+            configuration.host_file = String::from(extract_matches.value_of("host_file").unwrap());
+            configuration.operation = Operation::Extract;
+            configuration.text_encoding = extract_matches.value_of("text_encoding").unwrap().parse().unwrap();
+            configuration.threads = extract_matches.value_of("threads").unwrap().parse().unwrap();
+        },
+        ("capacity", Some(capacity_matches))=> {
+            configuration.host_file = String::from(capacity_matches.value_of("host_file").unwrap());
+            configuration.operation = Operation::Capacity;
+            // Validator has already checked this is a valid u8.
+            configuration.bits_per_pixel = capacity_matches.value_of("bits_per_pixel")
+                .map(|bits| bits.parse::<u8>().unwrap());
+            configuration.json = capacity_matches.is_present("json");
+        },
+        ("info", Some(info_matches))=> {
+            configuration.host_file = String::from(info_matches.value_of("host_file").unwrap());
+            configuration.operation = Operation::Info;
+            configuration.json = info_matches.is_present("json");
+        },
+        ("verify", Some(verify_matches))=> {
+            configuration.hidden_file = String::from(verify_matches.value_of("hidden_file").unwrap());
+            configuration.host_file = String::from(verify_matches.value_of("host_file").unwrap());
+            configuration.operation = Operation::Verify;
+        },
+        ("analyze", Some(analyze_matches))=> {
+            configuration.host_file = String::from(analyze_matches.value_of("host_file").unwrap());
+            configuration.operation = Operation::Analyze;
+            configuration.json = analyze_matches.is_present("json");
+        },
+        ("planes", Some(planes_matches))=> {
+            configuration.host_file = String::from(planes_matches.value_of("host_file").unwrap());
+            configuration.output_file = String::from(planes_matches.value_of("output_file").unwrap());
+            configuration.operation = Operation::Planes;
+            // Possible values and validator have already checked these are valid.
+            configuration.channel = planes_matches.value_of("channel").unwrap().parse().unwrap();
+            configuration.bit = planes_matches.value_of("bit").unwrap().parse::<u8>().unwrap();
+        },
+        ("diff", Some(diff_matches))=> {
+            configuration.cover_file = String::from(diff_matches.value_of("cover_file").unwrap());
+            configuration.host_file = String::from(diff_matches.value_of("host_file").unwrap());
+            configuration.output_file = String::from(diff_matches.value_of("output_file").unwrap());
+            configuration.operation = Operation::Diff;
+        },
+        ("dump", Some(dump_matches))=> {
+            configuration.host_file = String::from(dump_matches.value_of("host_file").unwrap());
+            configuration.output_file = String::from(dump_matches.value_of("output_file").unwrap());
+            configuration.operation = Operation::Dump;
+            // Validators and possible values have already checked these are valid.
+            configuration.dump_options = DumpOptions {
+                channels: parse_channels(dump_matches.value_of("channels").unwrap()).unwrap(),
+                bits: parse_bits(dump_matches.value_of("bits").unwrap()).unwrap(),
+                bit_order: if dump_matches.value_of("bit_order") == Some("lsb") {BitOrder::LsbFirst}
+                           else {BitOrder::MsbFirst},
+                traversal: if dump_matches.value_of("traversal") == Some("column") {Traversal::ColumnMajor}
+                           else {Traversal::RowMajor},
+            };
+        },
+        ("scan", Some(scan_matches))=> {
+            configuration.host_file = String::from(scan_matches.value_of("host_file").unwrap());
+            configuration.operation = Operation::Scan;
+            configuration.json = scan_matches.is_present("json");
+        },
+        ("wipe", Some(wipe_matches))=> {
+            configuration.host_file = String::from(wipe_matches.value_of("host_file").unwrap());
+            configuration.operation = Operation::Wipe;
+            // Validator has already checked this is a valid u8.
+            configuration.wipe_depth = wipe_matches.value_of("depth").unwrap().parse::<u8>().unwrap();
+        },
+        _=> {
+            eprintln!("Warning: \"steganer FILE_HIDDEN HOST_FILE [-x]\" form is deprecated. \
+                       Use \"steganer hide\" or \"steganer extract\" instead.");
+            configuration.hidden_file = String::from(matches.value_of("file_hidden").unwrap());
+            configuration.host_file = String::from(matches.value_of("host_file").unwrap());
+            configuration.operation = if matches.is_present("extraction_mode") {Operation::Extract}
+                                      else {Operation::Hide};
+        },
+    }
     configuration
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::textcarrier::TextEncoding;
+
+    #[test]
+    fn test_hide_subcommand() {
+        let configuration = parse_arguments_from(vec!["steganer", "hide", "secret.txt", "host.png"]);
+        assert_eq!(Operation::Hide, configuration.operation);
+        assert_eq!("secret.txt", configuration.hidden_file);
+        assert_eq!("host.png", configuration.host_file);
+        assert!(!configuration.report);
+        let reporting = parse_arguments_from(vec!["steganer", "hide", "secret.txt", "host.png",
+                                                  "--report", "--json"]);
+        assert!(reporting.report);
+        assert!(reporting.json);
+        assert!(reporting.output_file.is_empty());
+        let copying = parse_arguments_from(vec!["steganer", "hide", "secret.zip", "song.wav",
+                                                "-o", "out.wav"]);
+        assert_eq!("song.wav", copying.host_file);
+        assert_eq!("out.wav", copying.output_file);
+        assert_eq!(TextEncoding::ZeroWidth, copying.text_encoding);
+        let text = parse_arguments_from(vec!["steganer", "hide", "secret.txt", "notes.txt",
+                                             "--text-encoding", "whitespace"]);
+        assert_eq!(TextEncoding::Whitespace, text.text_encoding);
+        assert_eq!(1, text.threads);
+        let threaded = parse_arguments_from(vec!["steganer", "hide", "secret.txt", "host.png",
+                                                 "--threads", "0"]);
+        assert_eq!(0, threaded.threads);
+    }
+
+    #[test]
+    fn test_extract_subcommand() {
+        let configuration = parse_arguments_from(vec!["steganer", "extract", "host.png", "recovered.txt"]);
+        assert_eq!(Operation::Extract, configuration.operation);
+        assert_eq!("recovered.txt", configuration.hidden_file);
+        assert_eq!("host.png", configuration.host_file);
+        let threaded = parse_arguments_from(vec!["steganer", "extract", "host.png", "recovered.txt",
+                                                 "--threads", "4"]);
+        assert_eq!(4, threaded.threads);
+    }
+
+    #[test]
+    fn test_capacity_subcommand() {
+        let configuration = parse_arguments_from(vec!["steganer", "capacity", "host.png",
+                                                      "--bits-per-pixel", "3", "--json"]);
+        assert_eq!(Operation::Capacity, configuration.operation);
+        assert_eq!("host.png", configuration.host_file);
+        assert_eq!(Some(3), configuration.bits_per_pixel);
+        assert!(configuration.json);
+    }
+
+    #[test]
+    fn test_planes_subcommand() {
+        let configuration = parse_arguments_from(vec!["steganer", "planes", "host.png", "--channel", "r",
+                                                      "--bit", "2", "-o", "plane.png"]);
+        assert_eq!(Operation::Planes, configuration.operation);
+        assert_eq!("host.png", configuration.host_file);
+        assert_eq!("plane.png", configuration.output_file);
+        assert_eq!(Channel::Red, configuration.channel);
+        assert_eq!(2, configuration.bit);
+    }
+
+    #[test]
+    fn test_diff_subcommand() {
+        let configuration = parse_arguments_from(vec!["steganer", "diff", "cover.png", "stego.png",
+                                                      "-o", "heat.png"]);
+        assert_eq!(Operation::Diff, configuration.operation);
+        assert_eq!("cover.png", configuration.cover_file);
+        assert_eq!("stego.png", configuration.host_file);
+        assert_eq!("heat.png", configuration.output_file);
+    }
+
+    #[test]
+    fn test_dump_subcommand() {
+        let default_dump = parse_arguments_from(vec!["steganer", "dump", "host.png", "-o", "dump.bin"]);
+        assert_eq!(Operation::Dump, default_dump.operation);
+        assert_eq!("host.png", default_dump.host_file);
+        assert_eq!("dump.bin", default_dump.output_file);
+        assert_eq!(DumpOptions::default(), default_dump.dump_options);
+        let custom_dump = parse_arguments_from(vec!["steganer", "dump", "host.png", "-o", "dump.bin",
+                                                    "--channels", "bg", "--bits", "1,0",
+                                                    "--bit-order", "lsb", "--traversal", "column"]);
+        assert_eq!(DumpOptions{channels: vec![Channel::Blue, Channel::Green], bits: vec![1, 0],
+                               bit_order: BitOrder::LsbFirst, traversal: Traversal::ColumnMajor},
+                   custom_dump.dump_options);
+    }
+
+    #[test]
+    fn test_wipe_subcommand() {
+        let default_depth = parse_arguments_from(vec!["steganer", "wipe", "host.png"]);
+        assert_eq!(Operation::Wipe, default_depth.operation);
+        assert_eq!("host.png", default_depth.host_file);
+        assert_eq!(1, default_depth.wipe_depth);
+        let custom_depth = parse_arguments_from(vec!["steganer", "wipe", "host.png", "--depth", "4"]);
+        assert_eq!(4, custom_depth.wipe_depth);
+    }
+
+    #[test]
+    fn test_legacy_form() {
+        let hiding = parse_arguments_from(vec!["steganer", "secret.txt", "host.png"]);
+        assert_eq!(Operation::Hide, hiding.operation);
+        assert_eq!("secret.txt", hiding.hidden_file);
+        assert_eq!("host.png", hiding.host_file);
+        let extraction = parse_arguments_from(vec!["steganer", "recovered.txt", "host.png", "-x"]);
+        assert_eq!(Operation::Extract, extraction.operation);
+        assert_eq!("recovered.txt", extraction.hidden_file);
+        assert_eq!("host.png", extraction.host_file);
+    }
+}
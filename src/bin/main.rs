@@ -1,22 +1,23 @@
 extern crate steganer;
 
 use steganer::argparser::parse_arguments;
-use steganer::_run;
+use steganer::_run_with_exit_code;
 
 fn main() {
     let config = parse_arguments();
-    if let Err(ref errors) = _run(&config) {
-        eprintln!("Error found. Execution aborted.");
-        eprintln!("Error details: ");
-        errors.iter()
-            .enumerate()
-            .for_each(|(index, error)| eprintln!("\t {} --> {}", index, error));
-        if let Some(backtrace) = errors.backtrace(){
-            eprintln!("{:?}", backtrace);
-        }
-        std::process::exit(1);
-    } else {
-        std::process::exit(0)
+    match _run_with_exit_code(&config) {
+        Ok(exit_code)=> std::process::exit(exit_code),
+        Err(ref errors)=> {
+            eprintln!("Error found. Execution aborted.");
+            eprintln!("Error details: ");
+            errors.iter()
+                .enumerate()
+                .for_each(|(index, error)| eprintln!("\t {} --> {}", index, error));
+            if let Some(backtrace) = errors.backtrace(){
+                eprintln!("{:?}", backtrace);
+            }
+            std::process::exit(1);
+        },
     }
 }
 
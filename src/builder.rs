@@ -0,0 +1,257 @@
+/// Module with a builder style entry point to steganer library.
+///
+/// Steganer gathers every option to hide or extract data in a single place, instead of choosing
+/// among hide_into_*() and extract_from_*() functions. Host file kind is detected from its
+/// extension, just like steganer command does.
+///
+/// Steganer format only stores hidden data size along with hidden data, so encryption,
+/// compression and error correction codes are not available. Options to ask for them are
+/// accepted by builder, but *build()* fails if they are enabled, so nobody assumes their data is
+/// protected when it is not.
+///
+/// # Usage example:
+/// ```ignore
+/// let steganer = Steganer::builder()
+///     .carrier("lenna.png")
+///     .output("lenna_with_secret.png")
+///     .bits_per_pixel(3)
+///     .build()?;
+/// steganer.hide("secret.txt")?;
+/// ```
+use crate::*;
+
+/// Algorithms to hide data into host files.
+#[derive(Debug, PartialEq, Copy, Clone)]
+pub enum Algorithm {
+    /// Overwrite least significant bits of host samples: pixels, audio samples, text slots or
+    /// palette indexes.
+    Lsb,
+}
+
+/// Steganer operations with a given set of options.
+///
+/// Use *Steganer::builder()* to create one.
+#[derive(Debug, Clone)]
+pub struct Steganer {
+    configuration: Configuration,
+    algorithm: Algorithm,
+}
+
+impl Steganer {
+    /// Start setting options for a new Steganer.
+    pub fn builder()-> SteganerBuilder {
+        SteganerBuilder::default()
+    }
+
+    /// Get options this Steganer runs with.
+    pub fn configuration(&self)-> &Configuration {
+        &self.configuration
+    }
+
+    /// Get algorithm this Steganer hides data with.
+    pub fn algorithm(&self)-> Algorithm {
+        self.algorithm
+    }
+
+    /// Hide a file into carrier, or into output file if one was set.
+    ///
+    /// # Parameters:
+    /// * file_to_hide: Absolute path to file to hide.
+    pub fn hide(&self, file_to_hide: &str)-> Result<()> {
+        self.run(file_to_hide, Operation::Hide)
+    }
+
+    /// Extract a file hidden into carrier.
+    ///
+    /// # Parameters:
+    /// * hidden_file: Absolute path to file to store extracted data.
+    pub fn extract(&self, hidden_file: &str)-> Result<()> {
+        self.run(hidden_file, Operation::Extract)
+    }
+
+    /// Get how many bytes can be hidden into carrier.
+    ///
+    /// # Returns:
+    /// * Capacity report. If bits per pixel were set, only that level is reported.
+    /// * An error if carrier is not a static image, as capacity reports are only available for
+    ///   them. Use text_capacity() for text carriers.
+    pub fn capacity(&self)-> Result<Capacity> {
+        if !is_image_host(&self.configuration.host_file) {
+            bail!("Capacity reports are only available for host images, but carrier is {}.",
+                  self.configuration.host_file);
+        }
+        let options = CapacityOptions{bits_per_pixel: self.configuration.bits_per_pixel};
+        image_capacity(&self.configuration.host_file, &options)
+    }
+
+    /// Run an operation over carrier with this Steganer options.
+    fn run(&self, hidden_file: &str, operation: Operation)-> Result<()> {
+        let mut configuration = self.configuration.clone();
+        configuration.hidden_file = hidden_file.to_owned();
+        configuration.operation = operation;
+        _run(&configuration)
+    }
+}
+
+/// Builder to set Steganer options.
+///
+/// Every option has a default value but carrier, that must always be set.
+#[derive(Debug, Default)]
+pub struct SteganerBuilder {
+    carrier: String,
+    output: String,
+    key: Option<Vec<u8>>,
+    passphrase: Option<String>,
+    bits_per_pixel: Option<u8>,
+    algorithm: Option<Algorithm>,
+    compression: bool,
+    ecc: bool,
+    threads: Option<usize>,
+    text_encoding: Option<TextEncoding>,
+}
+
+impl SteganerBuilder {
+    /// Set host file to hide data into or extract data from.
+    pub fn carrier(mut self, host_file: &str)-> Self {
+        self.carrier = host_file.to_owned();
+        self
+    }
+
+    /// Set file to hide data into instead of carrier, that is left untouched. Carrier is copied
+    /// there first, so it must have the same extension as carrier. Only used when hiding.
+    pub fn output(mut self, output_file: &str)-> Self {
+        self.output = output_file.to_owned();
+        self
+    }
+
+    /// Set a key to encrypt hidden data with. Not supported yet, so *build()* fails if set.
+    pub fn key(mut self, key: &[u8])-> Self {
+        self.key = Some(key.to_vec());
+        self
+    }
+
+    /// Set a passphrase to encrypt hidden data with. Not supported yet, so *build()* fails if
+    /// set.
+    pub fn passphrase(mut self, passphrase: &str)-> Self {
+        self.passphrase = Some(passphrase.to_owned());
+        self
+    }
+
+    /// Set bits per pixel level to use with image carriers.
+    ///
+    /// Steganer always uses as few bits per pixel as hidden data needs, so this level is a
+    /// maximum: hiding fails with a PayloadTooLarge error if data would need more. Capacity
+    /// only reports this level. It must be between 1 and 24 and carrier must be a static image.
+    pub fn bits_per_pixel(mut self, bits_per_pixel: u8)-> Self {
+        self.bits_per_pixel = Some(bits_per_pixel);
+        self
+    }
+
+    /// Set algorithm to hide data with. Default is Algorithm::Lsb, the only one available.
+    pub fn algorithm(mut self, algorithm: Algorithm)-> Self {
+        self.algorithm = Some(algorithm);
+        self
+    }
+
+    /// Set if hidden data should be compressed. Not supported yet, so *build()* fails if
+    /// enabled.
+    pub fn compression(mut self, enabled: bool)-> Self {
+        self.compression = enabled;
+        self
+    }
+
+    /// Set if error correction codes should be added to hidden data. Not supported yet, so
+    /// *build()* fails if enabled.
+    pub fn ecc(mut self, enabled: bool)-> Self {
+        self.ecc = enabled;
+        self
+    }
+
+    /// Set threads to hide or extract with when carrier is an image. 0 means one thread per CPU
+    /// core. Default is 1.
+    pub fn threads(mut self, threads: usize)-> Self {
+        self.threads = Some(threads);
+        self
+    }
+
+    /// Set how data is hidden into text carriers. Default is TextEncoding::ZeroWidth.
+    pub fn text_encoding(mut self, encoding: TextEncoding)-> Self {
+        self.text_encoding = Some(encoding);
+        self
+    }
+
+    /// Check options and create a Steganer with them.
+    ///
+    /// # Returns:
+    /// * Steganer ready to hide, extract or report capacity.
+    /// * An error if carrier was not set, bits per pixel are out of range or an unsupported
+    ///   feature was enabled.
+    pub fn build(self)-> Result<Steganer> {
+        if self.carrier.is_empty() {
+            bail!("A carrier file must be set to build a Steganer.");
+        }
+        if self.key.is_some() || self.passphrase.is_some() {
+            bail!("Encryption is not supported, as steganer format has no room for it. \
+                   Encrypt data before hiding it.");
+        }
+        if self.compression {
+            bail!("Compression is not supported, as steganer format has no room for it. \
+                   Compress data before hiding it.");
+        }
+        if self.ecc {
+            bail!("Error correction codes are not supported, as steganer format has no room \
+                   for them.");
+        }
+        if let Some(bits) = self.bits_per_pixel {
+            if !(1..=24).contains(&bits) {
+                bail!("Bits per pixel must be between 1 and 24, but {} was requested.", bits);
+            }
+        }
+        let mut configuration = Configuration::new_default();
+        configuration.host_file = self.carrier;
+        configuration.output_file = self.output;
+        configuration.bits_per_pixel = self.bits_per_pixel;
+        if let Some(threads) = self.threads {
+            configuration.threads = threads;
+        }
+        if let Some(encoding) = self.text_encoding {
+            configuration.text_encoding = encoding;
+        }
+        Ok(Steganer{configuration, algorithm: self.algorithm.unwrap_or(Algorithm::Lsb)})
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_build_configuration() {
+        let steganer = Steganer::builder()
+            .carrier("host.png")
+            .output("output.png")
+            .bits_per_pixel(3)
+            .algorithm(Algorithm::Lsb)
+            .threads(4)
+            .build()
+            .unwrap();
+        assert_eq!(Algorithm::Lsb, steganer.algorithm());
+        let configuration = steganer.configuration();
+        assert_eq!("host.png", configuration.host_file);
+        assert_eq!("output.png", configuration.output_file);
+        assert_eq!(Some(3), configuration.bits_per_pixel);
+        assert_eq!(4, configuration.threads);
+        assert_eq!(TextEncoding::ZeroWidth, configuration.text_encoding);
+    }
+
+    #[test]
+    fn test_build_errors() {
+        assert!(Steganer::builder().build().is_err());
+        assert!(Steganer::builder().carrier("host.png").key(b"secret").build().is_err());
+        assert!(Steganer::builder().carrier("host.png").passphrase("secret").build().is_err());
+        assert!(Steganer::builder().carrier("host.png").compression(true).build().is_err());
+        assert!(Steganer::builder().carrier("host.png").ecc(true).build().is_err());
+        assert!(Steganer::builder().carrier("host.png").bits_per_pixel(25).build().is_err());
+        assert!(Steganer::builder().carrier("host.png").compression(false).ecc(false).build().is_ok());
+    }
+}
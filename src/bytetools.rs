@@ -1,7 +1,10 @@
 /// Module to perform byte operations.
+#[cfg(test)]
 use std::mem::size_of;
 use num::Integer;
-use std::ops::{BitAnd, Shl, Shr, BitOr, Not};
+use std::ops::{Shl, BitOr, Not};
+#[cfg(test)]
+use std::ops::{BitAnd, Shr};
 use std::fmt::Debug;
 
 /// Convert 3 bytes to a 24 bits long integer.
@@ -86,6 +89,8 @@ pub fn mask<T>(length: u8, inverted: bool)-> T
 /// let bits_u32 = get_bits(INT, 24,2);
 /// assert_eq!(bits_u32, 0b_11u32);
 /// ```
+// Only tests need to extract bits this way since fileio packs bits using whole words.
+#[cfg(test)]
 pub fn get_bits<T>(source: T, position: u8, length: u8)-> T
     where
         T: Integer + From<u8> + Shl<usize, Output=T> + Shr<usize, Output=T> +
@@ -98,53 +103,6 @@ pub fn get_bits<T>(source: T, position: u8, length: u8)-> T
 
 }
 
-/// Justify at top left given data.
-///
-/// Leftmost 8 bits are discarded, because although an u32 is entered an u24 is returned
-/// distributed in 3 bytes.
-///
-/// # Parameters:
-/// * data: u32 containing data.
-/// * data_length: How many bits are actually useful data.
-///
-/// # Returns:
-/// * An array of three bytes. Returned u24 leftmost bits are returned in first byte.
-///
-/// # Example:
-/// ```ignore
-/// let data = 0b_11_u32;
-/// let returned_data = left_justify(data, 2);
-/// assert_eq!(0b_1100_0000_u8, returned_data[0]);
-/// ```
-pub fn left_justify(data: u32, data_length: u8)-> [u8; 3]{
-    let left_shift = 24 - data_length; // Remember 8 leftmost bits are discarded.
-    let justified_data = data << left_shift;
-    u24_to_bytes(justified_data)
-}
-
-/// Take data bits and return a vector with its bytes.
-///
-/// # Parameters:
-/// * data: Left justified u32 with data bits.
-/// * length: How many bits from left are actual data.
-///
-/// # Returns:
-/// * Vector with bytes extracted from data.
-pub fn get_bytes(data: u32, length: u8)-> Option<Vec<u8>>{
-    let complete_bytes = length / 8;
-    let bytes_to_return = if length % 8 > 0 {complete_bytes + 1} else {complete_bytes};
-    let mut returned_complete_bytes: Vec<u8> = Vec::new();
-    if bytes_to_return > 0 {
-        for i in 0..bytes_to_return{
-            let extracted_byte = get_bits(data, i*8, 8) as u8;
-            returned_complete_bytes.extend_from_slice(&[extracted_byte]);
-        }
-        Some(returned_complete_bytes)
-    } else {
-        None
-    }
-}
-
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -197,63 +155,4 @@ mod tests {
                    "Bits not properly extracted. Expected {:#b} but we've got {:#b}",
                    0b_11_u8, bits_u8);
     }
-
-    #[test]
-    fn test_left_justify() {
-        let data = 0b_11_u32;
-        let returned_data = left_justify(data, 2);
-        assert_eq!(0b_1100_0000_u8, returned_data[0]);
-    }
-
-    #[test]
-    fn test_get_bytes() {
-        // Not enough bits to fill a byte.
-        let data_incomplete_byte_length = 5_u8;
-        let data_incomplete_byte = (0b_1_0101 as u32) << (32 - data_incomplete_byte_length);
-        if let None = get_bytes(data_incomplete_byte, data_incomplete_byte_length) {
-            assert!(true);
-        }
-        // Enough bits to fill a byte and partially a second.
-        let data_up_to_second_byte_length = 13_u8;
-        let data_up_to_second_byte = (0b_1_0101 as u32) << (32 - data_up_to_second_byte_length);
-        if let Some(bytes) = get_bytes(data_up_to_second_byte, data_up_to_second_byte_length) {
-            assert_eq!(0_u8, bytes[0],
-                       "Recovered first byte was not what we were expecting. Expected {} but got {}.",
-                       0_u8, bytes[0]);
-            assert_eq!(0b_1010_1000_u8, bytes[1],
-                       "Recovered second byte was not what we were expecting. Expected {} but got {}.",
-                       0b_1010_1000_u8, bytes[1]);
-        }
-        // Enough bits to fill two bytes and partially a third.
-        let data_up_to_third_byte_length = 21_u8;
-        let data_up_to_third_byte = (0b_1_0101 as u32) << (32 - data_up_to_third_byte_length);
-        if let Some(bytes) = get_bytes(data_up_to_third_byte, data_up_to_third_byte_length) {
-            assert_eq!(0_u8, bytes[0],
-                       "Recovered first byte was not what we were expecting. Expected {} but got {}.",
-                       0_u8, bytes[0]);
-            assert_eq!(0_u8, bytes[1],
-                       "Recovered second byte was not what we were expecting. Expected {} but got {}.",
-                       0_u8, bytes[1]);
-            assert_eq!(0b_1010_1000_u8, bytes[2],
-                       "Recovered third byte was not what we were expecting. Expected {} but got {}.",
-                       0b_1010_1000_u8, bytes[2]);
-        }
-        // Enough bits to fill three bytes and partially a fourth.
-        let data_up_to_fourth_byte_length = 29_u8;
-        let data_up_to_fourth_byte = (0b_1_0101 as u32) << (32 - data_up_to_fourth_byte_length);
-        if let Some(bytes) = get_bytes(data_up_to_fourth_byte, data_up_to_fourth_byte_length) {
-            assert_eq!(0_u8, bytes[0],
-                       "Recovered first byte was not what we were expecting. Expected {} but got {}.",
-                       0_u8, bytes[0]);
-            assert_eq!(0_u8, bytes[1],
-                       "Recovered second byte was not what we were expecting. Expected {} but got {}.",
-                       0_u8, bytes[1]);
-            assert_eq!(0_u8, bytes[2],
-                       "Recovered third byte was not what we were expecting. Expected {} but got {}.",
-                       0_u8, bytes[2]);
-            assert_eq!(0b_1010_1000_u8, bytes[3],
-                       "Recovered fourth byte was not what we were expecting. Expected {} but got {}.",
-                       0b_1010_1000_u8, bytes[3]);
-        }
-    }
 }
\ No newline at end of file
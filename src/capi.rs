@@ -0,0 +1,362 @@
+/// Module with steganer C API.
+///
+/// Every function returns a STEGANER_* status code. When a function fails, a description of
+/// what went wrong can be retrieved with *steganer_last_error_message()* from the same thread.
+///
+/// Paths are NUL terminated UTF-8 strings. Bytes returned by steganer are allocated by steganer
+/// and must be released with *steganer_free_bytes()*.
+///
+/// C header for this API is at include/steganer.h. It is generated with cbindgen, using
+/// cbindgen.toml config at repository root, so regenerate it when this module changes. CI
+/// regenerates it and fails if it differs from the committed one.
+///
+/// # Usage example:
+/// ```ignore
+/// uint8_t *data = NULL;
+/// size_t length = 0;
+/// if (steganer_extract_bytes_from_image("cover.png", &data, &length) != STEGANER_OK) {
+///     fprintf(stderr, "%s\n", steganer_last_error_message());
+/// }
+/// steganer_free_bytes(data, length);
+/// ```
+use std::cell::RefCell;
+use std::ffi::{CStr, CString};
+use std::os::raw::c_char;
+use std::panic::{catch_unwind, AssertUnwindSafe};
+use std::{ptr, slice};
+
+use crate::*;
+
+/// Function succeeded.
+pub const STEGANER_OK: i32 = 0;
+/// Function failed for any reason not covered by other status codes.
+pub const STEGANER_ERROR: i32 = 1;
+/// A null pointer or a non UTF-8 path was given.
+pub const STEGANER_INVALID_ARGUMENT: i32 = 2;
+/// Data to hide does not fit into host.
+pub const STEGANER_PAYLOAD_TOO_LARGE: i32 = 3;
+/// Host type is not supported.
+pub const STEGANER_UNSUPPORTED_FORMAT: i32 = 4;
+/// Host has no steganer hidden data.
+pub const STEGANER_NO_PAYLOAD: i32 = 5;
+
+thread_local! {
+    /// Description of last error at this thread.
+    static LAST_ERROR: RefCell<Option<CString>> = const { RefCell::new(None) };
+}
+
+/// Why a C API call failed.
+enum CallError {
+    /// Caller gave a wrong argument, with its description.
+    InvalidArgument(String),
+    /// Steganer failed doing its work.
+    Failed(Error),
+}
+
+impl From<Error> for CallError {
+    fn from(error: Error)-> Self {
+        CallError::Failed(error)
+    }
+}
+
+/// Store last error description of this thread.
+fn set_last_error(message: String) {
+    // Error messages never have NUL characters but, just in case, they are dropped.
+    let message = CString::new(message.replace('\0', ""))
+        .expect("NUL characters were removed from error message");
+    LAST_ERROR.with(|last_error| *last_error.borrow_mut() = Some(message));
+}
+
+/// Run a C API call, turning its errors and panics into status codes.
+///
+/// Panics must not unwind across C frames, so they are caught and reported as STEGANER_ERROR.
+fn run<F: FnOnce()-> std::result::Result<(), CallError>>(call: F)-> i32 {
+    match catch_unwind(AssertUnwindSafe(call)) {
+        Ok(Ok(()))=> {
+            LAST_ERROR.with(|last_error| *last_error.borrow_mut() = None);
+            STEGANER_OK
+        },
+        Ok(Err(CallError::InvalidArgument(message)))=> {
+            set_last_error(message);
+            STEGANER_INVALID_ARGUMENT
+        },
+        Ok(Err(CallError::Failed(errors)))=> {
+            let message: Vec<String> = errors.iter()
+                .enumerate()
+                .map(|(index, error)| format!("\t {} --> {}", index, error))
+                .collect();
+            set_last_error(message.join("\n"));
+            match error_kind(&errors) {
+                Some(ErrorKind::PayloadTooLarge(_, _))=> STEGANER_PAYLOAD_TOO_LARGE,
+                Some(ErrorKind::UnsupportedFormat(_, _))=> STEGANER_UNSUPPORTED_FORMAT,
+                Some(ErrorKind::NoPayload)=> STEGANER_NO_PAYLOAD,
+                _=> STEGANER_ERROR,
+            }
+        },
+        Err(_)=> {
+            set_last_error("Unexpected steganer internal error.".to_owned());
+            STEGANER_ERROR
+        },
+    }
+}
+
+/// Read a path given by C caller.
+///
+/// # Safety
+/// Path must be null or a valid NUL terminated string.
+unsafe fn path_argument<'a>(name: &str, path: *const c_char)-> std::result::Result<&'a str, CallError> {
+    if path.is_null() {
+        return Err(CallError::InvalidArgument(format!("{} is a null pointer.", name)));
+    }
+    CStr::from_ptr(path).to_str()
+        .map_err(|_| CallError::InvalidArgument(format!("{} is not valid UTF-8.", name)))
+}
+
+/// Read a memory buffer given by C caller.
+///
+/// # Safety
+/// Buffer must be null or point to at least length readable bytes.
+unsafe fn bytes_argument<'a>(name: &str, data: *const u8, length: usize)
+    -> std::result::Result<&'a [u8], CallError> {
+    if data.is_null() {
+        if length == 0 { return Ok(&[]); }
+        return Err(CallError::InvalidArgument(format!("{} is a null pointer.", name)));
+    }
+    Ok(slice::from_raw_parts(data, length))
+}
+
+/// Give bytes to C caller, who must release them with *steganer_free_bytes()*.
+///
+/// # Safety
+/// Output pointers must be valid for writes.
+unsafe fn return_bytes(bytes: Vec<u8>, data: *mut *mut u8, length: *mut usize) {
+    let bytes = bytes.into_boxed_slice();
+    *length = bytes.len();
+    *data = Box::into_raw(bytes) as *mut u8;
+}
+
+/// Check output pointers given by C caller are not null.
+fn check_outputs<T, U>(data: *mut T, length: *mut U)-> std::result::Result<(), CallError> {
+    if data.is_null() || length.is_null() {
+        return Err(CallError::InvalidArgument("Output pointers must not be null.".to_owned()));
+    }
+    Ok(())
+}
+
+/// Hide a file into an image. C version of *hide_into_image()*.
+///
+/// # Safety
+/// Paths must be valid NUL terminated strings.
+#[no_mangle]
+pub unsafe extern "C" fn steganer_hide_into_image(file_to_hide: *const c_char,
+                                                  host_file: *const c_char)-> i32 {
+    run(|| {
+        let file_to_hide = path_argument("file_to_hide", file_to_hide)?;
+        let host_file = path_argument("host_file", host_file)?;
+        Ok(hide_into_image(file_to_hide, host_file)?)
+    })
+}
+
+/// Extract a file hidden into an image. C version of *extract_from_image()*.
+///
+/// # Safety
+/// Paths must be valid NUL terminated strings.
+#[no_mangle]
+pub unsafe extern "C" fn steganer_extract_from_image(hidden_file: *const c_char,
+                                                     host_file: *const c_char)-> i32 {
+    run(|| {
+        let hidden_file = path_argument("hidden_file", hidden_file)?;
+        let host_file = path_argument("host_file", host_file)?;
+        Ok(extract_from_image(hidden_file, host_file)?)
+    })
+}
+
+/// Hide a memory buffer into an image. C version of *hide_bytes_into_image()*.
+///
+/// # Safety
+/// Data must point to length readable bytes and path must be a valid NUL terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn steganer_hide_bytes_into_image(data: *const u8, length: usize,
+                                                        host_file: *const c_char)-> i32 {
+    run(|| {
+        let data = bytes_argument("data", data, length)?;
+        let host_file = path_argument("host_file", host_file)?;
+        Ok(hide_bytes_into_image(data, host_file)?)
+    })
+}
+
+/// Extract data hidden into an image to memory. C version of *extract_bytes_from_image()*.
+///
+/// Extracted bytes must be released with *steganer_free_bytes()*.
+///
+/// # Safety
+/// Path must be a valid NUL terminated string and output pointers must be valid for writes.
+#[no_mangle]
+pub unsafe extern "C" fn steganer_extract_bytes_from_image(host_file: *const c_char,
+                                                           data: *mut *mut u8,
+                                                           length: *mut usize)-> i32 {
+    run(|| {
+        let host_file = path_argument("host_file", host_file)?;
+        check_outputs(data, length)?;
+        return_bytes(extract_bytes_from_image(host_file)?, data, length);
+        Ok(())
+    })
+}
+
+/// Hide a memory buffer into raw RGB or RGBA pixels, modifying them in place.
+///
+/// Pixels are laid out row by row, as *PixelBuffer* expects.
+///
+/// # Safety
+/// Data must point to length readable bytes and pixels to width * height * channels writable
+/// bytes.
+#[no_mangle]
+pub unsafe extern "C" fn steganer_hide_bytes_into_pixels(data: *const u8, length: usize,
+                                                         pixels: *mut u8, width: u32,
+                                                         height: u32, channels: u8)-> i32 {
+    run(|| {
+        let data = bytes_argument("data", data, length)?;
+        let pixels_length = width as usize * height as usize * channels as usize;
+        if pixels.is_null() {
+            return Err(CallError::InvalidArgument("pixels is a null pointer.".to_owned()));
+        }
+        let pixels = slice::from_raw_parts_mut(pixels, pixels_length);
+        let mut buffer = PixelBuffer::new(pixels.to_vec(), width, height, channels)?;
+        hide_bytes_into_carrier(data, &mut buffer)?;
+        pixels.copy_from_slice(&buffer.into_pixels());
+        Ok(())
+    })
+}
+
+/// Extract data hidden into raw RGB or RGBA pixels to memory.
+///
+/// Extracted bytes must be released with *steganer_free_bytes()*.
+///
+/// # Safety
+/// Pixels must point to width * height * channels readable bytes and output pointers must be
+/// valid for writes.
+#[no_mangle]
+pub unsafe extern "C" fn steganer_extract_bytes_from_pixels(pixels: *const u8, width: u32,
+                                                            height: u32, channels: u8,
+                                                            data: *mut *mut u8,
+                                                            length: *mut usize)-> i32 {
+    run(|| {
+        let pixels_length = width as usize * height as usize * channels as usize;
+        let pixels = bytes_argument("pixels", pixels, pixels_length)?;
+        check_outputs(data, length)?;
+        let buffer = PixelBuffer::new(pixels.to_vec(), width, height, channels)?;
+        return_bytes(extract_bytes_from_carrier(&buffer)?, data, length);
+        Ok(())
+    })
+}
+
+/// Get how many bytes can be hidden into an image. C version of *image_capacity()*.
+///
+/// # Parameters:
+/// * host_file: Path to image file to analyze.
+/// * bits_per_pixel: Bits per pixel level to get capacity for, or 0 for the biggest level.
+/// * max_payload_bytes: Where maximum payload size is written.
+///
+/// # Safety
+/// Path must be a valid NUL terminated string and output pointer must be valid for writes.
+#[no_mangle]
+pub unsafe extern "C" fn steganer_image_capacity(host_file: *const c_char, bits_per_pixel: u8,
+                                                 max_payload_bytes: *mut u64)-> i32 {
+    run(|| {
+        let host_file = path_argument("host_file", host_file)?;
+        check_outputs(max_payload_bytes, max_payload_bytes)?;
+        let bits_per_pixel = if bits_per_pixel == 0 { None } else { Some(bits_per_pixel) };
+        let capacity = image_capacity(host_file, &CapacityOptions{bits_per_pixel})?;
+        *max_payload_bytes = capacity.max_payload_bytes();
+        Ok(())
+    })
+}
+
+/// Get description of last error at calling thread.
+///
+/// # Returns:
+/// * NUL terminated string, owned by steganer and valid until next steganer call at this
+///   thread, or null if last call succeeded.
+#[no_mangle]
+pub extern "C" fn steganer_last_error_message()-> *const c_char {
+    LAST_ERROR.with(|last_error| match last_error.borrow().as_ref() {
+        Some(message)=> message.as_ptr(),
+        None=> ptr::null(),
+    })
+}
+
+/// Release bytes returned by steganer.
+///
+/// # Safety
+/// Data and length must be exactly those returned by steganer, or data must be null.
+#[no_mangle]
+pub unsafe extern "C" fn steganer_free_bytes(data: *mut u8, length: usize) {
+    if !data.is_null() {
+        drop(Box::from_raw(ptr::slice_from_raw_parts_mut(data, length)));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    #[test]
+    fn test_pixels_round_trip() {
+        let (width, height) = (16_u32, 8_u32);
+        let mut pixels = vec![0x80_u8; (width * height * 3) as usize];
+        let secret = b"Meet at noon";
+        unsafe {
+            assert_eq!(STEGANER_OK, steganer_hide_bytes_into_pixels(
+                secret.as_ptr(), secret.len(), pixels.as_mut_ptr(), width, height, 3));
+            assert!(steganer_last_error_message().is_null());
+            let mut data: *mut u8 = ptr::null_mut();
+            let mut length = 0_usize;
+            assert_eq!(STEGANER_OK, steganer_extract_bytes_from_pixels(
+                pixels.as_ptr(), width, height, 3, &mut data, &mut length));
+            assert_eq!(&secret[..], slice::from_raw_parts(data, length));
+            steganer_free_bytes(data, length);
+        }
+    }
+
+    #[test]
+    fn test_error_codes() {
+        let mut pixels = vec![0_u8; 4 * 4 * 3];
+        let big_data = [0_u8; 100];
+        unsafe {
+            assert_eq!(STEGANER_PAYLOAD_TOO_LARGE, steganer_hide_bytes_into_pixels(
+                big_data.as_ptr(), big_data.len(), pixels.as_mut_ptr(), 4, 4, 3));
+            let message = CStr::from_ptr(steganer_last_error_message()).to_str().unwrap();
+            assert!(message.contains("too big"), "Unexpected error message: {}", message);
+            assert_eq!(STEGANER_UNSUPPORTED_FORMAT, steganer_hide_bytes_into_pixels(
+                big_data.as_ptr(), 0, pixels.as_mut_ptr(), 4, 4, 2));
+            assert_eq!(STEGANER_INVALID_ARGUMENT,
+                       steganer_hide_into_image(ptr::null(), ptr::null()));
+            let mut capacity = 0_u64;
+            let host_file = CString::new("host.jpg").unwrap();
+            assert_eq!(STEGANER_UNSUPPORTED_FORMAT,
+                       steganer_image_capacity(host_file.as_ptr(), 0, &mut capacity));
+        }
+    }
+
+    #[test]
+    fn test_header_declares_every_function() {
+        let manifest_dir = env!("CARGO_MANIFEST_DIR");
+        let header = fs::read_to_string(format!("{}/include/steganer.h", manifest_dir))
+            .expect("Error reading C header");
+        let source = fs::read_to_string(format!("{}/src/capi.rs", manifest_dir))
+            .expect("Error reading C API source");
+        let exported = source.lines()
+            .filter_map(|line| line.split("extern \"C\" fn ").nth(1))
+            .filter_map(|declaration| declaration.split('(').next());
+        for function in exported {
+            assert!(header.contains(&format!("{}(", function)),
+                    "{} is not declared at C header.", function);
+        }
+        for line in source.lines().filter(|line| line.starts_with("pub const STEGANER_")) {
+            let constant = line.split(':').next().unwrap().trim_start_matches("pub const ");
+            assert!(header.contains(&format!("#define {} ", constant)),
+                    "{} is not defined at C header.", constant);
+        }
+    }
+}
@@ -0,0 +1,420 @@
+/// Module with hiding logic shared by every kind of host file.
+///
+/// Any host file whose content can be seen as a sequence of integer samples, like pixels of an
+/// image or samples of an audio track, can host hidden data as long as it implements Carrier
+/// trait. Hidden data is laid out the same way at every carrier:
+/// * First HEADER_SAMPLE_LENGTH samples hide hidden data size, as a big endian u32, one bit at
+///   the least significant bit of every sample.
+/// * Following samples hide hidden data itself, chunk_size bits at every sample. Chunk size is
+///   the smallest one that lets hidden data fit into available samples.
+///
+/// So new kinds of host files only have to tell how to read and write their samples, and how to
+/// save themselves, to get hiding, extraction and inspection for free.
+///
+/// # Usage example:
+/// ```ignore
+/// let mut host_audio = ContainerAudio::new("song.wav")
+///                         .expect("Error opening host audio");
+/// hide_into_carrier("secret.zip", &mut host_audio)
+///     .expect("Error hiding file");
+/// extract_from_carrier("recovered.zip", &host_audio)
+///     .expect("Error extracting file");
+/// ```
+use std::iter::Iterator;
+
+use crate::*;
+use crate::bytetools::mask;
+use crate::fileio::Chunk;
+
+/// Length in bits of hidden data size.
+pub(crate) const SIZE_LENGTH: u8 = 32;
+/// How many samples are used to store hidden data size, one bit at each one.
+pub(crate) const HEADER_SAMPLE_LENGTH: u8 = SIZE_LENGTH;
+
+/// A host file that can hide data at the least significant bits of its samples.
+///
+/// Samples are addressed by their position, from 0 to *sample_count()* - 1, and their values
+/// are handled as right justified integers of *sample_bits()* bits.
+///
+/// Implementors may keep their samples in memory, but then every change must be written to
+/// their backing store when *save()* is called.
+pub trait Carrier {
+    /// Get how many samples this carrier has.
+    fn sample_count(&self)-> u64;
+
+    /// Get how many bits every sample has, so how many of them can be overwritten at most.
+    /// It must be between 1 and 64, so carriers with 16 bits per channel RGB pixels fit too.
+    fn sample_bits(&self)-> u8;
+
+    /// Get nth sample value, right justified.
+    fn read_sample(&self, position: u64)-> u64;
+
+    /// Overwrite nth sample value.
+    ///
+    /// # Parameters:
+    /// * position: Sample to overwrite.
+    /// * value: New sample value, right justified. Bits beyond *sample_bits()* are ignored.
+    fn write_sample(&mut self, position: u64, value: u64);
+
+    /// Write every overwritten sample to carrier backing store.
+    fn save(&mut self)-> Result<()>;
+}
+
+/// Get how many samples are left to hide data after reserving header samples.
+pub(crate) fn usable_samples<C: Carrier + ?Sized>(carrier: &C)-> u64 {
+    carrier.sample_count().saturating_sub(HEADER_SAMPLE_LENGTH as u64)
+}
+
+/// Get how many bytes can be hidden at most into given carrier.
+///
+/// Hidden data size is stored in an u32 header, so bigger payloads can not be hidden whatever
+/// carrier size is.
+pub(crate) fn max_payload_bytes<C: Carrier + ?Sized>(carrier: &C)-> u64 {
+    std::cmp::min(usable_samples(carrier) * carrier.sample_bits() as u64 / 8, u32::MAX as u64)
+}
+
+/// Get needed chunk size to hide desired amount of data into given carrier.
+///
+/// # Parameters:
+/// * carrier: Host to hide data into.
+/// * total_data_size: Total amount of bytes for data to be hidden.
+///
+/// # Returns:
+/// * Chunk size. Each chunk will be encoded in a sample.
+/// * An error if data does not fit into carrier, or if carrier is too short to hold a header.
+pub(crate) fn chunk_size<C: Carrier + ?Sized>(carrier: &C, total_data_size: u32)-> Result<u8> {
+    let max_bytes = max_payload_bytes(carrier);
+    if total_data_size as u64 > max_bytes || carrier.sample_count() < HEADER_SAMPLE_LENGTH as u64 {
+        bail!(ErrorKind::PayloadTooLarge(total_data_size as u64, max_bytes));
+    }
+    let total_data_size_in_bits = total_data_size as u64 * 8;
+    if total_data_size_in_bits == 0 {
+        return Ok(0);
+    }
+    Ok(total_data_size_in_bits.div_ceil(usable_samples(carrier)) as u8)
+}
+
+/// Overwrite least significant bits of a sample.
+///
+/// # Parameters:
+/// * carrier: Host to hide data into.
+/// * position: Sample to overwrite.
+/// * bits: Data to be hidden, right justified.
+/// * bits_length: How many bits at bits parameter are actually data to be hidden.
+pub(crate) fn encode_bits<C: Carrier + ?Sized>(carrier: &mut C, position: u64, bits: u64,
+                                               bits_length: u8) {
+    let original_value = carrier.read_sample(position);
+    let modified_value = (original_value & mask::<u64>(bits_length, true)) | bits;
+    carrier.write_sample(position, modified_value);
+}
+
+/// Read least significant bits of a sample.
+///
+/// # Parameters:
+/// * carrier: Host to read hidden data from.
+/// * position: Sample to read.
+/// * bits_length: How many bits at sample are actually hidden data.
+///
+/// # Returns:
+/// * Recovered bits, right justified.
+pub(crate) fn decode_bits<C: Carrier + ?Sized>(carrier: &C, position: u64, bits_length: u8)-> u64 {
+    carrier.read_sample(position) & mask::<u64>(bits_length, false)
+}
+
+/// Hide data size at first HEADER_SAMPLE_LENGTH samples of carrier.
+pub(crate) fn encode_header<C: Carrier + ?Sized>(carrier: &mut C, total_data_size: u32) {
+    for i in 0..HEADER_SAMPLE_LENGTH {
+        let bit = (total_data_size >> (HEADER_SAMPLE_LENGTH - 1 - i)) & 1;
+        encode_bits(carrier, i as u64, bit as u64, 1);
+    }
+}
+
+/// Read hidden data size from first HEADER_SAMPLE_LENGTH samples of carrier.
+///
+/// # Returns:
+/// * Length in bytes of hidden data.
+/// * A NoPayload error if carrier is too short to hold a header.
+pub(crate) fn decode_header<C: Carrier + ?Sized>(carrier: &C)-> Result<u32> {
+    if carrier.sample_count() < HEADER_SAMPLE_LENGTH as u64 {
+        bail!(ErrorKind::NoPayload);
+    }
+    Ok((0..HEADER_SAMPLE_LENGTH)
+        .fold(0_u32, |size, i| (size << 1) | decode_bits(carrier, i as u64, 1) as u32))
+}
+
+/// Read header to guess whether given carrier hosts hidden data.
+///
+/// Header only stores hidden data size, so any carrier decodes to some size. Sizes that could
+/// not have been hidden into this carrier, or empty ones, are taken as no hidden data at all.
+///
+/// # Returns:
+/// * Some with hidden data size, in bytes, and bits used per sample to hide it, if header looks
+///   valid. None otherwise.
+pub(crate) fn probe_header<C: Carrier + ?Sized>(carrier: &C)-> Option<(u32, u8)> {
+    let hidden_file_size = decode_header(carrier).ok()?;
+    match chunk_size(carrier, hidden_file_size) {
+        Ok(chunk_size) if hidden_file_size > 0=> Some((hidden_file_size, chunk_size)),
+        _=> None,
+    }
+}
+
+/// Hide a file content into given carrier.
+///
+/// Carrier is not saved, so caller can still inspect it before saving.
+///
+/// # Parameters:
+/// * carrier: Host to hide data into.
+/// * content: Content of file to hide.
+/// * total_data_size: File to hide size in bytes.
+///
+/// # Returns:
+/// * Bits hidden per sample.
+pub(crate) fn hide_content<C: Carrier + ?Sized>(carrier: &mut C, content: &FileContent,
+                                                total_data_size: u32)-> Result<u8> {
+    let chunk_size = chunk_size(carrier, total_data_size)?;
+    encode_header(carrier, total_data_size);
+    for chunk in ContentReader::new(content, chunk_size) {
+        encode_bits(carrier, HEADER_SAMPLE_LENGTH as u64 + chunk.order as u64, chunk.data,
+                    chunk.length);
+    }
+    Ok(chunk_size)
+}
+
+/// Iterator to extract data hidden into a carrier a chunk at a time.
+///
+/// Last chunk only gets bits that are left of hidden data, so it may be shorter than the others.
+pub(crate) struct HiddenChunks<'c, C: Carrier + ?Sized> {
+    carrier: &'c C,
+    hidden_bits: u64,
+    chunk_size: u8,
+    reading_position: u32,
+}
+
+/// Prepare extraction of data hidden into given carrier.
+///
+/// # Returns:
+/// * Iterator over hidden chunks.
+/// * An error if header declares more data than carrier can hold, so it can not have been
+///   hidden there.
+pub(crate) fn hidden_chunks<C: Carrier + ?Sized>(carrier: &C)-> Result<HiddenChunks<'_, C>> {
+    let hidden_file_size = decode_header(carrier)?;
+    let chunk_size = chunk_size(carrier, hidden_file_size)
+        .chain_err(|| ErrorKind::NoPayload)?;
+    Ok(HiddenChunks{carrier, hidden_bits: hidden_file_size as u64 * 8, chunk_size,
+                    reading_position: 0})
+}
+
+impl <'c, C: Carrier + ?Sized> Iterator for HiddenChunks<'c, C> {
+    type Item = Chunk;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let bit_position = self.reading_position as u64 * self.chunk_size as u64;
+        if bit_position < self.hidden_bits {
+            let length = std::cmp::min(self.chunk_size as u64, self.hidden_bits - bit_position) as u8;
+            let data = decode_bits(self.carrier,
+                                   HEADER_SAMPLE_LENGTH as u64 + self.reading_position as u64,
+                                   length);
+            let chunk = Chunk::new(data, length, self.reading_position);
+            self.reading_position += 1;
+            Some(chunk)
+        } else { // No more hidden data left in carrier.
+            None
+        }
+    }
+}
+
+/// Hide a file into any carrier using steganography techniques.
+///
+/// Carrier is saved once file is hidden.
+///
+/// # Parameters:
+/// * file_to_hide: Absolute path to hidden file.
+/// * carrier: Host that is going to contain hidden file.
+///
+/// # Returns:
+/// * Bits hidden per sample.
+pub fn hide_into_carrier<C: Carrier + ?Sized>(file_to_hide: &str, carrier: &mut C)-> Result<u8> {
+    let (file_to_hide_content, file_to_hide_size) = read_file_to_hide(file_to_hide)?;
+    let chunk_size = hide_content(carrier, &file_to_hide_content, file_to_hide_size)?;
+    carrier.save()?;
+    Ok(chunk_size)
+}
+
+/// Extract a file hidden into any carrier using steganography techniques.
+///
+/// # Parameters:
+/// * hidden_file: Absolute path to file to store extracted data.
+/// * carrier: Host that contains hidden file.
+pub fn extract_from_carrier<C: Carrier + ?Sized>(hidden_file: &str, carrier: &C)-> Result<()> {
+    let chunks = hidden_chunks(carrier)?;
+    let mut extracted_file = FileWriter::new(hidden_file)
+        .chain_err(||"Error creating destination file to store extracted data")?
+        .with_expected_size(decode_header(carrier)? as u64);
+    extracted_file.write_chunks(chunks)?;
+    extracted_file.finish()?;
+    Ok(())
+}
+
+/// Hide data already in memory into any carrier using steganography techniques.
+///
+/// Carrier is saved once data is hidden.
+///
+/// # Parameters:
+/// * data: Bytes to hide.
+/// * carrier: Host that is going to contain hidden data.
+///
+/// # Returns:
+/// * Bits hidden per sample.
+pub fn hide_bytes_into_carrier<C: Carrier + ?Sized>(data: &[u8], carrier: &mut C)-> Result<u8> {
+    if data.len() as u64 > u32::MAX as u64 {
+        bail!(ErrorKind::PayloadTooLarge(data.len() as u64, u32::MAX as u64));
+    }
+    let content = FileContent::from_bytes(data.to_vec());
+    let chunk_size = hide_content(carrier, &content, data.len() as u32)?;
+    carrier.save()?;
+    Ok(chunk_size)
+}
+
+/// Extract data hidden into any carrier, keeping it in memory instead of writing it to a file.
+///
+/// # Parameters:
+/// * carrier: Host that contains hidden data.
+///
+/// # Returns:
+/// * Hidden bytes.
+pub fn extract_bytes_from_carrier<C: Carrier + ?Sized>(carrier: &C)-> Result<Vec<u8>> {
+    let hidden_size = decode_header(carrier)? as usize;
+    Ok(pack_chunks(hidden_chunks(carrier)?, hidden_size))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+    use test_common::fs::tmp::TestEnvironment;
+
+    /// Carrier kept in memory, with samples of given width.
+    struct MemoryCarrier {
+        samples: Vec<u64>,
+        sample_bits: u8,
+        saved: bool,
+    }
+
+    impl MemoryCarrier {
+        fn new(samples: Vec<u64>, sample_bits: u8)-> Self {
+            MemoryCarrier{samples, sample_bits, saved: false}
+        }
+    }
+
+    impl Carrier for MemoryCarrier {
+        fn sample_count(&self)-> u64 { self.samples.len() as u64 }
+
+        fn sample_bits(&self)-> u8 { self.sample_bits }
+
+        fn read_sample(&self, position: u64)-> u64 { self.samples[position as usize] }
+
+        fn write_sample(&mut self, position: u64, value: u64) {
+            self.samples[position as usize] = value & mask::<u64>(self.sample_bits, false);
+        }
+
+        fn save(&mut self)-> Result<()> {
+            self.saved = true;
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_header() {
+        let mut carrier = MemoryCarrier::new(vec![0xff; 40], 8);
+        encode_header(&mut carrier, 0x8000_0005);
+        assert_eq!(0xff, carrier.samples[0]);
+        assert_eq!(0xfe, carrier.samples[1]);
+        assert_eq!(0xff, carrier.samples[31]);
+        assert_eq!(0xff, carrier.samples[32]);
+        assert_eq!(0x8000_0005, decode_header(&carrier).unwrap());
+    }
+
+    #[test]
+    fn test_chunk_size() {
+        let carrier = MemoryCarrier::new(vec![0; 32 + 10], 16);
+        assert_eq!(1, chunk_size(&carrier, 1).unwrap());
+        // 3 bytes into 10 samples need 3 bits per sample.
+        assert_eq!(3, chunk_size(&carrier, 3).unwrap());
+        assert_eq!(16, chunk_size(&carrier, 20).unwrap());
+        assert!(matches!(chunk_size(&carrier, 21).unwrap_err().kind(),
+                         ErrorKind::PayloadTooLarge(21, 20)));
+    }
+
+    #[test]
+    fn test_hide_and_extract() {
+        let test_env = TestEnvironment::new();
+        let source_path = test_env.path().join("source.bin");
+        let recovered_path = test_env.path().join("recovered.bin");
+        // 10 bytes into 9 samples need 9 bits per sample, so last chunk is shorter.
+        let data = [0xde_u8, 0xad, 0xbe, 0xef, 0x01, 0x23, 0x45, 0x67, 0x89, 0xab];
+        File::create(&source_path).expect("Error creating source file")
+            .write_all(&data).expect("Error writing source file");
+        let mut carrier = MemoryCarrier::new(vec![0xaaaa; 32 + 9], 16);
+        assert_eq!(9, hide_into_carrier(source_path.to_str().unwrap(), &mut carrier).unwrap());
+        assert!(carrier.saved, "Carrier was not saved after hiding.");
+        // Bits above chunk size are kept.
+        assert!(carrier.samples[32..].iter().all(|sample| sample & 0xfe00 == 0xaa00));
+        assert_eq!(Some((10, 9)), probe_header(&carrier));
+        extract_from_carrier(recovered_path.to_str().unwrap(), &carrier)
+            .expect("Error extracting hidden data");
+        assert_eq!(data.to_vec(), std::fs::read(&recovered_path).unwrap());
+    }
+
+    #[test]
+    fn test_hide_and_extract_bytes() {
+        let data = b"Meet at noon";
+        let mut carrier = MemoryCarrier::new(vec![0x55; 32 + 100], 8);
+        assert_eq!(1, hide_bytes_into_carrier(data, &mut carrier).unwrap());
+        assert!(carrier.saved, "Carrier was not saved after hiding.");
+        assert_eq!(data.to_vec(), extract_bytes_from_carrier(&carrier).unwrap());
+        assert!(hide_bytes_into_carrier(&[0; 101], &mut carrier).is_err());
+    }
+
+    #[test]
+    fn test_chunks_wider_than_32_bits() {
+        // 10 bytes into 2 samples need 40 bits per sample.
+        let data = [0xde_u8, 0xad, 0xbe, 0xef, 0x01, 0x23, 0x45, 0x67, 0x89, 0xab];
+        let mut carrier = MemoryCarrier::new(vec![0xaaaa_aaaa_aaaa; 32 + 2], 48);
+        assert_eq!(40, hide_bytes_into_carrier(&data, &mut carrier).unwrap());
+        assert!(carrier.samples[32..].iter().all(|sample| sample >> 40 == 0xaa));
+        assert_eq!(data.to_vec(), extract_bytes_from_carrier(&carrier).unwrap());
+        // Streamed hiding keeps more than 64 pending bits while chunks are 64 bits long.
+        let data: Vec<u8> = (0..16).map(|i| i * 17).collect();
+        let mut carrier = MemoryCarrier::new(vec![0; 32 + 2], 64);
+        let mut writer = HiddenWriter::new(&mut carrier);
+        for block in data.chunks(3) {
+            writer.write_all(block).expect("Error writing hidden data");
+        }
+        assert_eq!(64, writer.finish().unwrap());
+        let mut recovered: Vec<u8> = Vec::new();
+        std::io::copy(&mut HiddenReader::new(&carrier).unwrap(), &mut recovered)
+            .expect("Error reading hidden data");
+        assert_eq!(data, recovered);
+    }
+
+    #[test]
+    fn test_invalid_header() {
+        let carrier = MemoryCarrier::new(vec![1; 32 + 9], 16);
+        assert!(matches!(hidden_chunks(&carrier).err().unwrap().kind(), ErrorKind::NoPayload));
+        assert_eq!(None, probe_header(&carrier));
+        let empty_carrier = MemoryCarrier::new(vec![0; 32 + 9], 16);
+        assert_eq!(None, probe_header(&empty_carrier));
+    }
+
+    #[test]
+    fn test_carrier_shorter_than_header() {
+        let mut carrier = MemoryCarrier::new(vec![0; 4], 16);
+        assert!(matches!(decode_header(&carrier).err().unwrap().kind(), ErrorKind::NoPayload));
+        assert!(matches!(hidden_chunks(&carrier).err().unwrap().kind(), ErrorKind::NoPayload));
+        assert!(matches!(extract_bytes_from_carrier(&carrier).err().unwrap().kind(),
+                         ErrorKind::NoPayload));
+        assert_eq!(None, probe_header(&carrier));
+        assert!(matches!(hide_bytes_into_carrier(&[], &mut carrier).err().unwrap().kind(),
+                         ErrorKind::PayloadTooLarge(0, 0)));
+    }
+}
@@ -1,29 +1,109 @@
-/// Configuration to make run an steganer execution properly.
-#[derive(Debug)]
+use crate::stegimage::DumpOptions;
+use crate::textcarrier::TextEncoding;
+use crate::visualize::Channel;
+
+/// Operations steganer can perform.
+#[derive(Debug, PartialEq, Copy, Clone)]
+pub enum Operation {
+    /// Hide *hidden_file* inside *host_file*.
+    Hide,
+    /// Extract data hidden at *host_file* and store it at *hidden_file*.
+    Extract,
+    /// Report how much data *host_file* can hold.
+    Capacity,
+    /// Inspect *host_file* looking for hidden data, without modifying anything.
+    Info,
+    /// Check *host_file* has *hidden_file* hidden inside, without modifying anything.
+    Verify,
+    /// Destroy any data hidden inside *host_file*.
+    Wipe,
+    /// Run steganalysis detectors over *host_file*, without modifying anything.
+    Analyze,
+    /// Render a bit plane of *host_file* into *output_file*.
+    Planes,
+    /// Render a heatmap of differences between *cover_file* and *host_file* into *output_file*.
+    Diff,
+    /// Dump raw bits of *host_file* into *output_file*.
+    Dump,
+    /// Look for payloads of any LSB tool inside *host_file*, without modifying anything.
+    Scan,
+}
+
+/// Options for a steganer execution.
+///
+/// Steganer command builds one from its arguments, but library users can fill one too and pass
+/// it to *_run()*, or let *Steganer::builder()* build it. Only fields related to chosen
+/// operation are used.
+#[derive(Debug, Clone)]
 pub struct Configuration {
-    /// If *self.extract* is *true* then *self.hidden_file* gives the name of the file to create to
-    /// put extracted hidden data into. Conversely, if *self.extract" is *false* then
-    /// *self.hidden_file* points to the file whose content must be hidden.
+    /// If *self.operation* is *Operation::Extract* then *self.hidden_file* gives the name of the
+    /// file to create to put extracted hidden data into. Conversely, if *self.operation* is
+    /// *Operation::Hide* then *self.hidden_file* points to the file whose content must be hidden.
     pub hidden_file: String,
-    /// Name of file where data must be hidden or recovered from depending of whereas *self.extract*
-    /// is *true* or *false*.
+    /// Name of file where data must be hidden or recovered from depending of whereas
+    /// *self.operation* is *Operation::Hide* or *Operation::Extract*.
     pub host_file: String,
-    /// Set if this operation is going to hide data or extract it.
-    pub extract: bool,
+    /// Set which operation is going to be performed.
+    pub operation: Operation,
+    /// Set if reports should be printed as JSON instead of human readable text.
+    pub json: bool,
+    /// Bits per pixel level to report when *self.operation* is *Operation::Capacity*. If None
+    /// every level is reported. When *self.operation* is *Operation::Hide*, hiding fails if
+    /// data needs more bits per pixel than this, and host file must be a static image.
+    pub bits_per_pixel: Option<u8>,
+    /// How many least significant bit planes to wipe at every channel when *self.operation* is
+    /// *Operation::Wipe*.
+    pub wipe_depth: u8,
+    /// Set if a quality report should be printed after hiding when *self.operation* is
+    /// *Operation::Hide*.
+    pub report: bool,
+    /// File to render or dump into when *self.operation* is *Operation::Planes*,
+    /// *Operation::Diff* or *Operation::Dump*. When *self.operation* is *Operation::Hide*, if not
+    /// empty, host file is copied here and data is hidden into that copy, leaving *host_file*
+    /// untouched.
+    pub output_file: String,
+    /// Original image to compare *host_file* with when *self.operation* is *Operation::Diff*.
+    pub cover_file: String,
+    /// Channel to render when *self.operation* is *Operation::Planes*.
+    pub channel: Channel,
+    /// Bit plane to render when *self.operation* is *Operation::Planes*.
+    pub bit: u8,
+    /// Bits to dump when *self.operation* is *Operation::Dump*.
+    pub dump_options: DumpOptions,
+    /// How data is hidden into text host files when *self.operation* is *Operation::Hide* or
+    /// *Operation::Extract*.
+    pub text_encoding: TextEncoding,
+    /// Threads to hide or extract with when host file is an image and *self.operation* is
+    /// *Operation::Hide* or *Operation::Extract*. 0 means one thread per CPU core.
+    pub threads: usize,
 }
 
 impl Configuration{
     /// Create an empty Configuration struct.
     ///
-    /// String attributes of this struct will br initialized to an empty string. Extract to false.
-    /// To initialize attributtes set them directly after creation.
+    /// String attributes of this struct will be initialized to an empty string. Operation to
+    /// hide. To initialize attributes set them directly after creation.
     pub fn new_default() -> Self {
-        Configuration{ hidden_file: "".to_owned(), host_file: "".to_owned(), extract: false}
+        Configuration{ hidden_file: "".to_owned(), host_file: "".to_owned(),
+                       operation: Operation::Hide, json: false, bits_per_pixel: None,
+                       wipe_depth: 1, report: false, output_file: "".to_owned(),
+                       cover_file: "".to_owned(), channel: Channel::Blue, bit: 0,
+                       dump_options: DumpOptions::default(),
+                       text_encoding: TextEncoding::ZeroWidth, threads: 1}
     }
 
-    /// Create a Configuration struct with given attributes.
+    /// Create a Configuration struct to hide or extract a file, with default values for every
+    /// other attribute.
     #[must_use]
     pub fn new(hidden_file: &str, host_file: &str, extract: bool)-> Self {
-        Configuration{hidden_file: hidden_file.to_owned(), host_file: host_file.to_owned(), extract}
+        let operation = if extract {Operation::Extract} else {Operation::Hide};
+        Configuration{hidden_file: hidden_file.to_owned(), host_file: host_file.to_owned(),
+                      operation, ..Configuration::new_default()}
     }
-}
\ No newline at end of file
+}
+
+impl Default for Configuration {
+    fn default()-> Self {
+        Configuration::new_default()
+    }
+}
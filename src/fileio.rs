@@ -3,47 +3,42 @@
 /// Thanks to ContentReader type you can get an iterator to read a file to hide and get its bits
 /// in predefined bunches. Every bunch of bits are returned inside a Chunk type.
 ///
-/// Conversely, FileWriter allows you write chunks of bits into a destination file.
+/// Conversely, FileWriter allows you write chunks of bits into a destination file, or into any
+/// other writer.
 ///
 /// # Usage example:
 /// ```ignore
 /// let file_content = FileContent::new("source_file.txt")
 ///                         .expect("Error obtaining source file content");
-/// let mut reader = ContentReader::new(&file_content, 4);
+/// let reader = ContentReader::new(&file_content, 4);
 /// {
 ///     let mut writer = FileWriter::new("output_file")
 ///                     .expect("Error creating output file for extracted data.");
-///     for chunk in reader {
-///         // Do things with every chunk of 4 bits of data from source_file.txt.
-///         writer.write(&chunk);
-///     }
-/// } // When FileWriter types get out of scope they write to file pending last few bytes.
+///     // Every chunk of 4 bits of data from source_file.txt is written to output_file.
+///     writer.write_chunks(reader).expect("Error writing chunks to output file");
+///     // Check every bit was written and flush pending bytes to file.
+///     writer.finish().expect("Error finishing output file");
+/// }
 /// // At this point contents of source_file.txt and output_file.txt should be the same.
 /// ```
-use std::fmt;
-use std::fmt::{Debug, Formatter};
+use std::cmp::min;
 use std::fs::File;
 // Write import gets a compiler warning. It warns about importing Write is useless but actually
 // if I remove Write import I get a compiler error in this module code.
-use std::io::{BufReader, Read, Write};
+use std::io::{BufReader, BufWriter, Read, Write};
 use std::iter::Iterator;
-use std::ops::Add;
 // Pathbuf import gets a compiler warning. It warns about importing PathBuf is useless but actually
 // if I remove PathBuf import I get a compiler error in this module code.
 use std::path::PathBuf;
 
-
-use bitreader::{BitReader, BitReaderError};
-
 use crate::*;
-use crate::bytetools::{mask, bytes_to_u24, get_bits, left_justify, get_bytes};
 
 
 /// Bits read from files to be hidden are stored at Chunks.
 pub struct Chunk {
-    /// Every Chunk stores a maximum of 32 read bits at this property, those bits are
+    /// Every Chunk stores a maximum of 64 read bits at this property, those bits are
     /// at natural order (Big Endian) and justified to right.
-    pub data: u32,
+    pub data: u64,
     /// Number of bits actually stored at data attribute. If you are reading the last few file bits
     /// you're probably going read less bits than requested.
     pub length: u8,
@@ -53,96 +48,66 @@ pub struct Chunk {
 
 impl Chunk {
     #[must_use]
-    pub fn new(data: u32, length: u8, order: u32)-> Self {
+    pub fn new(data: u64, length: u8, order: u32)-> Self {
         Chunk {data, length, order}
     }
 }
 
-/// Type to represent excess bits that are not enough to conform an entire byte.
-#[derive(PartialEq, Clone)]
-struct Remainder {
-    /// u8 with remainder data bits insufficient to conform a byte. Bits are right justified.
-    data: u8,
-    /// u8 with how many bits of remainder are actual data.
-    length: u8,
+/// Get a mask with given amount of least significant bits set to 1.
+///
+/// # Parameters:
+/// * length: Bits to set, from 0 to 64.
+fn low_bits(length: u8)-> u64 {
+    if length >= 64 { u64::MAX } else { (1_u64 << length) - 1 }
 }
 
-impl Remainder {
-    #[must_use]
-    pub fn new(data: u8, length: u8)-> Self{
-        Remainder {data, length}
-    }
+/// Accumulator to pack bits of any length, up to 64, into bytes.
+///
+/// Bits are appended at the right of those already accumulated, so they keep their natural
+/// order (Big Endian). Completed bytes are given away as soon as they fill, so less than 8 bits
+/// are kept pending between appends.
+#[derive(Default)]
+pub(crate) struct BitPacker {
+    /// Pending bits, right justified. Bits above *self.length* are garbage.
+    bits: u64,
+    /// How many bits at *self.bits* are actual data.
+    length: u8,
 }
 
-/// This Add is not a binary sum but a binary accumulator instead.
-///
-/// RHS bits are accumulated at the end of first operand bits. So this operation is not
-/// commutative. Everything is left justified.
-///
-/// A BinaryAccumulationResult is returned after this operation.
-///
-/// This implementation was possible thanks to
-/// [this Stackoverflow post](https://stackoverflow.com/questions/28005134/how-do-i-implement-the-add-trait-for-a-reference-to-a-struct)
-impl<'a, 'b> Add<&'b Remainder> for &'a Remainder {
-    type Output = BinaryAccumulation;
-
-    fn add(self, rhs: &'b Remainder) -> Self::Output {
-        let total_length = self.length + rhs.length;
-        if total_length <= 8 {
-            let shifted_bits_to_add = rhs.data << (8 - self.length - rhs.length);
-            let accumulated_bits = self.data + shifted_bits_to_add;
-            if total_length == 8 {
-                BinaryAccumulation {
-                    complete_byte: Some(accumulated_bits),
-                    remainder: None,
-                }
-            } else {
-                BinaryAccumulation {
-                    complete_byte: None,
-                    remainder: Some(Remainder::new(accumulated_bits, total_length)),
-                }
-            }
-        } else {
-            let shifted_left_hand_side_bits = (self.data as u16) << 8;
-            let shifted_bits_to_add = (rhs.data as u16) << (16 - self.length - rhs.length);
-            let accumulated_bits = shifted_left_hand_side_bits + shifted_bits_to_add;
-            let remainder_length = total_length - 8;
-            let complete_byte = ((accumulated_bits & (!mask::<u16>(8, false))) >> 8) as u8;
-            let remainder_bits = (accumulated_bits & (mask::<u16>(8-remainder_length, true))) as u8;
-            BinaryAccumulation {
-                complete_byte: Some(complete_byte),
-                remainder: Some(Remainder::new(remainder_bits, remainder_length)),
+impl BitPacker {
+    /// Append bits and move every byte they complete into bytes.
+    ///
+    /// # Parameters:
+    /// * data: Bits to append, right justified.
+    /// * length: How many bits at data are actual data.
+    /// * bytes: Vector to push completed bytes into.
+    pub(crate) fn push(&mut self, data: u64, length: u8, bytes: &mut Vec<u8>) {
+        if length <= 56 {
+            // Usual case, chunk fits at once into accumulator.
+            self.bits = (self.bits << length) | (data & low_bits(length));
+            self.length += length;
+            self.flush_bytes(bytes);
+            return;
+        }
+        let mut remaining = length;
+        while remaining > 0 {
+            // Less than 8 bits are pending, so 56 more bits always fit into accumulator.
+            let taken = min(remaining, 56);
+            remaining -= taken;
+            self.bits = (self.bits << taken) | ((data >> remaining) & low_bits(taken));
+            self.length += taken;
+            if self.length >= 8 {
+                self.flush_bytes(bytes);
             }
         }
     }
-}
-
-impl Debug for Remainder {
-    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
-        write!(f, "(data: {}, length: {})",
-               self.data, self.length)
-    }
-}
-
-/// Type returned after binary accumulate two remainders.
-#[derive(PartialEq)]
-struct BinaryAccumulation {
-    complete_byte: Option<u8>,
-    remainder: Option<Remainder>,
-}
 
-impl Debug for BinaryAccumulation {
-    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
-        let complete_byte = match &self.complete_byte {
-            Some(value)=> *value,
-            None=> 0,
-        };
-        let remainder = match &self.remainder {
-            Some(remainder)=> (*remainder).clone(),
-            None=> Remainder::new(0,0),
-        };
-        write!(f, "(complete_byte: {}, remainder: {:?})",
-               complete_byte, remainder)
+    /// Move every complete byte of accumulator into bytes.
+    fn flush_bytes(&mut self, bytes: &mut Vec<u8>) {
+        while self.length >= 8 {
+            self.length -= 8;
+            bytes.push((self.bits >> self.length) as u8);
+        }
     }
 }
 
@@ -171,14 +136,34 @@ impl FileContent {
             content,
         })
     }
+
+    /// Wrap data already in memory, so it can be hidden without reading it from a file.
+    ///
+    /// # Parameters:
+    /// * content: Bytes to hide.
+    pub fn from_bytes(content: Vec<u8>)-> Self {
+        FileContent{content}
+    }
+
+    /// Get content bytes.
+    pub(crate) fn bytes(&self)-> &[u8] {
+        &self.content
+    }
 }
 
 /// ContentReader gives you an iterator to read a FileContent data.
 ///
-/// Iterator returns a Chunk Type with bits read in every read iteration.
+/// Iterator returns a Chunk Type with bits read in every read iteration. Chunks can be up to
+/// 64 bits long.
 pub struct ContentReader<'a> {
-    /// BitReader type to read bits alone.
-    bit_reader: BitReader<'a>,
+    /// Content to read.
+    content: &'a [u8],
+    /// Index of first content byte not loaded yet into *self.bits*.
+    next_byte: usize,
+    /// Loaded bits not read yet, right justified. Bits above *self.bits_length* are garbage.
+    bits: u64,
+    /// How many bits at *self.bits* are still to be read.
+    bits_length: u8,
     /// Amount of bits to get in each iterator round.
     chunk_size: u8,
     /// Index about how many read rounds we've done using iterator.
@@ -188,13 +173,32 @@ pub struct ContentReader<'a> {
 impl<'a> ContentReader<'a> {
     #[must_use]
     pub fn new(content: &'a FileContent, chunk_size: u8)-> Self {
-        let file_bytes = content.content.as_slice();
         ContentReader {
-            bit_reader: BitReader::new(file_bytes.clone()),
-            chunk_size,
+            content: content.content.as_slice(),
+            next_byte: 0,
+            bits: 0,
+            bits_length: 0,
+            chunk_size: min(chunk_size, 64),
             position: 0,
         }
     }
+
+    /// Load next content bytes into *self.bits*, once every loaded bit has been read.
+    ///
+    /// Only 7 bytes are loaded at once, so a read never takes 64 bits from *self.bits* and
+    /// shifts never overflow.
+    ///
+    /// # Returns:
+    /// * False if there were no content bytes left to load.
+    fn load_bytes(&mut self)-> bool {
+        let loaded_bytes = min(7, self.content.len() - self.next_byte);
+        for byte in &self.content[self.next_byte..self.next_byte + loaded_bytes] {
+            self.bits = (self.bits << 8) | *byte as u64;
+        }
+        self.next_byte += loaded_bytes;
+        self.bits_length = loaded_bytes as u8 * 8;
+        loaded_bytes > 0
+    }
 }
 
 /// Iterator to read file content a chunk at a time.
@@ -207,170 +211,164 @@ impl<'a> Iterator for ContentReader<'a> {
     type Item = Chunk;
 
     fn next(&mut self) -> Option<Self::Item> {
-        let chunk = match self.bit_reader.read_u32(self.chunk_size) {
-            Ok(bits)=> {
-                self.position += 1;
-                Some(Chunk::new(bits, self.chunk_size, self.position-1))
+        let mut data = 0_u64;
+        let mut length = 0_u8;
+        while length < self.chunk_size {
+            if self.bits_length == 0 && !self.load_bytes() {
+                break;
             }
-            Err(e)=> {
-                if let BitReaderError::NotEnoughData {position, length, requested: _ } = e {
-                    let available_bits = length - position;
-                    if available_bits > 0 {
-                        let bits = self.bit_reader.read_u32(available_bits as u8)
-                            // I cannot use error_chain because it is not possible change next()
-                            // signature because it belongs to Iterator trait.
-                            .expect("Error reading last few bits from file to be hidden.");
-                        self.position += 1;
-                        Some(Chunk::new(bits, available_bits as u8, self.position-1))
-                    } else {
-                        None
-                    }
-                } else {
-                    panic!("Error reading data to be hidden");
-                }
-            }
-        };
-        chunk
+            let taken = min(self.chunk_size - length, self.bits_length);
+            self.bits_length -= taken;
+            data = (data << taken) | ((self.bits >> self.bits_length) & low_bits(taken));
+            length += taken;
+        }
+        if length == 0 {
+            None
+        } else {
+            self.position += 1;
+            Some(Chunk::new(data, length, self.position-1))
+        }
     }
 }
 
-/// Wrapper over an open file to write into it chunks extracted from host files.
+/// Pack chunks bits into bytes until a given amount of bytes is filled.
+///
+/// # Parameters:
+/// * chunks: Chunks to pack, in order.
+/// * max_bytes: How many bytes to fill at most.
+///
+/// # Returns:
+/// * Vector with packed bytes. It can be shorter than max_bytes if there were not enough chunks.
+pub fn pack_chunks<I: Iterator<Item=Chunk>>(chunks: I, max_bytes: usize)-> Vec<u8> {
+    // Last chunk may overflow max_bytes by up to 8 bytes before being truncated.
+    let mut bytes: Vec<u8> = Vec::with_capacity(max_bytes + 8);
+    let mut packer = BitPacker::default();
+    for chunk in chunks {
+        if bytes.len() >= max_bytes { break; }
+        packer.push(chunk.data, chunk.length, &mut bytes);
+    }
+    bytes.truncate(max_bytes);
+    bytes
+}
+
+/// How many complete bytes *FileWriter::write_chunks()* packs before handing them to destination.
+const WRITE_BATCH_SIZE: usize = 64 * 1024;
+
+/// Wrapper over any writable destination to write into it chunks extracted from host files.
 ///
 /// Complete bytes are written at once but border bytes need to be rebuild from two different
 /// chunks, so we need *self.pending_data* to use as a temporal container until it is filled
-/// completely and we can write it.
-pub struct FileWriter {
-    /// Destination file to write chunks into.
-    destination: File,
+/// completely and we can write it. Chunks can be up to 64 bits long.
+///
+/// Destination is usually a buffered file, as *FileWriter::new()* creates, but any other
+/// writer can be given with *FileWriter::from_writer()*, like a vector in memory, a socket or
+/// stdout. Those writers are not buffered by FileWriter, so wrap them in a BufWriter if they
+/// are expensive to write into.
+///
+/// Once every chunk is written, *FileWriter::finish()* must be called to check written data
+/// is complete and to flush destination. Otherwise, buffered bytes may be lost and errors go
+/// unnoticed.
+pub struct FileWriter<W: Write = BufWriter<File>> {
+    /// Destination to write chunks into.
+    destination: W,
     /// Buffer to write into extracted bits until we have a complete byte to write into
     /// destination.
-    pending_data: Option<Remainder>,
+    pending_data: BitPacker,
+    /// How many complete bytes have been written into destination.
+    written_bytes: u64,
+    /// How many bytes hidden data header declared, if known.
+    expected_bytes: Option<u64>,
 }
 
 impl FileWriter {
+    /// Create a buffered file to write chunks into.
+    ///
+    /// # Parameters:
+    /// * destination_file: Absolute path to file to create. If it already exists it is truncated.
     #[must_use]
     pub fn new(destination_file: &str)-> Result<Self> {
         let destination = File::create(destination_file)
             .chain_err(|| "Error creating destination file.")?;
-        let initial_remainder = None;
-        Ok(FileWriter{destination, pending_data: initial_remainder})
+        Ok(FileWriter::from_writer(BufWriter::new(destination)))
     }
+}
 
-    /// Write Chunk into *self.destination* file.
+impl<W: Write> FileWriter<W> {
+    /// Write chunks into any destination.
     ///
-    /// Actually only complete bytes will be written into file. Incomplete remainder bytes
-    /// will be stored into self.pending_bytes until they fill up. When pending_bytes fills
-    /// it is written and replaced by new exceeding bits.
-    pub fn write(&mut self, chunk: &Chunk)-> Result<()> {
-        if let Some(complete_bytes) = self.store_remainder(chunk)?{
-            for byte in complete_bytes.iter(){
-                let _ = self.destination.write(&[*byte])
-                    .chain_err(||"An IO error happened when trying to write chunk to destination file.")?;
-            }
-        }
-        Ok(())
+    /// # Parameters:
+    /// * destination: Writer to send complete bytes to.
+    pub fn from_writer(destination: W)-> Self {
+        FileWriter{destination, pending_data: BitPacker::default(), written_bytes: 0, expected_bytes: None}
     }
 
-    /// Get bits that do not conform complete bytes.
+    /// Set how many bytes are expected to be written, so *self.finish()* can detect truncated
+    /// data.
     ///
     /// # Parameters:
-    /// * data: Chunk data already left justified.
-    /// * length: How many bits from left are actual data.
-    ///
-    /// # Returns:
-    /// * A Some(Remainder) if a remainder is available.
-    /// * None is returned if there is no remainder available (i.e data conforms an integer
-    /// amount of bytes).
-    fn get_remainder(data: u32, length: u8)-> Option<Remainder>{
-        let remainder_length = length % 8;
-        if remainder_length == 0 {
-            None
-        } else {
-            let complete_bytes = length / 8;
-            let remainder_bits = get_bits(data, complete_bytes*8, remainder_length) as u8;
-            let left_justified_remainder_bits = remainder_bits << (8 - remainder_length);
-            Some(Remainder::new(left_justified_remainder_bits, remainder_length))
-        }
+    /// * expected_bytes: Hidden data size, as declared by its header.
+    pub fn with_expected_size(mut self, expected_bytes: u64)-> Self {
+        self.expected_bytes = Some(expected_bytes);
+        self
     }
 
-    /// Called by *store_remainder()* to get a left justified u32 with current remainder with
-    /// chunk data appended.
+    /// Write chunks into *self.destination*, packing their complete bytes together.
     ///
-    /// # Parameters:
-    /// * chunk: Chunk to append.
+    /// Actually only complete bytes will be written into destination, in big batches instead of
+    /// a few bytes per chunk. Incomplete remainder bytes will be stored into self.pending_data
+    /// until next chunks fill them up.
     ///
-    /// # Returns:
-    /// * u32 with left justified current remainder with chunk data appended.
-    /// * u8 how many bits from left are actual data.
-    fn append_to_remainder(&self, chunk: &Chunk)-> (u32, u8){
-        let left_justified_data = left_justify(chunk.data, chunk.length);
-        let data_int = bytes_to_u24(&left_justified_data);
-        let (pending_data, pending_data_length) = match &self.pending_data {
-            Some(remainder)=> (remainder.data, remainder.length),
-            None=> {
-                let default_remainder = Remainder::new(0, 0);
-                (default_remainder.data, default_remainder.length)
+    /// # Parameters:
+    /// * chunks: Chunks to write, in order.
+    pub fn write_chunks<I: IntoIterator<Item=Chunk>>(&mut self, chunks: I)-> Result<()> {
+        // Room for a whole batch plus the biggest chunk.
+        let mut batch: Vec<u8> = Vec::with_capacity(WRITE_BATCH_SIZE + 8);
+        for chunk in chunks {
+            self.pending_data.push(chunk.data, chunk.length, &mut batch);
+            if batch.len() >= WRITE_BATCH_SIZE {
+                self.write_batch(&mut batch)?;
             }
-        };
-        let pending_data_left_justified = (pending_data as u32) << (32 - 8);
-        let data_appended_to_remainder: u32 = pending_data_left_justified + (data_int << (8 - pending_data_length));
-        let total_length = pending_data_length + chunk.length;
-        (data_appended_to_remainder, total_length)
+        }
+        self.write_batch(&mut batch)
     }
 
-    /// Keep in *self.pending_data* those bits that are not enough to conform a complete byte.
-    ///
-    /// Bits are accumulated until they fill a byte. If adding bits to *self.pending_data* fills
-    /// entire bytes, then those bytes are returned in a vector and excess bits become the
-    /// new *self.pending_data*.
+    /// Write a batch of complete bytes into *self.destination* and empty it.
+    fn write_batch(&mut self, batch: &mut Vec<u8>)-> Result<()> {
+        if batch.is_empty() { return Ok(()); }
+        self.destination.write_all(batch)
+            .chain_err(||"An IO error happened when trying to write chunks to destination file.")?;
+        self.written_bytes += batch.len() as u64;
+        batch.clear();
+        Ok(())
+    }
+
+    /// Check every written bit conformed complete bytes and flush destination.
     ///
-    /// # Parameters:
-    /// * chunk: Chunk to be written.
+    /// Bits left at *self.pending_data* can not conform a byte, so they are not written but
+    /// reported as an error. If an expected size was set, written bytes are checked against it.
     ///
     /// # Returns:
-    /// * Optionally returns a vector with complete bytes if adding remainder to *self.pending_data* fills
-    /// any. If that does not happen a None is returned instead.
-    fn store_remainder(&mut self, chunk: &Chunk)-> Result<Option<Vec<u8>>> {
-        let (data_appended_to_remainder, total_length) = self.append_to_remainder(chunk);
-        if let Some(new_remainder) = Self::get_remainder(data_appended_to_remainder, total_length){
-            let non_remainder_length = total_length - new_remainder.length;
-            self.pending_data = Some(new_remainder);
-            if non_remainder_length == 0 {
-                // Only remainder. No entire bytes.
-                Ok(None)
-            } else {
-                // Remainder and entire bytes.
-                // I don't use get_bits() because I want to keep non_remainder_data left justified.
-                let non_remainder_data = data_appended_to_remainder & mask::<u32>(32-non_remainder_length, true);
-                Ok(Some(get_bytes(non_remainder_data, non_remainder_length)
-                            // At this point we have calculated that there should be entire bytes
-                            // so if get_bytes returns None then we have an error, and we use
-                            // ok_or() to launch that error.
-                            .ok_or("Could not extract any byte from provided data")?))
-            }
-        } else {
-            // Only entire bytes. No remainder left.
-            self.pending_data = None;
-            Ok(Some(get_bytes(data_appended_to_remainder, total_length)
-                        .ok_or("Could not extract any byte from provided data")?))
+    /// * Destination, already flushed.
+    /// * An error if written bits do not end at a byte boundary, if less or more bytes than
+    ///   expected were written or if destination could not be flushed.
+    pub fn finish(mut self)-> Result<W> {
+        self.destination.flush()
+            .chain_err(||"An IO error happened when trying to flush destination file.")?;
+        if self.pending_data.length > 0 {
+            bail!("Written data does not end at a byte boundary. There are {} bits left after {} bytes.",
+                  self.pending_data.length, self.written_bytes);
         }
-    }
-}
-
-impl Drop for FileWriter {
-    /// On drop, self.pending_data content is considered complete and should be stored
-    /// into self.destination.
-    fn drop(&mut self) {
-        if let Some(remainder) = &self.pending_data {
-            let _ = self.destination.write(&[remainder.data])
-                // I don't use error_chain here because a I cannot change drop trait signature
-                // to return a Result.
-                .expect("An IO error happened when trying to write chunk to output file.");
+        match self.expected_bytes {
+            Some(expected_bytes) if expected_bytes > self.written_bytes=>
+                bail!(ErrorKind::TruncatedPayload(expected_bytes, self.written_bytes)),
+            Some(expected_bytes) if expected_bytes < self.written_bytes=>
+                bail!("More data was written than expected. Expected {} bytes but {} bytes were written.",
+                      expected_bytes, self.written_bytes),
+            _=> Ok(self.destination),
         }
     }
 }
 
-
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -455,14 +453,14 @@ mod tests {
         // Remove lower half of "L".
         expected_chunk = expected_chunk & 0xF0;
         expected_chunk = expected_chunk >> 4;
-        assert_eq!(expected_chunk, chunk.data);
+        assert_eq!(expected_chunk as u64, chunk.data);
         reader.next(); // Lower half of "L".
         reader.next(); // Upper half of "o".
         chunk = reader.next()
             .expect("Error reading chunk"); // Lower half of "o".
         expected_chunk = "o".to_owned().as_bytes()[0] as u32;
         expected_chunk = expected_chunk & 0x0F;
-        assert_eq!(expected_chunk, chunk.data);
+        assert_eq!(expected_chunk as u64, chunk.data);
     }
 
     #[test]
@@ -492,7 +490,7 @@ mod tests {
             .expect("Error writing chunk bigger than 8.");
         let mut expected_int = normalize(bytes_to_u32(wtr), 12, true);
         // expected_int = 0b0100_1100_0110
-        assert_eq!(expected_int, chunk.data);
+        assert_eq!(expected_int as u64, chunk.data);
         reader.next(); // Lower half of "o" and "r".
         reader.next(); // "e" and upper half of "m".
         chunk = reader.next()
@@ -515,7 +513,7 @@ mod tests {
         // expected_int = 0b1101_0010_0000
         expected_int = normalize(bytes_to_u32(wtr), 12, false);
         // chunk_data = 0b1101_0010_0000
-        assert_eq!(expected_int, chunk.data);
+        assert_eq!(expected_int as u64, chunk.data);
     }
 
     fn test_writing_n_bits_chunks(chunk_size: u8) {
@@ -528,17 +526,14 @@ mod tests {
         // Destination file setup.
         let destination_file_name_path = test_env.path().join("output.txt").into_os_string().into_string()
             .expect("Error reading destination file name. Unsupported character might have been used.");
-        {
-            // We enclose destination_writer in its own scope so drop() is called at that scope end
-            // to write remaining bits to destination file.
-            let mut destination_writer = FileWriter::new(destination_file_name_path.as_str())
-                .expect("Error happened trying to created FileWriter type.");
-            // Transferring chunks.
-             for chunk in reader {
-                destination_writer.write(&chunk)
-                    .expect("Error happened writing chunk.");
-            }
-        }
+        let mut destination_writer = FileWriter::new(destination_file_name_path.as_str())
+            .expect("Error happened trying to created FileWriter type.")
+            .with_expected_size(MESSAGE.len() as u64);
+        // Transferring chunks.
+        destination_writer.write_chunks(reader)
+            .expect("Error happened writing chunks.");
+        destination_writer.finish()
+            .expect("Error happened finishing destination file.");
         // Test destination file has same content than source file.
         let source_file_hash = hash_file(source_path.to_str()
             .expect("Source file name contains odd characters"))
@@ -571,173 +566,119 @@ mod tests {
         test_writing_n_bits_chunks(4);
     }
 
+    #[test]
+    fn test_writing_64_bits_chunks() {
+        test_writing_n_bits_chunks(64);
+    }
+
+    #[test]
+    fn test_writing_57_bits_chunks() {
+        test_writing_n_bits_chunks(57);
+    }
+
+    #[test]
+    fn test_writing_33_bits_chunks() {
+        test_writing_n_bits_chunks(33);
+    }
+
     #[test]
     fn test_writing_3_bits_chunks() {
         test_writing_n_bits_chunks(3);
     }
 
+    /// Writer that keeps written bytes in memory and counts how many times it was written.
+    struct CountingWriter {
+        bytes: Vec<u8>,
+        writes: usize,
+    }
+
+    impl Write for CountingWriter {
+        fn write(&mut self, buf: &[u8])-> std::io::Result<usize> {
+            self.writes += 1;
+            self.bytes.extend_from_slice(buf);
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self)-> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
     #[test]
-    fn test_get_remainder() {
-        let expected_remainder = 0b_1011_0000_u8;
-        let data_1_length = 4_u8;
-        let data_1 = (expected_remainder as u32) << (32 - data_1_length - 4);
-        let data_2_length = 12_u8;
-        let data_2 = (expected_remainder as u32) << (32 - data_2_length - 4);
-        let data_3_length = 20_u8;
-        let data_3 = (expected_remainder as u32) << (32 - data_3_length - 4);
-        let remainder1 = FileWriter::get_remainder(data_1, data_1_length)
-            .expect("No remainder found");
-        assert_eq!((expected_remainder, 4), (remainder1.data, remainder1.length),
-                   "We did not get expected remainder when analyzing 1 byte case. Expected {:#?}, but got {:#?}.",
-                   (expected_remainder, 4), (remainder1.data, remainder1.length));
-        let remainder2 = FileWriter::get_remainder(data_2, data_2_length)
-            .expect("No remainder found");
-        assert_eq!((expected_remainder, 4), (remainder2.data, remainder2.length),
-                   "We did not get expected remainder when analyzing 2 byte case. Expected {:#?}, but got {:#?}.",
-                   (expected_remainder, 4), (remainder2.data, remainder2.length));
-        let remainder3 = FileWriter::get_remainder(data_3, data_3_length)
-            .expect("No remainder found");
-        assert_eq!((expected_remainder, 4), (remainder3.data, remainder3.length),
-                   "We did not get expected remainder when analyzing 3 bytes case. Expected {:#?}, but got {:#?}.",
-                   (expected_remainder, 4), (remainder3.data, remainder3.length));
+    fn test_write_chunks_into_memory() {
+        let content: Vec<u8> = (0..200_000_u32).map(|i| (i % 251) as u8).collect();
+        let file_content = FileContent::from_bytes(content.clone());
+        let mut destination = CountingWriter{bytes: Vec::new(), writes: 0};
+        let mut destination_writer = FileWriter::from_writer(&mut destination);
+        destination_writer.write_chunks(ContentReader::new(&file_content, 7))
+            .expect("Error happened writing chunks.");
+        destination_writer.finish().expect("Error happened finishing destination.");
+        assert!(content == destination.bytes, "Written bytes are not the same as read ones.");
+        assert!(destination.writes <= 4, "Chunks were not written in batches. There were {} writes.",
+                destination.writes);
     }
 
     #[test]
-    fn test_add_remainder() {
-        // Accumulating without overflow.
-        let remainder1 = Remainder::new(0b_101_0_0000_u8, 3);
-        let remainder2 = Remainder::new(0b_11_u8, 2);
-        let expected_result = BinaryAccumulation {
-            complete_byte: None,
-            remainder: Some(Remainder::new(0b_1011_1_000_u8, 5))
-        };
-        let result = &remainder1 + &remainder2;
-        assert_eq!(expected_result, result,
-                   "Accumulation without overflow did not worked as we expected. \
-                   We expected a remainder of {:?} but we got {:?}",
-                   expected_result, result);
-        // Accumulating with overflow.
-        let remainder1 = Remainder::new(0b_1010_111_0_u8, 7);
-        let remainder2 = Remainder::new(0b_011_u8, 3);
-        let expected_result = BinaryAccumulation {
-            complete_byte: Some(0b_1010_1110_u8),
-            remainder: Some(Remainder::new(0b_11_00_0000_u8, 2))
-        };
-        let result = &remainder1 + &remainder2;
-        assert_eq!(expected_result, result,
-                   "Accumulation with overflow did not worked as we expected. \
-                   We expected a remainder of {:?} but we got {:?}",
-                   expected_result, result);
-        // Accumulating an exact byte.
-        let remainder1 = Remainder::new(0b_1010_111_0_u8, 7);
-        let remainder2 = Remainder::new(0b_0_u8, 1);
-        let expected_result = BinaryAccumulation {
-            complete_byte: Some(0b_1010_1110_u8),
-            remainder: None,
-        };
-        let result = &remainder1 + &remainder2;
-        assert_eq!(expected_result, result,
-                   "Accumulation with overflow did not worked as we expected. \
-                   We expected a remainder of {:?} but we got {:?}",
-                   expected_result, result);
+    fn test_finish_incomplete_data() {
+        let mut destination_writer = FileWriter::from_writer(Vec::new());
+        destination_writer.write_chunks(vec![Chunk::new(0b010_0110_0011, 11, 0)])
+            .expect("Error happened writing chunk.");
+        assert!(destination_writer.finish().is_err(), "Unaligned data was not reported.");
+        let mut destination_writer = FileWriter::from_writer(Vec::new()).with_expected_size(2);
+        destination_writer.write_chunks(vec![Chunk::new(0b_0100_1100, 8, 0)])
+            .expect("Error happened writing chunk.");
+        let error = destination_writer.finish().expect_err("Truncated data was not reported.");
+        assert!(matches!(error_kind(&error), Some(ErrorKind::TruncatedPayload(2, 1))));
+        let mut destination_writer = FileWriter::from_writer(Vec::new()).with_expected_size(1);
+        destination_writer.write_chunks(vec![Chunk::new(0b_0100_1100_0110_1111, 16, 0)])
+            .expect("Error happened writing chunk.");
+        let error = destination_writer.finish().expect_err("Oversized data was not reported.");
+        assert!(error_kind(&error).is_none(), "Oversized data was reported as truncated.");
+        assert!(error.to_string().contains("More data was written than expected"));
+        let mut destination_writer = FileWriter::from_writer(Vec::new()).with_expected_size(1);
+        destination_writer.write_chunks(vec![Chunk::new(0b_0100_1100, 8, 0)])
+            .expect("Error happened writing chunk.");
+        assert_eq!(b"L".to_vec(), destination_writer.finish().expect("Error finishing data."));
     }
 
     #[test]
-    fn test_store_remainder() {
-        // Destination file setup.
-        let ( _,test_env) = get_temporary_test_file();
-        let destination_file_name_path = test_env.path().join("output.txt").into_os_string().into_string()
-            .expect("Error reading destination file name. Unsupported character might have been used.");
-        {
-            // We enclose destination_writer in its own scope so drop() is called at that scope end
-            // to write remaining bits to destination file.
-            let mut destination_writer = FileWriter::new(destination_file_name_path.as_str())
-                .expect("Error happened trying to created FileWriter type.");
-            // Accumulating without overflow.
-            let remainder1 = Remainder::new(0b_101_0_0000_u8, 3);
-            let remainder2 = Chunk::new(0b_11, 2, 1);
-            let expected_result = 0b_1011_1_000_u8;
-            destination_writer.pending_data = Some(remainder1);
-            if let Ok(Some(_)) = destination_writer.store_remainder(&remainder2) {
-                assert!(false, "A complete byte was returned when no remainder fill was expected.");
-            } else {
-                if let Some(remainder) = &destination_writer.pending_data {
-                    assert_eq!(expected_result, remainder.data,
-                               "Store remainder without overflow did not worked as we expected. \
-                            We expected a remainder of {:#b} but we got {:#b}",
-                               expected_result, remainder.data);
-                } else {
-                    assert!(false, "We expected a remainder but none was found.");
-                }
-            }
-            // Accumulating with overflow.
-            let remainder1 = Remainder::new(0b_1010_111_0_u8, 7);
-            let remainder2 = Chunk::new(0b_011, 3, 1);
-            let expected_result = 0b_11_00_0000_u8;
-            let expected_complete_byte = 0b_1010_1110_u8;
-            destination_writer.pending_data = Some(remainder1);
-            if let Ok(Some(complete_byte)) = destination_writer.store_remainder(&remainder2){
-                if let Some(remainder) = &destination_writer.pending_data {
-                    assert_eq!(expected_result, remainder.data,
-                               "Store remainder with overflow did not worked as we expected. \
-                                We expected a remainder of {:#b} but we got {:#b}",
-                               expected_result, remainder.data);
-                    assert_eq!(expected_complete_byte, complete_byte[0],
-                               "Recovered complete byte was not what we were expecting. \
-                               We expected {:#b} but we got {:#b}",
-                               expected_complete_byte, complete_byte[0]);
-                } else {
-                    assert!(false, "We expected a remainder but none was found.");
-                }
-            } else {
-                assert!(false, "We were expecting to fill remainder but no complete byte was returned.");
-            }
-            // Accumulating an exact byte.
-            let remainder1 = Remainder::new(0b_1010_111_0_u8, 7);
-            let remainder2 = Chunk::new(0b_0, 1, 1);
-            let expected_complete_byte = 0b_1010_1110_u8;
-            destination_writer.pending_data = Some(remainder1);
-            if let Ok(Some(complete_byte)) = destination_writer.store_remainder(&remainder2){
-                if let Some(remainder) = &destination_writer.pending_data {
-                    assert!(false, "We expected no remainder but one found instead. Found remainder \
-                        has data {:#b} a length {}",
-                        remainder.data, remainder.length);
-                } else {
-                    assert_eq!(expected_complete_byte, complete_byte[0],
-                               "Recovered complete byte was not what we were expecting. \
-                               We expected {:#b} but we got {:#b}",
-                               expected_complete_byte, complete_byte[0]);
-                }
-            } else {
-                assert!(false, "We were expecting to fill remainder but no complete byte was returned.");
-            }
-        }
+    fn test_pack_chunks() {
+        // "Lo" split in 3 bits chunks, last one only 1 bit long.
+        let chunks = vec![Chunk::new(0b_010, 3, 0), Chunk::new(0b_011, 3, 1),
+                          Chunk::new(0b_000, 3, 2), Chunk::new(0b_110, 3, 3),
+                          Chunk::new(0b_111, 3, 4), Chunk::new(0b_1, 1, 5)];
+        let packed = pack_chunks(chunks.into_iter(), 2);
+        assert_eq!(b"Lo".to_vec(), packed,
+                   "Chunks were not properly packed. Expected {:?} but got {:?}",
+                   b"Lo".to_vec(), packed);
     }
 
     #[test]
-    fn test_append_to_remainder() {
-        // Destination file setup.
-        let ( _,test_env) = get_temporary_test_file();
-        let destination_file_name_path = test_env.path().join("output.txt").into_os_string().into_string()
-            .expect("Error reading destination file name. Unsupported character might have been used.");
-        {
-            // We enclose destination_writer in its own scope so drop() is called at that scope end
-            // to write remaining bits to destination file.
-            let mut destination_writer = FileWriter::new(destination_file_name_path.as_str())
-                .expect("Error happened trying to created FileWriter type.");
-            let current_remainder_length = 7_u8;
-            let current_remainder = 0b_1010_1110_u8;
-            destination_writer.pending_data = Some(Remainder::new(current_remainder, current_remainder_length));
-            let chunk = Chunk::new(0b_011, 3, 1);
-            let expected_appended_remainder_length = 10_u8;
-            let expected_appended_remainder = 0b_1010_1110_11_u32 << 32 - expected_appended_remainder_length;
-            let (appended_remainder, appended_remainder_length) = destination_writer.append_to_remainder(&chunk);
-            assert_eq!(expected_appended_remainder, appended_remainder,
-                       "Appended remainder is not what we were expecting. Expected {} but got {}.",
-                       expected_appended_remainder, appended_remainder);
-            assert_eq!(expected_appended_remainder_length, appended_remainder_length,
-                       "Appended remainder length is not what we were expecting. Expected {} but got {}.",
-                       expected_appended_remainder_length, appended_remainder_length);
-        }
+    fn test_bit_packer() {
+        let mut packer = BitPacker::default();
+        let mut bytes: Vec<u8> = Vec::new();
+        // Accumulating without completing a byte.
+        packer.push(0b_101, 3, &mut bytes);
+        packer.push(0b_11, 2, &mut bytes);
+        assert!(bytes.is_empty(), "A complete byte was returned when no byte fill was expected.");
+        assert_eq!((0b1_0111, 5), (packer.bits & low_bits(packer.length), packer.length));
+        // Accumulating with overflow.
+        packer.push(0b_0110, 4, &mut bytes);
+        assert_eq!(vec![0b_1011_1011_u8], bytes);
+        assert_eq!((0b_0, 1), (packer.bits & low_bits(packer.length), packer.length));
+        // Accumulating a whole 64 bits word after a pending bit.
+        packer.push(0xFEDC_BA98_7654_3210, 64, &mut bytes);
+        assert_eq!(vec![0b_1011_1011_u8, 0x7F, 0x6E, 0x5D, 0x4C, 0x3B, 0x2A, 0x19, 0x08], bytes);
+        assert_eq!((0b_0, 1), (packer.bits & low_bits(packer.length), packer.length));
+    }
+
+    #[test]
+    fn test_iterator_next_64_bits() {
+        let file_content = FileContent::from_bytes(MESSAGE.as_bytes().to_vec());
+        let mut reader = ContentReader::new(&file_content, 64);
+        let chunk = reader.next().expect("Error reading chunk");
+        assert_eq!((u64::from_be_bytes(*b"Lorem ip"), 64), (chunk.data, chunk.length));
+        let chunk = reader.next().expect("Error reading chunk");
+        assert_eq!((u64::from_be_bytes(*b"sum dolo"), 64), (chunk.data, chunk.length));
     }
 }
\ No newline at end of file
@@ -0,0 +1,476 @@
+/// Module to hide data inside GIF images, animated or not.
+///
+/// GIF pixels are indexes into a palette, so changing their least significant bit could change
+/// their color completely. Instead, like EzStego does, palette colors are sorted by luminance and
+/// grouped in pairs of similar colors. Every pixel hides a bit choosing between both colors of
+/// its pair, so palettes are never modified.
+///
+/// Every frame is used, in the same order they are stored at file, and pixels are read in the
+/// same order they are stored at every frame. Pixels are skipped if their color, or their pair
+/// color, is the transparent one of their frame, or if their color has no pair because palette
+/// has an odd number of colors. Those rules do not depend on hidden data, so the same pixels are
+/// found when extracting.
+///
+/// Only image data of modified frames is rewritten. Any other block, like frame delays, disposal
+/// methods, loop count or comments, is kept untouched.
+///
+/// Hidden data uses the same 32 samples header every carrier uses, which only stores hidden data
+/// size. No frame/offset mapping is recorded there, as frames and samples are always found again
+/// following the fixed order above. Header has no version or carrier type field either, so
+/// extracting from a host of a different type than the one used to hide is not detected: it
+/// just fails with a NoPayload error or gets garbage.
+///
+/// # Usage example:
+/// ```ignore
+/// let host_gif = ContainerGif::new("funny_cat.gif")
+///                         .expect("Error opening host GIF");
+/// println!("{} bytes can be hidden", max_payload_bytes(&host_gif));
+/// ```
+use std::fs;
+use std::path::Path;
+
+use crate::*;
+use crate::carrier::Carrier;
+
+const SUPPORTED_EXTENSIONS: [&str; 1] = ["gif"];
+/// GIF header plus logical screen descriptor.
+const SCREEN_HEADER_LENGTH: usize = 13;
+const EXTENSION_INTRODUCER: u8 = 0x21;
+const IMAGE_SEPARATOR: u8 = 0x2C;
+const TRAILER: u8 = 0x3B;
+const GRAPHIC_CONTROL_LABEL: u8 = 0xF9;
+/// Flag at packed fields telling a color table follows.
+const COLOR_TABLE_FLAG: u8 = 0x80;
+/// Maximum length of every data sub-block.
+const MAX_SUB_BLOCK_LENGTH: usize = 255;
+/// GIF minimum LZW code size can not be smaller than this.
+const MIN_LZW_CODE_SIZE: u8 = 2;
+
+/// Check if this file is supported as a valid host GIF image.
+///
+/// # Parameters:
+/// * filename: Host image filename.
+///
+/// # Returns:
+/// * True if this file is a GIF image and false if not.
+pub(crate) fn supported_gif(filename: &str)-> bool {
+    match Path::new(filename).extension().and_then(|extension| extension.to_str()) {
+        Some(extension)=> SUPPORTED_EXTENSIONS.contains(&extension.to_lowercase().as_str()),
+        None=> false,
+    }
+}
+
+/// A GIF frame, with its pixels decompressed.
+struct GifFrame {
+    /// Position at file of LZW minimum code size byte.
+    data_start: usize,
+    /// Position at file right after image data block terminator.
+    data_end: usize,
+    min_code_size: u8,
+    /// Palette index of every pixel, in the same order they are stored at file.
+    pixels: Vec<u8>,
+    /// Palette indexes sorted by color luminance.
+    sorted_colors: Vec<u8>,
+    /// Position at *sorted_colors* of every usable palette index.
+    ranks: [Option<u16>; 256],
+    /// Set when any pixel has been changed, so its image data must be compressed again.
+    modified: bool,
+}
+
+impl GifFrame {
+    /// Get usable pair of colors of a palette index, given as its position at *sorted_colors*.
+    fn rank(&self, index: u8)-> Option<u16> {
+        self.ranks[index as usize]
+    }
+}
+
+/// Read a little endian u16 at given position.
+fn read_u16(content: &[u8], position: usize)-> u16 {
+    u16::from_le_bytes([content[position], content[position + 1]])
+}
+
+/// Get length in bytes of a color table given packed fields byte that announces it.
+fn color_table_length(packed_fields: u8)-> usize {
+    if packed_fields & COLOR_TABLE_FLAG == 0 {
+        0
+    } else {
+        3 * (2 << (packed_fields & 0x07))
+    }
+}
+
+/// Read a sequence of data sub-blocks, until block terminator.
+///
+/// # Returns:
+/// * Concatenated sub-blocks data.
+/// * Position at file right after block terminator.
+fn read_sub_blocks(content: &[u8], mut position: usize)-> Result<(Vec<u8>, usize)> {
+    let mut data = Vec::new();
+    loop {
+        let length = match content.get(position) {
+            Some(&length)=> length as usize,
+            None=> bail!("GIF data block is truncated."),
+        };
+        position += 1;
+        if length == 0 {
+            return Ok((data, position));
+        }
+        if position + length > content.len() {
+            bail!("GIF data block is truncated.");
+        }
+        data.extend_from_slice(&content[position..position + length]);
+        position += length;
+    }
+}
+
+/// Decompress LZW data of a frame.
+///
+/// # Parameters:
+/// * min_code_size: LZW minimum code size.
+/// * data: Compressed data.
+/// * pixel_count: How many pixels frame has.
+///
+/// # Returns:
+/// * Palette index of every pixel.
+fn decode_lzw(min_code_size: u8, mut data: &[u8], pixel_count: usize)-> Result<Vec<u8>> {
+    let mut decoder = lzw::Decoder::new(lzw::LsbReader::new(), min_code_size);
+    let mut pixels = Vec::with_capacity(pixel_count);
+    while !data.is_empty() && pixels.len() < pixel_count {
+        let (consumed, bytes) = decoder.decode_bytes(data)
+            .chain_err(|| "Error decompressing GIF frame.")?;
+        pixels.extend_from_slice(bytes);
+        data = &data[consumed..];
+    }
+    // Decoder can still keep a few buffered bits with last codes.
+    for _ in 0..8 {
+        if pixels.len() >= pixel_count { break; }
+        let (_, bytes) = decoder.decode_bytes(&[])
+            .chain_err(|| "Error decompressing GIF frame.")?;
+        pixels.extend_from_slice(bytes);
+    }
+    if pixels.len() < pixel_count {
+        bail!("GIF frame is truncated. It has {} pixels but only {} were found.",
+              pixel_count, pixels.len());
+    }
+    pixels.truncate(pixel_count);
+    Ok(pixels)
+}
+
+/// Compress pixels of a frame as a GIF image data block.
+///
+/// # Parameters:
+/// * min_code_size: LZW minimum code size frame had originally.
+/// * pixels: Palette index of every pixel.
+///
+/// # Returns:
+/// * LZW minimum code size byte, followed by compressed data sub-blocks and block terminator.
+fn encode_lzw(min_code_size: u8, pixels: &[u8])-> Result<Vec<u8>> {
+    // A pixel can be set to a color of palette that was not used before and that needs a
+    // bigger code size.
+    let max_index = pixels.iter().max().copied().unwrap_or(0) as u32;
+    let needed_code_size = (32 - max_index.leading_zeros()) as u8;
+    let min_code_size = min_code_size.max(needed_code_size).max(MIN_LZW_CODE_SIZE);
+    let mut compressed = Vec::new();
+    {
+        let mut encoder = lzw::Encoder::new(lzw::LsbWriter::new(&mut compressed), min_code_size)
+            .chain_err(|| "Error compressing GIF frame.")?;
+        encoder.encode_bytes(pixels)
+            .chain_err(|| "Error compressing GIF frame.")?;
+        // End code is written when encoder is dropped.
+    }
+    let mut block = vec![min_code_size];
+    for sub_block in compressed.chunks(MAX_SUB_BLOCK_LENGTH) {
+        block.push(sub_block.len() as u8);
+        block.extend_from_slice(sub_block);
+    }
+    block.push(0);
+    Ok(block)
+}
+
+/// Sort palette colors by luminance to pair similar colors.
+///
+/// # Parameters:
+/// * palette: Color table, with 3 bytes for every color.
+/// * transparent: Transparent palette index, if any.
+///
+/// # Returns:
+/// * Palette indexes sorted by luminance.
+/// * Position at sorted indexes of every palette index that can be used to hide data.
+fn pair_colors(palette: &[u8], transparent: Option<u8>)-> (Vec<u8>, [Option<u16>; 256]) {
+    let mut sorted_colors: Vec<u8> = (0..palette.len() / 3).map(|index| index as u8).collect();
+    let luminance = |index: &u8| {
+        let color = &palette[*index as usize * 3..*index as usize * 3 + 3];
+        299 * color[0] as u32 + 587 * color[1] as u32 + 114 * color[2] as u32
+    };
+    sorted_colors.sort_by_key(luminance);
+    let mut ranks = [None; 256];
+    for (rank, &index) in sorted_colors.iter().enumerate() {
+        let pair = match sorted_colors.get(rank ^ 1) {
+            Some(&pair)=> pair,
+            None=> continue,
+        };
+        if Some(index) != transparent && Some(pair) != transparent {
+            ranks[index as usize] = Some(rank as u16);
+        }
+    }
+    (sorted_colors, ranks)
+}
+
+/// Parse GIF file content looking for its frames.
+///
+/// # Parameters:
+/// * content: Whole GIF file content.
+///
+/// # Returns:
+/// * Every frame found at file, with its pixels decompressed.
+/// * An error if file is not a valid GIF file.
+fn parse_gif(content: &[u8])-> Result<Vec<GifFrame>> {
+    if content.len() < SCREEN_HEADER_LENGTH || !(content.starts_with(b"GIF87a") || content.starts_with(b"GIF89a")) {
+        bail!("File is not a valid GIF file.");
+    }
+    let global_table_length = color_table_length(content[10]);
+    let global_palette_end = SCREEN_HEADER_LENGTH + global_table_length;
+    if global_palette_end > content.len() {
+        bail!("GIF global color table is truncated.");
+    }
+    let global_palette = &content[SCREEN_HEADER_LENGTH..global_palette_end];
+    let mut frames = Vec::new();
+    let mut transparent: Option<u8> = None;
+    let mut position = global_palette_end;
+    loop {
+        match content.get(position) {
+            Some(&EXTENSION_INTRODUCER)=> {
+                let label = content.get(position + 1).copied();
+                let (data, next_position) = read_sub_blocks(content, position + 2)?;
+                if label == Some(GRAPHIC_CONTROL_LABEL) && data.len() >= 4 {
+                    // Transparency only applies to next frame.
+                    transparent = if data[0] & 0x01 == 1 { Some(data[3]) } else { None };
+                }
+                position = next_position;
+            },
+            Some(&IMAGE_SEPARATOR)=> {
+                if position + 10 > content.len() {
+                    bail!("GIF image descriptor is truncated.");
+                }
+                let width = read_u16(content, position + 5) as usize;
+                let height = read_u16(content, position + 7) as usize;
+                let local_table_length = color_table_length(content[position + 9]);
+                let data_start = position + 10 + local_table_length;
+                if data_start >= content.len() {
+                    bail!("GIF frame {} is truncated.", frames.len());
+                }
+                let palette = if local_table_length > 0 {
+                    &content[position + 10..data_start]
+                } else {
+                    global_palette
+                };
+                let min_code_size = content[data_start];
+                if !(MIN_LZW_CODE_SIZE..=8).contains(&min_code_size) {
+                    bail!("GIF frame {} has an invalid LZW code size.", frames.len());
+                }
+                let (data, data_end) = read_sub_blocks(content, data_start + 1)?;
+                let pixels = decode_lzw(min_code_size, &data, width * height)
+                    .chain_err(|| format!("Error reading GIF frame {}", frames.len()))?;
+                let (sorted_colors, ranks) = pair_colors(palette, transparent);
+                frames.push(GifFrame{data_start, data_end, min_code_size, pixels, sorted_colors,
+                                     ranks, modified: false});
+                transparent = None;
+                position = data_end;
+            },
+            Some(&TRAILER)=> return Ok(frames),
+            _=> bail!("GIF file has an unknown block at position {}.", position),
+        }
+    }
+}
+
+/// A GIF image, animated or not, used to hide data at its pixels.
+///
+/// Frames are kept in memory and file is written back when *save()* is called or when this type
+/// is dropped, but only if any pixel was modified.
+pub struct ContainerGif<'a> {
+    content: Vec<u8>,
+    frames: Vec<GifFrame>,
+    /// Frame and pixel index of every usable pixel.
+    samples: Vec<(u32, u32)>,
+    file_pathname: &'a str,
+    /// Set when any pixel has been modified, so GIF must be saved on drop.
+    modified: bool,
+}
+
+impl <'a> ContainerGif<'a> {
+    /// Open a GIF image as a carrier.
+    ///
+    /// # Parameters:
+    /// * file_pathname: Absolute path to GIF image.
+    ///
+    /// # Returns:
+    /// * Image ready to hide data into or extract data from.
+    /// * An error if file could not be read or is not a valid GIF.
+    pub fn new(file_pathname: &'a str)-> Result<Self> {
+        if !supported_gif(file_pathname) {
+            bail!(ErrorKind::UnsupportedFormat("Image".to_owned(), file_pathname.to_owned()));
+        }
+        let content = fs::read(file_pathname)
+            .chain_err(|| format!("Error reading host image {}", file_pathname))?;
+        let frames = parse_gif(&content)
+            .chain_err(|| format!("Error parsing host image {}", file_pathname))?;
+        let samples = frames.iter().enumerate()
+            .flat_map(|(frame_index, frame)| frame.pixels.iter().enumerate()
+                .filter(move |(_, &pixel)| frame.rank(pixel).is_some())
+                .map(move |(pixel_index, _)| (frame_index as u32, pixel_index as u32)))
+            .collect();
+        Ok(ContainerGif{content, frames, samples, file_pathname, modified: false})
+    }
+
+    /// Get whole GIF content, compressing again modified frames.
+    fn to_bytes(&self)-> Result<Vec<u8>> {
+        let mut bytes = Vec::with_capacity(self.content.len());
+        let mut position = 0;
+        for frame in self.frames.iter().filter(|frame| frame.modified) {
+            bytes.extend_from_slice(&self.content[position..frame.data_start]);
+            bytes.extend(encode_lzw(frame.min_code_size, &frame.pixels)?);
+            position = frame.data_end;
+        }
+        bytes.extend_from_slice(&self.content[position..]);
+        Ok(bytes)
+    }
+}
+
+impl <'a> Carrier for ContainerGif<'a> {
+    fn sample_count(&self)-> u64 {
+        self.samples.len() as u64
+    }
+
+    fn sample_bits(&self)-> u8 {
+        1
+    }
+
+    /// Sample value is least significant bit of pixel color position at sorted palette.
+    fn read_sample(&self, position: u64)-> u64 {
+        let (frame_index, pixel_index) = self.samples[position as usize];
+        let frame = &self.frames[frame_index as usize];
+        let rank = frame.rank(frame.pixels[pixel_index as usize])
+            .expect("Samples only include pixels with usable colors");
+        (rank & 1) as u64
+    }
+
+    /// Pixel is set to its pair color when hidden bit does not match current one.
+    fn write_sample(&mut self, position: u64, value: u64) {
+        let (frame_index, pixel_index) = self.samples[position as usize];
+        let frame = &mut self.frames[frame_index as usize];
+        let rank = frame.rank(frame.pixels[pixel_index as usize])
+            .expect("Samples only include pixels with usable colors");
+        if (rank & 1) as u64 != value & 1 {
+            frame.pixels[pixel_index as usize] = frame.sorted_colors[(rank ^ 1) as usize];
+            frame.modified = true;
+            self.modified = true;
+        }
+    }
+
+    fn save(&mut self)-> Result<()> {
+        if self.modified {
+            fs::write(self.file_pathname, self.to_bytes()?)
+                .chain_err(|| format!("Error saving host image {}", self.file_pathname))?;
+            self.modified = false;
+        }
+        Ok(())
+    }
+}
+
+/// Save to file every change done over GIF.
+impl <'a> Drop for ContainerGif<'a> {
+    fn drop(&mut self) {
+        self.save().expect("GIF could not be saved");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use test_common::fs::tmp::TestEnvironment;
+
+    /// Build an animated GIF with two 4x2 frames, a loop extension and a comment.
+    ///
+    /// Second frame has its own palette and a transparent color.
+    fn gif_content()-> Vec<u8> {
+        let mut content = b"GIF89a".to_vec();
+        content.extend_from_slice(&[4, 0, 2, 0, 0x81, 0, 0]);
+        // Global palette: black, white, dark gray and light gray.
+        content.extend_from_slice(&[0, 0, 0, 255, 255, 255, 60, 60, 60, 200, 200, 200]);
+        content.extend_from_slice(b"\x21\xFF\x0BNETSCAPE2.0\x03\x01\x00\x00\x00");
+        content.extend_from_slice(b"\x21\xFE\x05hello\x00");
+        content.extend_from_slice(&[0x21, 0xF9, 4, 0x08, 10, 0, 0, 0]);
+        content.extend_from_slice(&[0x2C, 0, 0, 0, 0, 4, 0, 2, 0, 0]);
+        content.extend(encode_lzw(2, &[0, 1, 2, 3, 3, 2, 1, 0]).unwrap());
+        content.extend_from_slice(&[0x21, 0xF9, 4, 0x09, 20, 0, 3, 0]);
+        content.extend_from_slice(&[0x2C, 0, 0, 0, 0, 4, 0, 2, 0, 0x81]);
+        content.extend_from_slice(&[10, 10, 10, 20, 20, 20, 30, 30, 30, 40, 40, 40]);
+        content.extend(encode_lzw(2, &[3, 3, 0, 1, 2, 3, 0, 1]).unwrap());
+        content.push(TRAILER);
+        content
+    }
+
+    #[test]
+    fn test_lzw_round_trip() {
+        let pixels: Vec<u8> = (0..1000).map(|i| ((i * 7) % 13) as u8).collect();
+        let block = encode_lzw(4, &pixels).unwrap();
+        assert_eq!(4, block[0]);
+        let (data, end) = read_sub_blocks(&block, 1).unwrap();
+        assert_eq!(block.len(), end);
+        assert_eq!(pixels, decode_lzw(4, &data, pixels.len()).unwrap());
+        // Code size grows if a pixel needs it.
+        assert_eq!(5, encode_lzw(4, &[16, 0]).unwrap()[0]);
+    }
+
+    #[test]
+    fn test_pair_colors() {
+        // Black, white, dark gray and light gray: pairs are black-dark and light-white.
+        let palette = [0, 0, 0, 255, 255, 255, 60, 60, 60, 200, 200, 200];
+        let (sorted_colors, ranks) = pair_colors(&palette, None);
+        assert_eq!(vec![0, 2, 3, 1], sorted_colors);
+        assert_eq!([Some(0), Some(3), Some(1), Some(2)], ranks[..4]);
+        // Transparent color and its pair are not usable.
+        let (_, ranks) = pair_colors(&palette, Some(3));
+        assert_eq!([Some(0), None, Some(1), None], ranks[..4]);
+        // Last color of an odd palette has no pair.
+        let (_, ranks) = pair_colors(&palette[..9], None);
+        assert_eq!([Some(0), None, Some(1)], ranks[..3]);
+    }
+
+    #[test]
+    fn test_parse_gif() {
+        let frames = parse_gif(&gif_content()).unwrap();
+        assert_eq!(2, frames.len());
+        assert_eq!(vec![0, 1, 2, 3, 3, 2, 1, 0], frames[0].pixels);
+        // Color 3 is transparent at second frame, so its pair 2 is not usable either.
+        assert_eq!(None, frames[1].rank(3));
+        assert_eq!(None, frames[1].rank(2));
+        assert!(frames[1].rank(0).is_some());
+        assert!(parse_gif(b"GIF89a").is_err());
+    }
+
+    #[test]
+    fn test_write_samples_keeps_metadata() {
+        let test_env = TestEnvironment::new();
+        let gif_path = test_env.path().join("test.gif");
+        let content = gif_content();
+        fs::write(&gif_path, &content).expect("Error writing test GIF");
+        {
+            let mut gif = ContainerGif::new(gif_path.to_str().unwrap()).expect("Error opening test GIF");
+            assert_eq!(2, gif.frames.len());
+            // 8 pixels of first frame and 4 of second one.
+            assert_eq!(12, gif.sample_count());
+            for position in 0..gif.sample_count() {
+                let flipped = gif.read_sample(position) ^ 1;
+                gif.write_sample(position, flipped);
+                assert_eq!(flipped, gif.read_sample(position));
+            }
+        }
+        let modified_content = fs::read(&gif_path).expect("Error reading modified GIF");
+        let frames = parse_gif(&modified_content).unwrap();
+        assert_eq!(vec![2, 3, 0, 1, 1, 0, 3, 2], frames[0].pixels);
+        assert_eq!(vec![3, 3, 1, 0, 2, 3, 1, 0], frames[1].pixels);
+        // Everything before first frame image data, like loop and comment extensions, is kept.
+        let first_data_start = parse_gif(&content).unwrap()[0].data_start;
+        assert_eq!(&content[..first_data_start], &modified_content[..first_data_start]);
+    }
+}
@@ -0,0 +1,402 @@
+/// Module to access data hidden into carriers through standard IO traits.
+///
+/// HiddenReader implements std::io::Read and std::io::Seek over data hidden into any carrier,
+/// so hidden data can be piped into decompressors, parsers or std::io::copy() without
+/// extracting it first. As every chunk is always hidden at the same sample, any byte range can
+/// be read decoding only samples that hold it.
+///
+/// HiddenWriter implements std::io::Write, so data can be hidden as it is produced without
+/// knowing its size beforehand. Header samples are kept reserved and only written on finish.
+///
+/// # Usage example:
+/// ```ignore
+/// let host_image = ContainerImage::new("lenna.png")?;
+/// let mut reader = HiddenReader::new(&host_image)?;
+/// reader.seek(SeekFrom::Start(512))?;
+/// let mut block = [0_u8; 512];
+/// reader.read_exact(&mut block)?;
+///
+/// let mut host_image = ContainerImage::new("lenna.png")?;
+/// let mut writer = HiddenWriter::new(&mut host_image);
+/// std::io::copy(&mut tar_stream, &mut writer)?;
+/// writer.finish()?;
+/// ```
+use std::cmp::min;
+use std::io::{Read, Seek, SeekFrom, Write};
+
+use crate::*;
+use crate::bytetools::mask;
+use crate::carrier::{chunk_size, decode_bits, decode_header, encode_bits, encode_header,
+                     max_payload_bytes, usable_samples, HEADER_SAMPLE_LENGTH};
+use crate::fileio::BitPacker;
+
+/// Reader over data hidden into a carrier.
+///
+/// Carrier is borrowed, so it can not be modified while it is being read.
+pub struct HiddenReader<'c, C: Carrier + ?Sized> {
+    /// Host with hidden data.
+    carrier: &'c C,
+    /// Hidden data size in bytes, as declared by its header.
+    hidden_size: u64,
+    /// Bits hidden per sample.
+    chunk_size: u8,
+    /// Offset in bytes of next byte to read.
+    position: u64,
+}
+
+impl<'c, C: Carrier + ?Sized> HiddenReader<'c, C> {
+    /// Prepare reading data hidden into given carrier.
+    ///
+    /// # Parameters:
+    /// * carrier: Host with hidden data.
+    ///
+    /// # Returns:
+    /// * Reader placed at hidden data start.
+    /// * A NoPayload error if carrier is too short to hold a header or if header declares more
+    ///   data than carrier can hold, so it can not have been hidden there.
+    pub fn new(carrier: &'c C)-> Result<Self> {
+        let hidden_size = decode_header(carrier)?;
+        let chunk_size = chunk_size(carrier, hidden_size)
+            .chain_err(|| ErrorKind::NoPayload)?;
+        Ok(HiddenReader{carrier, hidden_size: hidden_size as u64, chunk_size, position: 0})
+    }
+
+    /// Get hidden data size.
+    ///
+    /// # Returns:
+    /// * Hidden data size in bytes, as declared by its header.
+    pub fn hidden_size(&self)-> u64 {
+        self.hidden_size
+    }
+
+    /// Decode hidden bits of a given range.
+    ///
+    /// Only samples holding those bits are decoded. Bits of first and last sample that are out
+    /// of range are discarded.
+    ///
+    /// # Parameters:
+    /// * first_bit: Offset in bits of first bit to decode.
+    /// * end_bit: Offset in bits of first bit not to decode. Range must be inside hidden data.
+    ///
+    /// # Returns:
+    /// * Decoded bytes. Range must start and end at byte boundaries.
+    fn decode_range(&self, first_bit: u64, end_bit: u64)-> Vec<u8> {
+        let chunk_size = self.chunk_size as u64;
+        let hidden_bits = self.hidden_size * 8;
+        let mut bytes: Vec<u8> = Vec::with_capacity(((end_bit - first_bit) / 8) as usize);
+        let mut packer = BitPacker::default();
+        let mut bit = first_bit;
+        while bit < end_bit {
+            let chunk = bit / chunk_size;
+            let chunk_start = chunk * chunk_size;
+            // Last chunk only gets bits that are left of hidden data.
+            let length = min(chunk_size, hidden_bits - chunk_start);
+            let data = decode_bits(self.carrier, HEADER_SAMPLE_LENGTH as u64 + chunk,
+                                   length as u8);
+            let kept_end = min(chunk_start + length, end_bit);
+            // Chunk bits are big endian, so bits after kept ones are at the right.
+            packer.push(data >> (chunk_start + length - kept_end), (kept_end - bit) as u8,
+                        &mut bytes);
+            bit = kept_end;
+        }
+        bytes
+    }
+}
+
+impl<'c, C: Carrier + ?Sized> Read for HiddenReader<'c, C> {
+    fn read(&mut self, buf: &mut [u8])-> std::io::Result<usize> {
+        let available = self.hidden_size.saturating_sub(self.position);
+        let read_bytes = min(buf.len() as u64, available);
+        if read_bytes == 0 {
+            return Ok(0);
+        }
+        let bytes = self.decode_range(self.position * 8, (self.position + read_bytes) * 8);
+        buf[..bytes.len()].copy_from_slice(&bytes);
+        self.position += read_bytes;
+        Ok(read_bytes as usize)
+    }
+}
+
+/// Seeking beyond hidden data end is allowed, but reads return no data there.
+impl<'c, C: Carrier + ?Sized> Seek for HiddenReader<'c, C> {
+    fn seek(&mut self, position: SeekFrom)-> std::io::Result<u64> {
+        let (base, offset) = match position {
+            SeekFrom::Start(offset)=> (0, offset as i64),
+            SeekFrom::End(offset)=> (self.hidden_size, offset),
+            SeekFrom::Current(offset)=> (self.position, offset),
+        };
+        let new_position = base as i64 + offset;
+        if new_position < 0 {
+            return Err(std::io::Error::new(std::io::ErrorKind::InvalidInput,
+                                           "Tried to seek before hidden data start."));
+        }
+        self.position = new_position as u64;
+        Ok(self.position)
+    }
+}
+
+/// Writer that hides data into a carrier as it is written.
+///
+/// Extraction derives bits per sample from hidden data size, using as few of them as possible.
+/// So writing starts hiding a bit per sample and, whenever written data does not fit any longer,
+/// chunks already hidden are laid out again with one more bit per sample. That only happens at
+/// most *sample_bits()* times and needs no memory, as chunks are moved in place. Samples left
+/// behind by earlier layouts keep their changed bits though, so streamed data changes more
+/// samples than hiding the same data at once.
+///
+/// Header is only written by *finish()*, so hidden data is not readable if writer is dropped
+/// before. Header only stores hidden data size, as steganer format has no room for a checksum.
+pub struct HiddenWriter<'c, C: Carrier + ?Sized> {
+    /// Host to hide data into.
+    carrier: &'c mut C,
+    /// Samples available for hidden data after header.
+    usable_samples: u64,
+    /// Bytes that can be hidden at most into carrier.
+    max_bytes: u64,
+    /// Bits hidden per sample with current layout.
+    chunk_size: u8,
+    /// Bytes written so far.
+    written_bytes: u64,
+    /// Complete chunks already hidden into carrier.
+    hidden_chunks: u64,
+    /// Written bits not hidden yet, as they do not fill a chunk. Right justified. Chunks can
+    /// be 64 bits long, so pending bits may take more than 64 bits while a chunk is filled.
+    pending_bits: u128,
+    /// How many bits at *self.pending_bits* are actual data.
+    pending_length: u8,
+}
+
+impl<'c, C: Carrier + ?Sized> HiddenWriter<'c, C> {
+    /// Prepare hiding data into given carrier.
+    ///
+    /// # Parameters:
+    /// * carrier: Host to hide data into. Header samples are reserved until *finish()*.
+    ///
+    /// # Returns:
+    /// * Writer placed at hidden data start.
+    pub fn new(carrier: &'c mut C)-> Self {
+        let usable_samples = usable_samples(carrier);
+        let max_bytes = max_payload_bytes(carrier);
+        HiddenWriter{carrier, usable_samples, max_bytes, chunk_size: 1, written_bytes: 0,
+                     hidden_chunks: 0, pending_bits: 0, pending_length: 0}
+    }
+
+    /// Get how many bytes have been written so far.
+    pub fn written_bytes(&self)-> u64 {
+        self.written_bytes
+    }
+
+    /// Write hidden data header and save carrier.
+    ///
+    /// # Returns:
+    /// * Bits hidden per sample.
+    /// * An error if carrier is too short to hold a header or could not be saved.
+    pub fn finish(self)-> Result<u8> {
+        // Written data always fits, but carrier may be too short to hold a header.
+        let chunk_size = chunk_size(self.carrier, self.written_bytes as u32)?;
+        if self.pending_length > 0 {
+            // Last chunk only gets bits that are left, so it is shorter than the others.
+            encode_bits(self.carrier, HEADER_SAMPLE_LENGTH as u64 + self.hidden_chunks,
+                        (self.pending_bits & mask::<u128>(self.pending_length, false)) as u64,
+                        self.pending_length);
+        }
+        encode_header(self.carrier, self.written_bytes as u32);
+        self.carrier.save()?;
+        Ok(chunk_size)
+    }
+
+    /// Hide every complete chunk of pending bits.
+    fn hide_pending_chunks(&mut self) {
+        while self.pending_length >= self.chunk_size {
+            self.pending_length -= self.chunk_size;
+            let chunk = (self.pending_bits >> self.pending_length) & mask::<u128>(self.chunk_size, false);
+            encode_bits(self.carrier, HEADER_SAMPLE_LENGTH as u64 + self.hidden_chunks,
+                        chunk as u64, self.chunk_size);
+            self.hidden_chunks += 1;
+        }
+    }
+
+    /// Read bits already hidden with current layout.
+    ///
+    /// # Parameters:
+    /// * first_bit: Offset in bits of first bit to read.
+    /// * length: How many bits to read. They must be at complete chunks already hidden.
+    ///
+    /// # Returns:
+    /// * Read bits, right justified.
+    fn read_hidden_bits(&self, first_bit: u64, length: u8)-> u128 {
+        let chunk_size = self.chunk_size as u64;
+        let end_bit = first_bit + length as u64;
+        let mut bits = 0_u128;
+        let mut bit = first_bit;
+        while bit < end_bit {
+            let chunk = bit / chunk_size;
+            let skipped = bit - chunk * chunk_size;
+            let taken = min(chunk_size - skipped, end_bit - bit);
+            let data = decode_bits(self.carrier, HEADER_SAMPLE_LENGTH as u64 + chunk,
+                                   self.chunk_size) as u128;
+            bits = (bits << taken) | ((data >> (chunk_size - skipped - taken)) & mask::<u128>(taken as u8, false));
+            bit += taken;
+        }
+        bits
+    }
+
+    /// Lay out again chunks already hidden, with one more bit per sample.
+    ///
+    /// Bits of new chunk N always come from old chunks N or later, so chunks can be moved in
+    /// place from first to last. Bits left that do not fill a new chunk are taken back as
+    /// pending ones.
+    fn grow_chunk_size(&mut self) {
+        let hidden_bits = self.hidden_chunks * self.chunk_size as u64;
+        let new_chunk_size = self.chunk_size + 1;
+        let new_chunks = hidden_bits / new_chunk_size as u64;
+        for chunk in 0..new_chunks {
+            let data = self.read_hidden_bits(chunk * new_chunk_size as u64, new_chunk_size);
+            encode_bits(self.carrier, HEADER_SAMPLE_LENGTH as u64 + chunk, data as u64, new_chunk_size);
+        }
+        let left_length = (hidden_bits - new_chunks * new_chunk_size as u64) as u8;
+        let left_bits = self.read_hidden_bits(new_chunks * new_chunk_size as u64, left_length);
+        self.pending_bits = (left_bits << self.pending_length) |
+            (self.pending_bits & mask::<u128>(self.pending_length, false));
+        self.pending_length += left_length;
+        self.chunk_size = new_chunk_size;
+        self.hidden_chunks = new_chunks;
+        self.hide_pending_chunks();
+    }
+}
+
+/// Writing as much as carrier can hold is accepted, but once it is full writes fail with
+/// a PayloadTooLarge error message.
+impl<'c, C: Carrier + ?Sized> Write for HiddenWriter<'c, C> {
+    fn write(&mut self, buf: &[u8])-> std::io::Result<usize> {
+        for (written, &byte) in buf.iter().enumerate() {
+            if self.written_bytes >= self.max_bytes {
+                if written > 0 {
+                    return Ok(written);
+                }
+                let error = Error::from(ErrorKind::PayloadTooLarge(self.written_bytes + buf.len() as u64,
+                                                                   self.max_bytes));
+                return Err(std::io::Error::other(error.to_string()));
+            }
+            self.written_bytes += 1;
+            while self.written_bytes * 8 > self.chunk_size as u64 * self.usable_samples {
+                self.grow_chunk_size();
+            }
+            self.pending_bits = (self.pending_bits << 8) | byte as u128;
+            self.pending_length += 8;
+            self.hide_pending_chunks();
+        }
+        Ok(buf.len())
+    }
+
+    /// Every complete chunk is already hidden, so there is nothing to flush until *finish()*.
+    fn flush(&mut self)-> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::stegimage::PixelBuffer;
+
+    /// Create a pixel buffer just big enough to need given bits per pixel to hide some bytes.
+    fn blank_pixels(data_size: usize, bits_per_pixel: usize)-> PixelBuffer {
+        let width = 64;
+        let pixels = HEADER_SAMPLE_LENGTH as usize + (data_size * 8).div_ceil(bits_per_pixel);
+        let height = pixels.div_ceil(width);
+        PixelBuffer::new(vec![0x5a; width * height * 3], width as u32, height as u32, 3).unwrap()
+    }
+
+    /// Hide data into a pixel buffer just big enough to need given bits per pixel.
+    fn hidden_pixels(data: &[u8], bits_per_pixel: usize)-> PixelBuffer {
+        let mut carrier = blank_pixels(data.len(), bits_per_pixel);
+        hide_bytes_into_carrier(data, &mut carrier).expect("Error hiding test data");
+        carrier
+    }
+
+    #[test]
+    fn test_read_whole_data() {
+        let data: Vec<u8> = (0..1000_u32).map(|i| (i * 13 + i / 7) as u8).collect();
+        for &bits_per_pixel in [1, 5, 11, 24].iter() {
+            let carrier = hidden_pixels(&data, bits_per_pixel);
+            let mut reader = HiddenReader::new(&carrier).unwrap();
+            assert_eq!(data.len() as u64, reader.hidden_size());
+            let mut recovered: Vec<u8> = Vec::new();
+            std::io::copy(&mut reader, &mut recovered).expect("Error copying hidden data");
+            assert!(data == recovered, "Recovered data differs at {} bits per pixel.", bits_per_pixel);
+        }
+    }
+
+    #[test]
+    fn test_seek_and_read_range() {
+        let data: Vec<u8> = (0..1000_u32).map(|i| (i * 13 + i / 7) as u8).collect();
+        let carrier = hidden_pixels(&data, 7);
+        let mut reader = HiddenReader::new(&carrier).unwrap();
+        let mut block = [0_u8; 100];
+        assert_eq!(333, reader.seek(SeekFrom::Start(333)).unwrap());
+        reader.read_exact(&mut block).unwrap();
+        assert_eq!(&data[333..433], &block[..]);
+        assert_eq!(990, reader.seek(SeekFrom::End(-10)).unwrap());
+        assert_eq!(10, reader.read(&mut block).unwrap());
+        assert_eq!(&data[990..], &block[..10]);
+        assert_eq!(0, reader.read(&mut block).unwrap());
+        assert_eq!(500, reader.seek(SeekFrom::Current(-500)).unwrap());
+        assert!(reader.seek(SeekFrom::Current(-501)).is_err());
+    }
+
+    #[test]
+    fn test_write_same_pixels_as_hiding() {
+        let data: Vec<u8> = (0..1000_u32).map(|i| (i * 13 + i / 7) as u8).collect();
+        for &bits_per_pixel in [1, 5, 11, 24].iter() {
+            let mut hidden = blank_pixels(data.len(), bits_per_pixel);
+            let chunk_size = hide_bytes_into_carrier(&data, &mut hidden).unwrap();
+            let mut streamed = blank_pixels(data.len(), bits_per_pixel);
+            let mut writer = HiddenWriter::new(&mut streamed);
+            // Uneven writes, so chunks are grown at the middle of them.
+            for block in data.chunks(37) {
+                writer.write_all(block).expect("Error writing hidden data");
+            }
+            assert_eq!(data.len() as u64, writer.written_bytes());
+            assert_eq!(chunk_size, writer.finish().unwrap());
+            assert_eq!(data, extract_bytes_from_carrier(&streamed).unwrap());
+            // Samples beyond last complete chunk may keep bits changed by narrower layouts.
+            let used_bytes = (HEADER_SAMPLE_LENGTH as usize + data.len() * 8 / chunk_size as usize) * 3;
+            assert!(hidden.into_pixels()[..used_bytes] == streamed.into_pixels()[..used_bytes],
+                    "Streamed hiding got different pixels at {} bits per pixel.", chunk_size);
+        }
+    }
+
+    #[test]
+    fn test_write_too_much() {
+        let mut carrier = PixelBuffer::new(vec![0; (32 + 8) * 3], 40, 1, 3).unwrap();
+        let mut writer = HiddenWriter::new(&mut carrier);
+        // 8 pixels hold 24 bytes at most.
+        assert_eq!(24, writer.write(&[0xa5; 30]).unwrap());
+        assert!(writer.write(&[0xa5]).is_err());
+        writer.finish().unwrap();
+        assert_eq!(vec![0xa5; 24], extract_bytes_from_carrier(&carrier).unwrap());
+    }
+
+    #[test]
+    fn test_write_nothing() {
+        let mut carrier = PixelBuffer::new(vec![0xff; 64 * 3], 64, 1, 3).unwrap();
+        assert_eq!(0, HiddenWriter::new(&mut carrier).finish().unwrap());
+        assert!(extract_bytes_from_carrier(&carrier).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_carrier_shorter_than_header() {
+        let mut carrier = PixelBuffer::new(vec![0; 4 * 3], 4, 1, 3).unwrap();
+        let error = HiddenReader::new(&carrier).err().expect("Short carrier was not detected");
+        assert!(matches!(error_kind(&error), Some(ErrorKind::NoPayload)));
+        assert!(HiddenWriter::new(&mut carrier).finish().is_err());
+    }
+
+    #[test]
+    fn test_no_payload() {
+        let carrier = PixelBuffer::new(vec![0xff; 64 * 3], 64, 1, 3).unwrap();
+        let error = HiddenReader::new(&carrier).err().expect("Invalid header was not detected");
+        assert!(matches!(error_kind(&error), Some(ErrorKind::NoPayload)));
+    }
+}
@@ -1,75 +1,353 @@
+mod analyze;
+mod apngimage;
 pub mod argparser;
+mod builder;
 mod bytetools;
+mod capi;
+mod carrier;
 mod configuration;
 mod fileio;
+mod gifimage;
+mod hiddenio;
+mod parallel;
+mod probe;
+#[cfg(feature = "python")]
+mod python;
+mod quality;
+mod scan;
 mod stegimage;
+mod textcarrier;
+mod visualize;
+mod wavaudio;
+mod wipe;
+mod y4mvideo;
 
-use std::fs::metadata;
-use std::ops::Add;
+use std::fs::{metadata, File};
+use std::io::{BufWriter, Write};
+use std::path::Path;
 
 use error_chain::{error_chain, bail};
-use pyo3::prelude::*;
-use pyo3::{wrap_pyfunction, PyErr, exceptions};
 
-use crate::configuration::Configuration;
-use crate::fileio::{FileContent, ContentReader, FileWriter};
-use crate::stegimage::ContainerImage;
+use crate::carrier::{chunk_size, decode_header, encode_header, hidden_chunks, hide_content, probe_header,
+                     usable_samples, SIZE_LENGTH};
+use crate::apngimage::supported_apng;
+use crate::fileio::{FileContent, ContentReader, FileWriter, pack_chunks};
+use crate::gifimage::supported_gif;
+use crate::parallel::{extract_from_pixels, hide_into_pixels, with_threads};
+use crate::textcarrier::supported_text;
+use crate::wavaudio::supported_audio;
+use crate::y4mvideo::supported_video;
+pub use crate::apngimage::ContainerApng;
+pub use crate::builder::{Algorithm, Steganer, SteganerBuilder};
+pub use crate::carrier::{extract_bytes_from_carrier, extract_from_carrier, hide_bytes_into_carrier,
+                         hide_into_carrier, Carrier};
+pub use crate::configuration::{Configuration, Operation};
+pub use crate::gifimage::ContainerGif;
+pub use crate::hiddenio::{HiddenReader, HiddenWriter};
+pub use crate::analyze::{analyze_image, AnalysisReport, ChannelAnalysis, Verdict};
+pub use crate::probe::{probe_image, FileType, PayloadInfo, ProbeReport, ProbeStatus};
+pub use crate::quality::QualityReport;
+pub use crate::scan::{scan_image, Finding, ScanCandidate, ScanReport};
+pub use crate::textcarrier::{text_capacity, ContainerText, TextCapacity, TextEncoding};
+pub use crate::stegimage::{BitOrder, Capacity, CapacityLevel, CapacityOptions, ChannelMode, ContainerImage, DumpOptions,
+                           LsbStream, PixelBuffer, Traversal};
+pub use crate::visualize::{render_bit_plane, render_diff, Channel, DiffReport};
+pub use crate::wavaudio::ContainerAudio;
+pub use crate::wipe::{wipe_image, WipeOptions, WipeReport};
+pub use crate::y4mvideo::ContainerVideo;
 
 // This will create the Error, ErrorKind, ResultExt, and Result types.
-error_chain!{}
+error_chain!{
+    errors {
+        // Data to hide does not fit into host file. Sizes are in bytes.
+        PayloadTooLarge(required: u64, available: u64) {
+            description("data to hide is too big for host file")
+            display("File to hide is too big for this host file. Current is {} bytes but maximum \
+                     for this host file is {} bytes", required, available)
+        }
+        // Host file is not of any supported type.
+        UnsupportedFormat(host_type: String, host_file: String) {
+            description("host file type not supported")
+            display("{} type not supported: {}", host_type, host_file)
+        }
+        // Host file has no steganer hidden data.
+        NoPayload {
+            description("no hidden data found")
+            display("Host file has no valid hidden data header.")
+        }
+        // Less hidden data was recovered than its header declared. Sizes are in bytes.
+        TruncatedPayload(expected: u64, recovered: u64) {
+            description("hidden data is truncated")
+            display("Hidden data is truncated. Header declares {} bytes but only {} bytes were \
+                     recovered", expected, recovered)
+        }
+    }
+}
+
+/// Find the most specific error kind of an error chain, skipping plain message errors.
+pub(crate) fn error_kind(errors: &Error)-> Option<&ErrorKind> {
+    std::iter::successors(Some(errors as &(dyn std::error::Error + 'static)),
+                          |error| error.source())
+        .filter_map(|error| error.downcast_ref::<Error>())
+        .map(|error| error.kind())
+        .find(|kind| !matches!(kind, ErrorKind::Msg(_)))
+}
 
 /// Main function in steganer. It runs its main logic.
 ///
 /// If you're using steganer as a library then this function is not useful for you.
 pub fn _run(config: &Configuration) -> Result<()> {
-    if config.extract {
-        extract_from_image(&config.hidden_file, &config.host_file)
-    } else {
-        hide_into_image(&config.hidden_file, &config.host_file)
+    _run_with_exit_code(config)?;
+    Ok(())
+}
+
+/// Run steganer main logic and get the exit code steganer command should return.
+///
+/// Most operations return 0 when they succeed, but info operation returns a different code
+/// depending on what it found. Errors are returned as Err so caller can print them.
+///
+/// If you're using steganer as a library then this function is not useful for you.
+pub fn _run_with_exit_code(config: &Configuration) -> Result<i32> {
+    match config.operation {
+        Operation::Extract=> {
+            if supported_audio(&config.host_file) {
+                extract_from_audio(&config.hidden_file, &config.host_file)?
+            } else if is_animation(&config.host_file) {
+                extract_from_animation(&config.hidden_file, &config.host_file)?
+            } else if supported_video(&config.host_file) {
+                extract_from_video(&config.hidden_file, &config.host_file)?
+            } else if supported_text(&config.host_file) {
+                extract_from_text(&config.hidden_file, &config.host_file, config.text_encoding)?
+            } else {
+                extract_from_image_with_threads(&config.hidden_file, &config.host_file,
+                                                config.threads)?
+            }
+        },
+        Operation::Hide=> {
+            if config.report && !is_image_host(&config.host_file) {
+                bail!("Quality reports are only available for host images.");
+            }
+            if let Some(bits_per_pixel) = config.bits_per_pixel {
+                check_bits_per_pixel(&config.hidden_file, &config.host_file, bits_per_pixel)?;
+            }
+            let host_file = if config.output_file.is_empty() {
+                config.host_file.as_str()
+            } else {
+                copy_host(&config.host_file, &config.output_file)?;
+                config.output_file.as_str()
+            };
+            if supported_audio(host_file) {
+                hide_into_audio(&config.hidden_file, host_file)?
+            } else if is_animation(host_file) {
+                hide_into_animation(&config.hidden_file, host_file)?
+            } else if supported_video(host_file) {
+                hide_into_video(&config.hidden_file, host_file)?
+            } else if supported_text(host_file) {
+                hide_into_text(&config.hidden_file, host_file, config.text_encoding)?
+            } else if config.report {
+                let report = hide(&config.hidden_file, host_file, true, config.threads)?
+                    .expect("Quality report was requested but it was not generated");
+                if config.json { println!("{}", report.to_json()) } else { println!("{}", report) }
+            } else {
+                hide_into_image_with_threads(&config.hidden_file, host_file, config.threads)?
+            }
+        },
+        Operation::Capacity if supported_text(&config.host_file)=> {
+            let capacity = text_capacity(&config.host_file)?;
+            if config.json { println!("{}", capacity.to_json()) } else { println!("{}", capacity) }
+        },
+        Operation::Capacity=> {
+            let options = CapacityOptions{bits_per_pixel: config.bits_per_pixel};
+            let capacity = image_capacity(&config.host_file, &options)?;
+            if config.json {
+                println!("{}", capacity.to_json());
+            } else {
+                println!("{}", capacity);
+            }
+        },
+        Operation::Info=> {
+            let report = probe_image(&config.host_file)?;
+            if config.json {
+                println!("{}", report.to_json());
+            } else {
+                println!("{}", report);
+            }
+            return Ok(report.status.exit_code());
+        },
+        Operation::Analyze=> {
+            let report = analyze_image(&config.host_file)?;
+            if config.json { println!("{}", report.to_json()) } else { println!("{}", report) }
+        },
+        Operation::Planes=> render_bit_plane(&config.host_file, config.channel, config.bit,
+                                              &config.output_file)?,
+        Operation::Diff=> {
+            let report = render_diff(&config.cover_file, &config.host_file, &config.output_file)?;
+            println!("{}", report);
+        },
+        Operation::Dump=> {
+            let dumped_bytes = dump_image(&config.host_file, &config.dump_options, &config.output_file)?;
+            println!("Dumped {} bytes to {}.", dumped_bytes, config.output_file);
+        },
+        Operation::Scan=> {
+            let report = scan_image(&config.host_file)?;
+            if config.json { println!("{}", report.to_json()) } else { println!("{}", report) }
+        },
+        Operation::Wipe=> {
+            let options = WipeOptions{depth: config.wipe_depth};
+            let report = wipe_image(&config.host_file, &options)?;
+            println!("{}", report);
+        },
+        Operation::Verify=> {
+            if verify_image(&config.hidden_file, &config.host_file)? {
+                println!("{} is hidden inside {}.", config.hidden_file, config.host_file);
+            } else {
+                println!("{} is not hidden inside {}.", config.hidden_file, config.host_file);
+                return Ok(2);
+            }
+        },
     }
+    Ok(0)
 }
 
 /// Create a configuration struct.
 ///
-/// This function is only useful for integration tests in order to create configurations to test
-/// run function.
+/// This function is kept for integration tests. Library users should use Steganer::builder()
+/// or Configuration::new() instead.
 pub fn _create_configuration(hidden_file: &str, host_file: &str, extract: bool) -> Configuration {
     Configuration::new(hidden_file, host_file, extract)
 }
 
+/// Check if a host file is a GIF image or an animated PNG image.
+fn is_animation(host_file: &str)-> bool {
+    supported_gif(host_file) || supported_apng(host_file)
+}
+
+/// Check if a host file is a static image, instead of any other kind of supported host file.
+fn is_image_host(host_file: &str)-> bool {
+    !(supported_audio(host_file) || supported_video(host_file) || supported_text(host_file) ||
+      is_animation(host_file))
+}
+
+/// Check a file can be hidden into an image without using more than a given bits per pixel level.
+///
+/// # Parameters:
+/// * file_to_hide: Absolute path to file to hide.
+/// * host_file: Absolute path to image file that is going to contain hidden file.
+/// * bits_per_pixel: Maximum bits per pixel level allowed.
+///
+/// # Returns:
+/// * An error if host is not a static image or file needs more bits per pixel.
+pub(crate) fn check_bits_per_pixel(file_to_hide: &str, host_file: &str, bits_per_pixel: u8)-> Result<()> {
+    if !is_image_host(host_file) {
+        bail!("Bits per pixel can only be limited for host images, but host file is {}.", host_file);
+    }
+    let file_to_hide_size = metadata(file_to_hide)
+        .chain_err(|| format!("Error reading file to hide {}", file_to_hide))?
+        .len();
+    check_carrier_bits_per_pixel(file_to_hide_size, &ContainerImage::new(host_file)?, bits_per_pixel)
+}
+
+/// Check some data can be hidden into a carrier without using more than a given bits per pixel
+/// level.
+///
+/// # Parameters:
+/// * payload_size: Size in bytes of data to hide.
+/// * carrier: Carrier that is going to contain hidden data.
+/// * bits_per_pixel: Maximum bits per pixel level allowed.
+///
+/// # Returns:
+/// * An error if bits per pixel level is out of carrier range or data needs more bits per pixel.
+pub(crate) fn check_carrier_bits_per_pixel<C: Carrier + ?Sized>(payload_size: u64, carrier: &C,
+                                                               bits_per_pixel: u8)-> Result<()> {
+    if bits_per_pixel == 0 || bits_per_pixel > carrier.sample_bits() {
+        bail!("Bits per pixel must be between 1 and {}, but {} was requested.",
+              carrier.sample_bits(), bits_per_pixel);
+    }
+    // Hidden data size is stored in an u32 header, so bigger payloads can not be hidden.
+    let max_payload_bytes = std::cmp::min(usable_samples(carrier) * bits_per_pixel as u64 / 8,
+                                          u32::MAX as u64);
+    if payload_size > max_payload_bytes {
+        bail!(ErrorKind::PayloadTooLarge(payload_size, max_payload_bytes));
+    }
+    Ok(())
+}
+
+/// Copy a host file to hide data into that copy, leaving original host file untouched.
+///
+/// # Parameters:
+/// * host_file: Absolute path to original host file.
+/// * output_file: Absolute path to copy. It must have the same extension as host file, as that
+///   extension sets which kind of host file it is.
+fn copy_host(host_file: &str, output_file: &str)-> Result<()> {
+    let extension = |file: &str| Path::new(file).extension()
+        .map(|extension| extension.to_string_lossy().to_lowercase());
+    if extension(host_file) != extension(output_file) {
+        bail!("Output file {} must have the same extension as host file {}.", output_file, host_file);
+    }
+    std::fs::copy(host_file, output_file)
+        .chain_err(|| format!("Error copying host file {} to {}", host_file, output_file))?;
+    Ok(())
+}
+
 /// Extract a file hidden into an image using steganography techniques.
 ///
 /// # Parameters:
 /// * hidden_file: Absolute path to file to hide.
 /// * host_file: Absolute path to image file that is going to contain hidden file.
 pub fn extract_from_image(hidden_file: &str, host_file: &str)-> Result<()> {
-    let mut host_image = ContainerImage::new(host_file)?;
-    host_image.setup_hidden_data_extraction();
-    let mut extracted_file = FileWriter::new(hidden_file)
-        .chain_err(||"Error creating destination file to store extracted data")?;
-    for chunk in host_image {
-        extracted_file.write(&chunk)?;
+    let host_image = ContainerImage::new(host_file)?;
+    extract_from_carrier(hidden_file, &host_image)
+}
+
+/// Extract a file hidden into an image, spreading work across several threads.
+///
+/// Extracted file is exactly the same one extract_from_image() gets.
+///
+/// # Parameters:
+/// * hidden_file: Absolute path to file to store extracted data.
+/// * host_file: Absolute path to image file that contains hidden file.
+/// * threads: Threads to use. 0 means one thread per CPU core and 1 means no thread is spawned.
+pub fn extract_from_image_with_threads(hidden_file: &str, host_file: &str, threads: usize)-> Result<()> {
+    if threads == 1 {
+        return extract_from_image(hidden_file, host_file);
     }
+    let host_image = ContainerImage::new(host_file)?;
+    let hidden_file_size = decode_header(&host_image)?;
+    let chunk_size = chunk_size(&host_image, hidden_file_size)
+        .chain_err(|| ErrorKind::NoPayload)?;
+    let (pixels, channels) = host_image.raw_pixels();
+    let hidden_data = with_threads(threads, || {
+        extract_from_pixels(pixels, channels, hidden_file_size as usize, chunk_size)
+    })?;
+    std::fs::write(hidden_file, hidden_data)
+        .chain_err(||"Error creating destination file to store extracted data")?;
     Ok(())
 }
 
-/// Exported version of extract_from_image() for python module.
+/// Extract data hidden into an image, keeping it in memory instead of writing it to a file.
 ///
 /// # Parameters:
-/// * hidden_file: Absolute path to file to hide.
-/// * host_file: Absolute path to image file that is going to contain hidden file.
-#[pyfunction]
-fn unhide_from_image(hidden_file: &str, host_file: &str)-> PyResult<()> {
-    match extract_from_image(hidden_file, host_file) {
-        Ok(())=> Ok(()),
-        Err(ref errors)=> {
-            let mut message = String::new();
-            for (index, error) in errors.iter().enumerate() {
-                message = message.add(format!("\t {} --> {}", index, error).as_str());
-            }
-            Err(PyErr::new::<exceptions::IOError, _>(message))
-        },
+/// * host_file: Absolute path to image file that contains hidden data.
+///
+/// # Returns:
+/// * Hidden bytes.
+pub fn extract_bytes_from_image(host_file: &str)-> Result<Vec<u8>> {
+    let host_image = ContainerImage::new(host_file)?;
+    extract_bytes_from_carrier(&host_image)
+}
+
+/// Hide data already in memory into an image using steganography techniques.
+///
+/// # Parameters:
+/// * data: Bytes to hide.
+/// * host_file: Absolute path to image file that is going to contain hidden data.
+pub fn hide_bytes_into_image(data: &[u8], host_file: &str)-> Result<()> {
+    if data.len() as u64 > u32::MAX as u64 {
+        bail!(ErrorKind::PayloadTooLarge(data.len() as u64, u32::MAX as u64));
     }
+    let content = FileContent::from_bytes(data.to_vec());
+    hide_content_into_image(&content, data.len() as u32, host_file, false, 1)?;
+    Ok(())
 }
 
 /// Hide a file into into an image using steganography techniques.
@@ -78,47 +356,299 @@ fn unhide_from_image(hidden_file: &str, host_file: &str)-> PyResult<()> {
 /// * file_to_hide: Absolute path to hidden file.
 /// * host_file: Absolute path to image file that contains hidden file.
 pub fn hide_into_image(file_to_hide: &str, host_file: &str)-> Result<()> {
+    hide(file_to_hide, host_file, false, 1)?;
+    Ok(())
+}
+
+/// Hide a file into an image, spreading work across several threads.
+///
+/// Resulting image is exactly the same one hide_into_image() gets.
+///
+/// # Parameters:
+/// * file_to_hide: Absolute path to hidden file.
+/// * host_file: Absolute path to image file that contains hidden file.
+/// * threads: Threads to use. 0 means one thread per CPU core and 1 means no thread is spawned.
+pub fn hide_into_image_with_threads(file_to_hide: &str, host_file: &str, threads: usize)-> Result<()> {
+    hide(file_to_hide, host_file, false, threads)?;
+    Ok(())
+}
+
+/// Hide a file into an image and report how much that image was degraded.
+///
+/// Original image is kept in memory until hiding is done, to compare it with resulting image.
+///
+/// # Parameters:
+/// * file_to_hide: Absolute path to hidden file.
+/// * host_file: Absolute path to image file that contains hidden file.
+///
+/// # Returns:
+/// * Quality report comparing host image before and after hiding.
+pub fn hide_into_image_with_report(file_to_hide: &str, host_file: &str)-> Result<QualityReport> {
+    let report = hide(file_to_hide, host_file, true, 1)?
+        .expect("Quality report was requested but it was not generated");
+    Ok(report)
+}
+
+/// Hide a file into an image, generating a quality report if requested.
+///
+/// # Parameters:
+/// * file_to_hide: Absolute path to hidden file.
+/// * host_file: Absolute path to image file that contains hidden file.
+/// * report: Whether a quality report should be generated.
+/// * threads: Threads to use. 0 means one thread per CPU core and 1 means no thread is spawned.
+///
+/// # Returns:
+/// * Quality report if it was requested or None if not.
+fn hide(file_to_hide: &str, host_file: &str, report: bool, threads: usize)-> Result<Option<QualityReport>> {
+    let (file_to_hide_content, file_to_hide_size) = read_file_to_hide(file_to_hide)?;
+    hide_content_into_image(&file_to_hide_content, file_to_hide_size, host_file, report, threads)
+}
+
+/// Hide content into an image, generating a quality report if requested.
+///
+/// # Parameters:
+/// * file_to_hide_content: Content to hide.
+/// * file_to_hide_size: Content size in bytes.
+/// * host_file: Absolute path to image file that contains hidden file.
+/// * report: Whether a quality report should be generated.
+/// * threads: Threads to use. 0 means one thread per CPU core and 1 means no thread is spawned.
+///
+/// # Returns:
+/// * Quality report if it was requested or None if not.
+fn hide_content_into_image(file_to_hide_content: &FileContent, file_to_hide_size: u32,
+                           host_file: &str, report: bool, threads: usize)-> Result<Option<QualityReport>> {
+    let file_to_hide_size = file_to_hide_size as u64;
+    let mut host_image = ContainerImage::new(host_file)?;
+    let max_payload_bytes = host_image.capacity(&CapacityOptions::default())?
+        .max_payload_bytes();
+    if file_to_hide_size > max_payload_bytes {
+        bail!(ErrorKind::PayloadTooLarge(file_to_hide_size, max_payload_bytes));
+    }
+    let cover_image = if report { Some(host_image.to_rgb_image()) } else { None };
+    let chunk_size = if threads == 1 {
+        hide_content(&mut host_image, file_to_hide_content, file_to_hide_size as u32)?
+    } else {
+        let chunk_size = chunk_size(&host_image, file_to_hide_size as u32)?;
+        // Image is only modified once thread pool is ready, so a failed pool leaves it untouched.
+        let host_image = &mut host_image;
+        with_threads(threads, move || {
+            encode_header(host_image, file_to_hide_size as u32);
+            let (pixels, channels) = host_image.raw_pixels_mut();
+            hide_into_pixels(pixels, channels, file_to_hide_content.bytes(), chunk_size)
+        })?;
+        chunk_size
+    };
+    let quality_report = cover_image.map(|cover_image| {
+        let hidden_bits = SIZE_LENGTH as u64 + file_to_hide_size * 8;
+        QualityReport::compare(&cover_image, &host_image.to_rgb_image(), hidden_bits, chunk_size)
+    });
+    host_image.save()?;
+    Ok(quality_report)
+}
+
+/// Read a file to hide and check its size fits into a hidden data header.
+///
+/// # Parameters:
+/// * file_to_hide: Absolute path to file to hide.
+///
+/// # Returns:
+/// * File to hide content and its size in bytes.
+fn read_file_to_hide(file_to_hide: &str)-> Result<(FileContent, u32)> {
     let file_to_hide_content = FileContent::new(file_to_hide)
         .chain_err(||"Error creating file to hide content handle.")?;
     let file_to_hide_size = metadata(file_to_hide)
         .chain_err(||"Error accessing file to hide metadata.")?
         .len();
-    if file_to_hide_size > std::u32::MAX as u64 {
-        bail!("File to hide is too big. Maximum size is {}", std::u32::MAX);
-    } else {
-        let mut host_image = ContainerImage::new(host_file)?;
-        let chunk_size = host_image.setup_hiding(file_to_hide_size as u32);
-        let file_to_hide_reader = ContentReader::new(&file_to_hide_content, chunk_size);
-        for chunk in file_to_hide_reader {
-            host_image.hide_data(&chunk);
-        }
+    if file_to_hide_size > u32::MAX as u64 {
+        bail!(ErrorKind::PayloadTooLarge(file_to_hide_size, u32::MAX as u64));
     }
+    Ok((file_to_hide_content, file_to_hide_size as u32))
+}
+
+/// Hide a file into a WAV audio file using steganography techniques.
+///
+/// Supported audio files are uncompressed PCM ones, with 8, 16 or 24 bits per sample and one or
+/// two channels. Hidden data is laid out just like at images, using audio samples instead of
+/// pixels.
+///
+/// # Parameters:
+/// * file_to_hide: Absolute path to hidden file.
+/// * host_file: Absolute path to WAV file that is going to contain hidden file.
+pub fn hide_into_audio(file_to_hide: &str, host_file: &str)-> Result<()> {
+    let mut host_audio = ContainerAudio::new(host_file)?;
+    hide_into_carrier(file_to_hide, &mut host_audio)?;
     Ok(())
 }
 
-/// Exported version of hide_into_image() for python module.
+/// Extract a file hidden into a WAV audio file using steganography techniques.
+///
+/// # Parameters:
+/// * hidden_file: Absolute path to file to store extracted data.
+/// * host_file: Absolute path to WAV file that contains hidden file.
+pub fn extract_from_audio(hidden_file: &str, host_file: &str)-> Result<()> {
+    let host_audio = ContainerAudio::new(host_file)?;
+    extract_from_carrier(hidden_file, &host_audio)
+}
+
+/// Hide a file across every frame of an animated image using steganography techniques.
+///
+/// Supported animated images are GIF ones, animated or not, and 8 bits truecolor APNG ones.
+/// Frames are used in the same order they are stored at file, so no frame mapping is needed
+/// to extract hidden data. Frame timing, disposal and loop metadata are kept.
 ///
 /// # Parameters:
 /// * file_to_hide: Absolute path to hidden file.
-/// * host_file: Absolute path to image file that contains hidden file.
-#[pyfunction]
-fn hide_inside_image(file_to_hide: &str, host_file: &str)-> PyResult<()> {
-    match hide_into_image(file_to_hide, host_file) {
-        Ok(())=> Ok(()),
-        Err(ref errors)=> {
-            let mut message = String::new();
-            for (index, error) in errors.iter().enumerate() {
-                message = message.add(format!("\t {} --> {}", index, error).as_str());
-            }
-            Err(PyErr::new::<exceptions::IOError, _>(message))
-        },
+/// * host_file: Absolute path to GIF or APNG file that is going to contain hidden file.
+pub fn hide_into_animation(file_to_hide: &str, host_file: &str)-> Result<()> {
+    if supported_gif(host_file) {
+        let mut host_gif = ContainerGif::new(host_file)?;
+        hide_into_carrier(file_to_hide, &mut host_gif)?;
+    } else {
+        let mut host_apng = ContainerApng::new(host_file)?;
+        hide_into_carrier(file_to_hide, &mut host_apng)?;
     }
+    Ok(())
 }
 
-/// Export to create a steganer python module.
-#[pymodule]
-fn steganer(_py: Python, m: &PyModule)-> PyResult<()>{
-    m.add_wrapped(wrap_pyfunction!(unhide_from_image))?;
-    m.add_wrapped(wrap_pyfunction!(hide_inside_image))?;
+/// Extract a file hidden across frames of an animated image using steganography techniques.
+///
+/// # Parameters:
+/// * hidden_file: Absolute path to file to store extracted data.
+/// * host_file: Absolute path to GIF or APNG file that contains hidden file.
+pub fn extract_from_animation(hidden_file: &str, host_file: &str)-> Result<()> {
+    if supported_gif(host_file) {
+        extract_from_carrier(hidden_file, &ContainerGif::new(host_file)?)
+    } else {
+        extract_from_carrier(hidden_file, &ContainerApng::new(host_file)?)
+    }
+}
+
+/// Hide a file into a Y4M video file using steganography techniques.
+///
+/// Supported video files are uncompressed YUV4MPEG2 ones with 8 bits per sample. Hidden data is
+/// spread over luma and chroma planes of every frame.
+///
+/// # Parameters:
+/// * file_to_hide: Absolute path to hidden file.
+/// * host_file: Absolute path to Y4M file that is going to contain hidden file.
+pub fn hide_into_video(file_to_hide: &str, host_file: &str)-> Result<()> {
+    let mut host_video = ContainerVideo::new(host_file)?;
+    hide_into_carrier(file_to_hide, &mut host_video)?;
     Ok(())
-}
\ No newline at end of file
+}
+
+/// Extract a file hidden into a Y4M video file using steganography techniques.
+///
+/// # Parameters:
+/// * hidden_file: Absolute path to file to store extracted data.
+/// * host_file: Absolute path to Y4M file that contains hidden file.
+pub fn extract_from_video(hidden_file: &str, host_file: &str)-> Result<()> {
+    let host_video = ContainerVideo::new(host_file)?;
+    extract_from_carrier(hidden_file, &host_video)
+}
+
+/// Hide a file into a plain text file without changing its visible text.
+///
+/// Text must be UTF-8 encoded. Use *text_capacity()* to know how much data every encoding can
+/// hide into a text.
+///
+/// # Parameters:
+/// * file_to_hide: Absolute path to hidden file.
+/// * host_file: Absolute path to text file that is going to contain hidden file.
+/// * encoding: Whether to hide data as trailing whitespace at every line or as zero width
+///   characters between words.
+pub fn hide_into_text(file_to_hide: &str, host_file: &str, encoding: TextEncoding)-> Result<()> {
+    let mut host_text = ContainerText::new(host_file, encoding)?;
+    hide_into_carrier(file_to_hide, &mut host_text)?;
+    Ok(())
+}
+
+/// Extract a file hidden into a plain text file.
+///
+/// # Parameters:
+/// * hidden_file: Absolute path to file to store extracted data.
+/// * host_file: Absolute path to text file that contains hidden file.
+/// * encoding: Encoding used to hide data into text.
+pub fn extract_from_text(hidden_file: &str, host_file: &str, encoding: TextEncoding)-> Result<()> {
+    let host_text = ContainerText::new(host_file, encoding)?;
+    extract_from_carrier(hidden_file, &host_text)
+}
+
+/// Check if any item appears more than once at given slice.
+fn has_repeated<T: PartialEq>(items: &[T])-> bool {
+    items.iter().enumerate()
+        .any(|(i, item)| items[i + 1..].contains(item))
+}
+
+/// Dump raw bits of an image to a file, ignoring steganer header.
+///
+/// Image is not modified.
+///
+/// # Parameters:
+/// * host_file: Absolute path to image file to dump bits from.
+/// * options: Which bits to extract and in which order.
+/// * output_file: Absolute path to file to store dumped bytes.
+///
+/// # Returns:
+/// * How many bytes were dumped.
+pub fn dump_image(host_file: &str, options: &DumpOptions, output_file: &str)-> Result<u64> {
+    if options.channels.is_empty() || options.bits.is_empty() {
+        bail!("At least a channel and a bit plane must be given to dump an image.");
+    }
+    if let Some(bit) = options.bits.iter().find(|&&bit| bit > 7) {
+        bail!("Bit planes must be between 0 and 7, but {} was requested.", bit);
+    }
+    if has_repeated(&options.channels) || has_repeated(&options.bits) {
+        bail!("Channels and bit planes to dump must not be repeated.");
+    }
+    let host_image = ContainerImage::new(host_file)?;
+    let output = File::create(output_file)
+        .chain_err(|| format!("Error creating dump file {}", output_file))?;
+    let mut writer = BufWriter::new(output);
+    let mut dumped_bytes = 0_u64;
+    for byte in host_image.lsb_stream(options) {
+        writer.write_all(&[byte])
+            .chain_err(|| "Error writing to dump file.")?;
+        dumped_bytes += 1;
+    }
+    writer.flush()
+        .chain_err(|| "Error writing to dump file.")?;
+    Ok(dumped_bytes)
+}
+
+/// Get how many bytes can be hidden into an image.
+///
+/// # Parameters:
+/// * host_file: Absolute path to image file to analyze.
+/// * options: Which bits per pixel levels to report.
+///
+/// # Returns:
+/// * Capacity report with maximum payload bytes for every requested bits per pixel level.
+pub fn image_capacity(host_file: &str, options: &CapacityOptions)-> Result<Capacity> {
+    let host_image = ContainerImage::new(host_file)?;
+    host_image.capacity(options)
+}
+
+/// Check a file is hidden inside an image.
+///
+/// Image is not modified and hidden data is only extracted to memory to compare it with given
+/// file.
+///
+/// # Parameters:
+/// * hidden_file: Absolute path to file expected to be hidden.
+/// * host_file: Absolute path to image file to check.
+///
+/// # Returns:
+/// * True if hidden data is exactly hidden_file content and false otherwise.
+pub fn verify_image(hidden_file: &str, host_file: &str)-> Result<bool> {
+    let expected_content = std::fs::read(hidden_file)
+        .chain_err(||"Error reading file expected to be hidden.")?;
+    let host_image = ContainerImage::new(host_file)?;
+    match probe_header(&host_image) {
+        Some((hidden_file_size, _)) if hidden_file_size as usize == expected_content.len()=> {
+            let hidden_content = pack_chunks(hidden_chunks(&host_image)?, hidden_file_size as usize);
+            Ok(hidden_content == expected_content)
+        },
+        _=> Ok(false),
+    }
+}
@@ -0,0 +1,189 @@
+/// Module to hide and extract data using several threads at once.
+///
+/// Chunk N of hidden data always lands at sample HEADER_SAMPLE_LENGTH + N, so every chunk can be
+/// hidden or extracted on its own. Chunks are handled in groups of 8, as 8 chunks of chunk_size
+/// bits are exactly chunk_size bytes of hidden data. That way every group reads or writes its
+/// own bytes and groups can be spread across threads.
+///
+/// Only raw RGB or RGBA pixel buffers are handled, as generic carriers can only be written one
+/// sample at a time. Resulting pixels are exactly the same ones sequential hiding produces.
+///
+/// # Usage example:
+/// ```ignore
+/// let (pixels, channels) = host_image.raw_pixels_mut();
+/// with_threads(4, || hide_into_pixels(pixels, channels, &content, chunk_size))?;
+/// ```
+use rayon::prelude::*;
+
+use crate::*;
+use crate::bytetools::mask;
+use crate::carrier::HEADER_SAMPLE_LENGTH;
+
+/// How many chunks fill a whole number of bytes, whatever chunk size is.
+const CHUNKS_PER_GROUP: usize = 8;
+
+/// Run a job using a pool with given amount of threads.
+///
+/// # Parameters:
+/// * threads: Threads to use. 0 means one thread per CPU core.
+/// * job: Work to do.
+///
+/// # Returns:
+/// * Job result.
+/// * An error if thread pool could not be created.
+pub(crate) fn with_threads<T: Send, F: FnOnce()-> T + Send>(threads: usize, job: F)-> Result<T> {
+    let pool = rayon::ThreadPoolBuilder::new()
+        .num_threads(threads)
+        .build()
+        .chain_err(|| "Error creating thread pool")?;
+    Ok(pool.install(job))
+}
+
+/// Read bits from a byte slice, most significant bits first.
+///
+/// # Parameters:
+/// * content: Bytes to read from.
+/// * bit_offset: First bit to read.
+/// * length: How many bits to read. Requested bits must be inside content.
+///
+/// # Returns:
+/// * Read bits, right justified.
+fn read_bits(content: &[u8], bit_offset: usize, length: u8)-> u32 {
+    (bit_offset..bit_offset + length as usize)
+        .fold(0_u32, |bits, position| {
+            (bits << 1) | ((content[position / 8] >> (7 - position % 8)) & 1) as u32
+        })
+}
+
+/// Get RGB value of a raw pixel as a 24 bits sample.
+fn read_pixel(pixel: &[u8])-> u32 {
+    ((pixel[0] as u32) << 16) | ((pixel[1] as u32) << 8) | pixel[2] as u32
+}
+
+/// Overwrite RGB channels of a raw pixel with a 24 bits sample. Alpha channel is kept.
+fn write_pixel(pixel: &mut [u8], sample: u32) {
+    pixel[0] = (sample >> 16) as u8;
+    pixel[1] = (sample >> 8) as u8;
+    pixel[2] = sample as u8;
+}
+
+/// Get how many bits every chunk has.
+///
+/// # Parameters:
+/// * chunk: Chunk index.
+/// * chunk_size: Bits per chunk.
+/// * total_bits: Bits of hidden data.
+///
+/// # Returns:
+/// * Chunk length. Only last chunk may be shorter than chunk_size, and chunks beyond hidden
+///   data are empty.
+fn chunk_length(chunk: usize, chunk_size: u8, total_bits: usize)-> u8 {
+    let first_bit = chunk * chunk_size as usize;
+    std::cmp::min(chunk_size as usize, total_bits.saturating_sub(first_bit)) as u8
+}
+
+/// Hide content at pixels following header, spreading work across current thread pool.
+///
+/// Header is not written, so caller must encode it beforehand.
+///
+/// # Parameters:
+/// * pixels: Raw pixels, row by row.
+/// * channels: Bytes per pixel, 3 for RGB or 4 for RGBA.
+/// * content: Data to hide.
+/// * chunk_size: Bits hidden per pixel.
+pub(crate) fn hide_into_pixels(pixels: &mut [u8], channels: usize, content: &[u8], chunk_size: u8) {
+    if chunk_size == 0 { return; }
+    let total_bits = content.len() * 8;
+    let chunks = total_bits.div_ceil(chunk_size as usize);
+    let groups = chunks.div_ceil(CHUNKS_PER_GROUP);
+    pixels[HEADER_SAMPLE_LENGTH as usize * channels..]
+        .par_chunks_mut(CHUNKS_PER_GROUP * channels)
+        .take(groups)
+        .enumerate()
+        .for_each(|(group, group_pixels)| {
+            for (index, pixel) in group_pixels.chunks_mut(channels).enumerate() {
+                let chunk = group * CHUNKS_PER_GROUP + index;
+                let length = chunk_length(chunk, chunk_size, total_bits);
+                if length == 0 { break; }
+                let bits = read_bits(content, chunk * chunk_size as usize, length);
+                write_pixel(pixel, (read_pixel(pixel) & mask::<u32>(length, true)) | bits);
+            }
+        });
+}
+
+/// Extract data hidden at pixels following header, spreading work across current thread pool.
+///
+/// # Parameters:
+/// * pixels: Raw pixels, row by row.
+/// * channels: Bytes per pixel, 3 for RGB or 4 for RGBA.
+/// * hidden_size: Hidden data size in bytes, as read from header.
+/// * chunk_size: Bits hidden per pixel.
+///
+/// # Returns:
+/// * Hidden data.
+pub(crate) fn extract_from_pixels(pixels: &[u8], channels: usize, hidden_size: usize,
+                                  chunk_size: u8)-> Vec<u8> {
+    if chunk_size == 0 { return Vec::new(); }
+    let total_bits = hidden_size * 8;
+    let chunks = total_bits.div_ceil(chunk_size as usize);
+    let groups = chunks.div_ceil(CHUNKS_PER_GROUP);
+    let group_bytes: Vec<Vec<u8>> = pixels[HEADER_SAMPLE_LENGTH as usize * channels..]
+        .par_chunks(CHUNKS_PER_GROUP * channels)
+        .take(groups)
+        .enumerate()
+        .map(|(group, group_pixels)| {
+            let mut bytes = Vec::with_capacity(chunk_size as usize);
+            let mut pending_bits = 0_u64;
+            let mut pending_length = 0_u8;
+            for (index, pixel) in group_pixels.chunks(channels).enumerate() {
+                let length = chunk_length(group * CHUNKS_PER_GROUP + index, chunk_size, total_bits);
+                if length == 0 { break; }
+                pending_bits = (pending_bits << length) |
+                    (read_pixel(pixel) & mask::<u32>(length, false)) as u64;
+                pending_length += length;
+                while pending_length >= 8 {
+                    pending_length -= 8;
+                    bytes.push((pending_bits >> pending_length) as u8);
+                }
+            }
+            bytes
+        })
+        .collect();
+    group_bytes.concat()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::carrier::{encode_header, hide_content};
+    use crate::fileio::FileContent;
+    use crate::stegimage::PixelBuffer;
+
+    #[test]
+    fn test_read_bits() {
+        assert_eq!(0b1010, read_bits(&[0b1010_0000], 0, 4));
+        assert_eq!(0b11_1110, read_bits(&[0b0000_0011, 0b1110_0000], 6, 6));
+    }
+
+    #[test]
+    fn test_same_pixels_as_sequential_hiding() {
+        let (width, height) = (50_u32, 20_u32);
+        let pixels: Vec<u8> = (0..width * height * 4).map(|i| (i * 31) as u8).collect();
+        // 1000 bytes into 968 pixels need 9 bits per pixel, so last chunk is shorter.
+        let content: Vec<u8> = (0..1000_u32).map(|i| (i * 7 + i / 3) as u8).collect();
+        let mut sequential = PixelBuffer::new(pixels.clone(), width, height, 4).unwrap();
+        let chunk_size = hide_content(&mut sequential, &FileContent::from_bytes(content.clone()),
+                                      content.len() as u32).unwrap();
+        assert_eq!(9, chunk_size);
+        let mut parallel = PixelBuffer::new(pixels, width, height, 4).unwrap();
+        encode_header(&mut parallel, content.len() as u32);
+        let mut parallel_pixels = parallel.into_pixels();
+        with_threads(4, || hide_into_pixels(&mut parallel_pixels, 4, &content, chunk_size))
+            .unwrap();
+        let sequential_pixels = sequential.into_pixels();
+        assert!(sequential_pixels == parallel_pixels, "Parallel hiding changed other pixels.");
+        let extracted = with_threads(3, || extract_from_pixels(&parallel_pixels, 4, content.len(),
+                                                               chunk_size)).unwrap();
+        assert_eq!(content, extracted);
+    }
+}
@@ -0,0 +1,351 @@
+/// Module to inspect host images looking for steganer hidden data.
+///
+/// Probing is non destructive: host image is never written and hidden data is never stored to
+/// any file. Only a few bytes from hidden data start are extracted, in memory, to guess which kind
+/// of file was hidden.
+///
+/// # Usage example:
+/// ```ignore
+/// let report = probe_image("suspicious.png")
+///                 .expect("Error probing image");
+/// if let ProbeStatus::NoPayload = report.status {
+///     println!("Nothing hidden here.");
+/// }
+/// ```
+use std::fmt;
+
+use crate::*;
+use crate::fileio::pack_chunks;
+
+/// Only steganer format version. Its header only stores hidden data size.
+const FORMAT_VERSION: u8 = 1;
+/// How many bytes from hidden data start are extracted to guess its file type.
+const SNIFFED_BYTES: u32 = 512;
+
+/// Kinds of files that can be recognized by their first bytes.
+#[derive(Debug, PartialEq, Copy, Clone)]
+pub enum FileType {
+    Gzip,
+    Bzip2,
+    Xz,
+    Zstd,
+    Zip,
+    SevenZip,
+    Pgp,
+    OpenSsl,
+    Age,
+    Png,
+    Jpeg,
+    Gif,
+    Pdf,
+    Elf,
+    /// No known signature found.
+    Unknown,
+}
+
+impl FileType {
+    /// Guess file type from its first bytes.
+    ///
+    /// # Parameters:
+    /// * data: First bytes of file to check.
+    ///
+    /// # Returns:
+    /// * File type whose signature matches data start, or FileType::Unknown if no one does.
+    pub fn detect(data: &[u8])-> Self {
+        let signatures: [(&[u8], FileType); 14] = [
+            (&[0x1f, 0x8b], FileType::Gzip),
+            (b"BZh", FileType::Bzip2),
+            (&[0xfd, 0x37, 0x7a, 0x58, 0x5a, 0x00], FileType::Xz),
+            (&[0x28, 0xb5, 0x2f, 0xfd], FileType::Zstd),
+            (&[0x50, 0x4b, 0x03, 0x04], FileType::Zip),
+            (&[0x37, 0x7a, 0xbc, 0xaf, 0x27, 0x1c], FileType::SevenZip),
+            (b"-----BEGIN PGP MESSAGE-----", FileType::Pgp),
+            (b"Salted__", FileType::OpenSsl),
+            (b"age-encryption.org/v1", FileType::Age),
+            (&[0x89, 0x50, 0x4e, 0x47, 0x0d, 0x0a, 0x1a, 0x0a], FileType::Png),
+            (&[0xff, 0xd8, 0xff], FileType::Jpeg),
+            (b"GIF8", FileType::Gif),
+            (b"%PDF-", FileType::Pdf),
+            (&[0x7f, 0x45, 0x4c, 0x46], FileType::Elf),
+        ];
+        signatures.iter()
+            .find(|(signature, _)| data.starts_with(signature))
+            .map(|(_, file_type)| *file_type)
+            .unwrap_or(FileType::Unknown)
+    }
+
+    /// Whether this file type is a compressed format.
+    pub fn is_compressed(&self)-> bool {
+        matches!(self, FileType::Gzip | FileType::Bzip2 | FileType::Xz | FileType::Zstd |
+                       FileType::Zip | FileType::SevenZip)
+    }
+
+    /// Whether this file type is an encrypted container.
+    pub fn is_encrypted(&self)-> bool {
+        matches!(self, FileType::Pgp | FileType::OpenSsl | FileType::Age)
+    }
+}
+
+impl fmt::Display for FileType {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match self {
+            FileType::Gzip=> "gzip",
+            FileType::Bzip2=> "bzip2",
+            FileType::Xz=> "xz",
+            FileType::Zstd=> "zstd",
+            FileType::Zip=> "zip",
+            FileType::SevenZip=> "7z",
+            FileType::Pgp=> "pgp",
+            FileType::OpenSsl=> "openssl",
+            FileType::Age=> "age",
+            FileType::Png=> "png",
+            FileType::Jpeg=> "jpeg",
+            FileType::Gif=> "gif",
+            FileType::Pdf=> "pdf",
+            FileType::Elf=> "elf",
+            FileType::Unknown=> "unknown",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+/// Probe verdict about a host image.
+#[derive(Debug, PartialEq, Copy, Clone)]
+pub enum ProbeStatus {
+    /// Image header points to hidden data.
+    PayloadFound,
+    /// Image header points to hidden data and that data is encrypted.
+    EncryptedPayload,
+    /// No hidden data found.
+    NoPayload,
+}
+
+impl ProbeStatus {
+    /// Get exit code steganer command returns for this status.
+    ///
+    /// Code 1 is not used because steganer uses it to notify errors.
+    pub fn exit_code(&self)-> i32 {
+        match self {
+            ProbeStatus::PayloadFound=> 0,
+            ProbeStatus::NoPayload=> 2,
+            ProbeStatus::EncryptedPayload=> 3,
+        }
+    }
+}
+
+impl fmt::Display for ProbeStatus {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let status = match self {
+            ProbeStatus::PayloadFound=> "payload found",
+            ProbeStatus::EncryptedPayload=> "encrypted payload found",
+            ProbeStatus::NoPayload=> "no payload",
+        };
+        write!(f, "{}", status)
+    }
+}
+
+/// Details about hidden data found at a host image.
+#[derive(Debug, PartialEq, Clone)]
+pub struct PayloadInfo {
+    /// Steganer format version used to hide data.
+    pub format_version: u8,
+    /// Hidden data size in bytes.
+    pub size: u32,
+    /// Bits used at every pixel to hide data.
+    pub bits_per_pixel: u8,
+    /// Type of hidden file, guessed from its first bytes.
+    pub file_type: FileType,
+    /// Whether hidden file is compressed.
+    pub compressed: bool,
+    /// Whether hidden file is encrypted.
+    pub encrypted: bool,
+    /// Original hidden file name. Steganer does not store it, so it is only available if hidden
+    /// file format keeps it (gzip and zip do).
+    pub original_file_name: Option<String>,
+}
+
+/// Result of probing a host image.
+#[derive(Debug, PartialEq, Clone)]
+pub struct ProbeReport {
+    /// Probed image.
+    pub host_file: String,
+    /// Probe verdict.
+    pub status: ProbeStatus,
+    /// Details about hidden data. None if no hidden data was found.
+    pub payload: Option<PayloadInfo>,
+}
+
+impl ProbeReport {
+    /// Render this report as a JSON object.
+    pub fn to_json(&self)-> String {
+        let payload = match &self.payload {
+            Some(payload)=> {
+                let original_file_name = match &payload.original_file_name {
+                    Some(name)=> format!("\"{}\"", escape_json(name)),
+                    None=> String::from("null"),
+                };
+                format!("{{\"format_version\": {}, \"size\": {}, \"bits_per_pixel\": {}, \
+                         \"file_type\": \"{}\", \"compressed\": {}, \"encrypted\": {}, \
+                         \"original_file_name\": {}}}",
+                        payload.format_version, payload.size, payload.bits_per_pixel,
+                        payload.file_type, payload.compressed, payload.encrypted,
+                        original_file_name)
+            },
+            None=> String::from("null"),
+        };
+        format!("{{\"host_file\": \"{}\", \"status\": \"{}\", \"payload\": {}}}",
+                escape_json(&self.host_file), self.status, payload)
+    }
+}
+
+impl fmt::Display for ProbeReport {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}: {}", self.host_file, self.status)?;
+        if let Some(payload) = &self.payload {
+            writeln!(f, ".")?;
+            writeln!(f, "Format version: {}", payload.format_version)?;
+            writeln!(f, "Payload size: {} bytes", payload.size)?;
+            writeln!(f, "Bits per pixel: {}", payload.bits_per_pixel)?;
+            writeln!(f, "File type: {}", payload.file_type)?;
+            writeln!(f, "Compressed: {}", if payload.compressed {"yes"} else {"no"})?;
+            writeln!(f, "Encrypted: {}", if payload.encrypted {"yes"} else {"no"})?;
+            match &payload.original_file_name {
+                Some(name)=> write!(f, "Original file name: {}", name),
+                None=> write!(f, "Original file name: unknown"),
+            }
+        } else {
+            write!(f, ".")
+        }
+    }
+}
+
+/// Escape characters not allowed inside JSON strings.
+pub(crate) fn escape_json(text: &str)-> String {
+    let mut escaped = String::with_capacity(text.len());
+    for character in text.chars() {
+        match character {
+            '"'=> escaped.push_str("\\\""),
+            '\\'=> escaped.push_str("\\\\"),
+            '\n'=> escaped.push_str("\\n"),
+            '\r'=> escaped.push_str("\\r"),
+            '\t'=> escaped.push_str("\\t"),
+            c if (c as u32) < 0x20 => escaped.push_str(&format!("\\u{:04x}", c as u32)),
+            c=> escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// Get original file name from a gzip header, if it was stored.
+fn gzip_file_name(data: &[u8])-> Option<String> {
+    const FLAG_EXTRA: u8 = 0x04;
+    const FLAG_NAME: u8 = 0x08;
+    const HEADER_LENGTH: usize = 10;
+    let flags = *data.get(3)?;
+    if flags & FLAG_NAME == 0 { return None; }
+    let mut name_start = HEADER_LENGTH;
+    if flags & FLAG_EXTRA != 0 {
+        let extra_length = *data.get(name_start)? as usize + ((*data.get(name_start + 1)? as usize) << 8);
+        name_start += 2 + extra_length;
+    }
+    let name_length = data.get(name_start..)?.iter().position(|&byte| byte == 0)?;
+    // Gzip stores names in ISO 8859-1, whose code points map directly to unicode ones.
+    Some(data[name_start..name_start + name_length].iter().map(|&byte| byte as char).collect())
+}
+
+/// Get first file name from a zip local file header.
+fn zip_file_name(data: &[u8])-> Option<String> {
+    const NAME_LENGTH_OFFSET: usize = 26;
+    const NAME_OFFSET: usize = 30;
+    let name_length = *data.get(NAME_LENGTH_OFFSET)? as usize +
+        ((*data.get(NAME_LENGTH_OFFSET + 1)? as usize) << 8);
+    let name = data.get(NAME_OFFSET..NAME_OFFSET + name_length)?;
+    Some(String::from_utf8_lossy(name).into_owned())
+}
+
+/// Check zip local file header encryption flag.
+fn zip_encrypted(data: &[u8])-> bool {
+    const FLAGS_OFFSET: usize = 6;
+    const FLAG_ENCRYPTED: u8 = 0x01;
+    match data.get(FLAGS_OFFSET) {
+        Some(flags)=> flags & FLAG_ENCRYPTED != 0,
+        None=> false,
+    }
+}
+
+/// Build payload details from hidden data first bytes.
+fn analyze_payload(size: u32, bits_per_pixel: u8, first_bytes: &[u8])-> PayloadInfo {
+    let file_type = FileType::detect(first_bytes);
+    let (encrypted, original_file_name) = match file_type {
+        FileType::Gzip=> (false, gzip_file_name(first_bytes)),
+        FileType::Zip=> (zip_encrypted(first_bytes), zip_file_name(first_bytes)),
+        other=> (other.is_encrypted(), None),
+    };
+    PayloadInfo {
+        format_version: FORMAT_VERSION,
+        size,
+        bits_per_pixel,
+        file_type,
+        compressed: file_type.is_compressed(),
+        encrypted,
+        original_file_name,
+    }
+}
+
+/// Inspect an image looking for steganer hidden data.
+///
+/// # Parameters:
+/// * host_file: Absolute path to image file to inspect.
+///
+/// # Returns:
+/// * Report about hidden data found, if any.
+pub fn probe_image(host_file: &str)-> Result<ProbeReport> {
+    let host_image = ContainerImage::new(host_file)?;
+    let payload = match probe_header(&host_image) {
+        Some((size, bits_per_pixel))=> {
+            let bytes_to_sniff = std::cmp::min(size, SNIFFED_BYTES) as usize;
+            let first_bytes = pack_chunks(hidden_chunks(&host_image)?, bytes_to_sniff);
+            Some(analyze_payload(size, bits_per_pixel, &first_bytes))
+        },
+        None=> None,
+    };
+    let status = match &payload {
+        Some(info) if info.encrypted => ProbeStatus::EncryptedPayload,
+        Some(_)=> ProbeStatus::PayloadFound,
+        None=> ProbeStatus::NoPayload,
+    };
+    Ok(ProbeReport{host_file: host_file.to_owned(), status, payload})
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_detect_file_type() {
+        assert_eq!(FileType::Gzip, FileType::detect(&[0x1f, 0x8b, 0x08, 0x00]));
+        assert_eq!(FileType::OpenSsl, FileType::detect(b"Salted__12345678"));
+        assert_eq!(FileType::Unknown, FileType::detect(b"Lorem ipsum"));
+        assert_eq!(FileType::Unknown, FileType::detect(&[]));
+    }
+
+    #[test]
+    fn test_gzip_file_name() {
+        let mut gzip_header: Vec<u8> = vec![0x1f, 0x8b, 0x08, 0x08, 0, 0, 0, 0, 0, 0x03];
+        gzip_header.extend_from_slice(b"genesis.txt\0");
+        let payload = analyze_payload(100, 1, &gzip_header);
+        assert_eq!(Some(String::from("genesis.txt")), payload.original_file_name);
+        assert!(payload.compressed && !payload.encrypted);
+    }
+
+    #[test]
+    fn test_encrypted_zip() {
+        let mut zip_header: Vec<u8> = vec![0x50, 0x4b, 0x03, 0x04, 0x14, 0x00, 0x01, 0x00];
+        zip_header.extend_from_slice(&[0; 18]);
+        zip_header.extend_from_slice(&[9, 0, 0, 0]);
+        zip_header.extend_from_slice(b"loren.txt");
+        let payload = analyze_payload(100, 1, &zip_header);
+        assert_eq!(Some(String::from("loren.txt")), payload.original_file_name);
+        assert!(payload.compressed && payload.encrypted);
+    }
+}
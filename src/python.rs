@@ -0,0 +1,421 @@
+/// Module with steganer python bindings.
+///
+/// Every public feature useful from python scripts is exported here, converting steganer errors
+/// into python SteganerError exceptions and steganer reports into python dictionaries.
+///
+/// Hidden data has no passphrase or compression in steganer format, so python functions do not
+/// offer those options. Encrypt or compress data before hiding it if you need that.
+///
+/// # Usage example:
+/// ```ignore
+/// import steganer
+/// steganer.hide_bytes(b"Meet at noon", "host.png", output="cover.png")
+/// assert steganer.unhide_bytes("cover.png") == b"Meet at noon"
+/// ```
+use std::ops::Add;
+
+use pyo3::prelude::*;
+use pyo3::{create_exception, wrap_pyfunction, PyErr, PyRawObject, exceptions};
+use pyo3::types::{PyAny, PyBytes, PyDict};
+
+use crate::*;
+
+create_exception!(steganer, SteganerError, exceptions::IOError);
+create_exception!(steganer, PayloadTooLargeError, SteganerError);
+create_exception!(steganer, UnsupportedFormatError, SteganerError);
+create_exception!(steganer, NoPayloadError, SteganerError);
+
+/// Create a python exception of given type with some attributes set.
+fn new_exception<T: pyo3::type_object::PyTypeObject>(py: Python, message: String, attributes: &[(&str, PyObject)])
+    -> PyResult<PyErr> {
+    let exception = py.get_type::<T>().call1((message,))?;
+    for (name, value) in attributes {
+        exception.setattr(*name, value)?;
+    }
+    Ok(PyErr::from_instance(exception))
+}
+
+/// Convert a steganer error into a SteganerError subclass with every error of its chain.
+///
+/// Exception type is taken from the most specific error kind found at chain, and that kind
+/// details are set as exception attributes.
+fn to_py_error(errors: Error)-> PyErr {
+    let mut message = String::new();
+    for (index, error) in errors.iter().enumerate() {
+        message = message.add(format!("\t {} --> {}", index, error).as_str());
+    }
+    let gil = Python::acquire_gil();
+    let py = gil.python();
+    let exception = match error_kind(&errors) {
+        Some(ErrorKind::PayloadTooLarge(required, available))=>
+            new_exception::<PayloadTooLargeError>(py, message, &[
+                ("required", required.to_object(py)),
+                ("available", available.to_object(py))]),
+        Some(ErrorKind::UnsupportedFormat(host_type, host_file))=>
+            new_exception::<UnsupportedFormatError>(py, message, &[
+                ("host_type", host_type.to_object(py)),
+                ("host_file", host_file.to_object(py))]),
+        Some(ErrorKind::NoPayload)=> new_exception::<NoPayloadError>(py, message, &[]),
+        _=> new_exception::<SteganerError>(py, message, &[]),
+    };
+    exception.unwrap_or_else(|error| error)
+}
+
+/// Get the file data must be hidden into, copying host file first if an output file was given.
+///
+/// # Parameters:
+/// * host_file: Absolute path to image file to hide data into.
+/// * output: Absolute path to copy host file into, if any.
+///
+/// # Returns:
+/// * Absolute path to image file that is going to contain hidden data.
+fn hiding_target<'a>(host_file: &'a str, output: Option<&'a str>)-> Result<&'a str> {
+    match output {
+        Some(output_file)=> {
+            copy_host(host_file, output_file)?;
+            Ok(output_file)
+        },
+        None=> Ok(host_file),
+    }
+}
+
+/// Check a file can be hidden into an image without using more than a given bits per pixel
+/// level, if any was given.
+fn check_file_bits_per_pixel(file_to_hide: &str, host_file: &str, bits_per_pixel: Option<u8>)
+    -> Result<()> {
+    match bits_per_pixel {
+        Some(bits_per_pixel)=> check_bits_per_pixel(file_to_hide, host_file, bits_per_pixel),
+        None=> Ok(()),
+    }
+}
+
+/// Check bytes can be hidden into an image file without using more than a given bits per pixel
+/// level, if any was given.
+fn check_bytes_bits_per_pixel(data: &[u8], host_file: &str, bits_per_pixel: Option<u8>)-> Result<()> {
+    match bits_per_pixel {
+        Some(bits_per_pixel)=> check_carrier_bits_per_pixel(data.len() as u64,
+                                                           &ContainerImage::new(host_file)?,
+                                                           bits_per_pixel),
+        None=> Ok(()),
+    }
+}
+
+/// Render a capacity report as a python dictionary.
+fn capacity_to_dict(py: Python, capacity: &Capacity)-> PyResult<PyObject> {
+    let levels = capacity.levels.iter()
+        .map(|level| {
+            let level_dict = PyDict::new(py);
+            level_dict.set_item("bits_per_pixel", level.bits_per_pixel)?;
+            level_dict.set_item("max_payload_bytes", level.max_payload_bytes)?;
+            Ok(level_dict.to_object(py))
+        })
+        .collect::<PyResult<Vec<PyObject>>>()?;
+    let dict = PyDict::new(py);
+    dict.set_item("width", capacity.width)?;
+    dict.set_item("height", capacity.height)?;
+    dict.set_item("header_pixels", capacity.header_pixels)?;
+    dict.set_item("usable_pixels", capacity.usable_pixels)?;
+    dict.set_item("channels", capacity.channels.to_string())?;
+    dict.set_item("max_payload_bytes", capacity.max_payload_bytes())?;
+    dict.set_item("levels", levels)?;
+    Ok(dict.to_object(py))
+}
+
+/// Render a probe report as a python dictionary.
+fn report_to_dict(py: Python, report: &ProbeReport)-> PyResult<PyObject> {
+    let payload = match &report.payload {
+        Some(payload)=> {
+            let payload_dict = PyDict::new(py);
+            payload_dict.set_item("format_version", payload.format_version)?;
+            payload_dict.set_item("size", payload.size)?;
+            payload_dict.set_item("bits_per_pixel", payload.bits_per_pixel)?;
+            payload_dict.set_item("file_type", payload.file_type.to_string())?;
+            payload_dict.set_item("compressed", payload.compressed)?;
+            payload_dict.set_item("encrypted", payload.encrypted)?;
+            payload_dict.set_item("original_file_name", &payload.original_file_name)?;
+            payload_dict.to_object(py)
+        },
+        None=> py.None(),
+    };
+    let dict = PyDict::new(py);
+    dict.set_item("host_file", &report.host_file)?;
+    dict.set_item("status", report.status.to_string())?;
+    dict.set_item("payload", payload)?;
+    Ok(dict.to_object(py))
+}
+
+/// Kind of in memory image given by python caller, kept to give back an image of that same kind.
+enum MemoryImage {
+    /// Numpy uint8 array, with its height x width x channels shape.
+    Numpy(PyObject),
+    /// PIL image, with its mode and size.
+    Pil(String, PyObject),
+}
+
+/// Copy pixels of a numpy array or a PIL image into a pixel buffer.
+///
+/// # Parameters:
+/// * host: Numpy uint8 array of height x width x channels shape, or PIL image. Only RGB and
+///   RGBA images are supported.
+///
+/// # Returns:
+/// * Pixel buffer with a copy of image pixels.
+/// * Kind of given image.
+fn to_pixel_buffer(py: Python, host: &PyAny)-> PyResult<(PixelBuffer, MemoryImage)> {
+    let (width, height, channels, kind) = if host.hasattr("__array_interface__")? {
+        let dtype: String = host.getattr("dtype")?.getattr("name")?.extract()?;
+        let shape = host.getattr("shape")?;
+        let (height, width, channels): (u32, u32, u8) = match shape.extract() {
+            Ok(dimensions) if dtype == "uint8"=> dimensions,
+            _=> return Err(PyErr::new::<exceptions::ValueError, _>(
+                "Only uint8 arrays with height x width x channels shape are supported.")),
+        };
+        (width, height, channels, MemoryImage::Numpy(shape.to_object(py)))
+    } else if host.hasattr("mode")? && host.hasattr("size")? {
+        let mode: String = host.getattr("mode")?.extract()?;
+        let size = host.getattr("size")?;
+        let (width, height): (u32, u32) = size.extract()?;
+        let channels = match mode.as_str() {
+            "RGB"=> 3,
+            "RGBA"=> 4,
+            _=> return Err(to_py_error(ErrorKind::UnsupportedFormat(
+                "Image".to_owned(), format!("PIL image with {} mode", mode)).into())),
+        };
+        (width, height, channels, MemoryImage::Pil(mode, size.to_object(py)))
+    } else {
+        return Err(PyErr::new::<exceptions::TypeError, _>(
+            "Host must be an image file path, a numpy array or a PIL image."));
+    };
+    let pixels = host.call_method0("tobytes")?.downcast_ref::<PyBytes>()?.as_bytes().to_vec();
+    let buffer = PixelBuffer::new(pixels, width, height, channels).map_err(to_py_error)?;
+    Ok((buffer, kind))
+}
+
+/// Build an image of given kind with pixels of a pixel buffer.
+fn from_pixel_buffer(py: Python, buffer: PixelBuffer, kind: MemoryImage)-> PyResult<PyObject> {
+    let pixels = PyBytes::new(py, &buffer.into_pixels());
+    let image = match kind {
+        MemoryImage::Numpy(shape)=> py.import("numpy")?
+            .call1("frombuffer", (pixels, "uint8"))?
+            .call_method1("reshape", (shape,))?
+            // Arrays built from a buffer are read only, so caller would not be able to edit it.
+            .call_method0("copy")?,
+        MemoryImage::Pil(mode, size)=> py.import("PIL.Image")?
+            .call1("frombytes", (mode, size, pixels))?,
+    };
+    Ok(image.to_object(py))
+}
+
+/// Exported version of extract_from_image() for python module.
+///
+/// # Parameters:
+/// * hidden_file: Absolute path to file to hide.
+/// * host_file: Absolute path to image file that is going to contain hidden file.
+#[pyfunction]
+fn unhide_from_image(py: Python, hidden_file: &str, host_file: &str)-> PyResult<()> {
+    py.allow_threads(|| extract_from_image(hidden_file, host_file)).map_err(to_py_error)
+}
+
+/// Exported version of hide_into_image() for python module.
+///
+/// # Parameters:
+/// * file_to_hide: Absolute path to hidden file.
+/// * host_file: Absolute path to image file that contains hidden file.
+/// * output: Optional absolute path to hide file into a copy of host image, leaving host image
+///   untouched.
+/// * bits_per_pixel: Optional maximum bits per pixel level to hide file with. Hiding fails
+///   with PayloadTooLargeError if file needs more.
+#[pyfunction]
+fn hide_inside_image(py: Python, file_to_hide: &str, host_file: &str, output: Option<&str>,
+                     bits_per_pixel: Option<u8>)-> PyResult<()> {
+    py.allow_threads(|| check_file_bits_per_pixel(file_to_hide, host_file, bits_per_pixel)
+        .and_then(|_| hiding_target(host_file, output))
+        .and_then(|target_file| hide_into_image(file_to_hide, target_file)))
+        .map_err(to_py_error)
+}
+
+/// Exported version of hide_bytes_into_image() for python module.
+///
+/// Host can be an in memory image too. Then data is hidden into a copy of that image, which is
+/// returned.
+///
+/// # Parameters:
+/// * data: Bytes to hide.
+/// * host: Absolute path to image file that is going to contain hidden data, numpy uint8 array
+///   of height x width x channels shape or PIL image.
+/// * output: Optional absolute path to hide data into a copy of host image file, leaving host
+///   image file untouched. Only valid for image files.
+/// * bits_per_pixel: Optional maximum bits per pixel level to hide data with. Hiding fails
+///   with PayloadTooLargeError if data needs more.
+///
+/// # Returns:
+/// * None for image files, or an image of the same kind as host for in memory images.
+#[pyfunction]
+fn hide_bytes(py: Python, data: &PyBytes, host: &PyAny, output: Option<&str>,
+              bits_per_pixel: Option<u8>)-> PyResult<PyObject> {
+    let data = data.as_bytes();
+    if let Ok(host_file) = host.extract::<&str>() {
+        py.allow_threads(|| check_bytes_bits_per_pixel(data, host_file, bits_per_pixel)
+            .and_then(|_| hiding_target(host_file, output))
+            .and_then(|target_file| hide_bytes_into_image(data, target_file)))
+            .map_err(to_py_error)?;
+        return Ok(py.None());
+    }
+    if output.is_some() {
+        return Err(PyErr::new::<exceptions::ValueError, _>(
+            "Output file can only be used with image files."));
+    }
+    let (mut buffer, kind) = to_pixel_buffer(py, host)?;
+    py.allow_threads(|| {
+        if let Some(bits_per_pixel) = bits_per_pixel {
+            check_carrier_bits_per_pixel(data.len() as u64, &buffer, bits_per_pixel)?;
+        }
+        hide_bytes_into_carrier(data, &mut buffer)
+    }).map_err(to_py_error)?;
+    from_pixel_buffer(py, buffer, kind)
+}
+
+/// Exported version of extract_bytes_from_image() for python module.
+///
+/// # Parameters:
+/// * host: Absolute path to image file that contains hidden data, numpy uint8 array of
+///   height x width x channels shape or PIL image.
+///
+/// # Returns:
+/// * Hidden bytes.
+#[pyfunction]
+fn unhide_bytes(py: Python, host: &PyAny)-> PyResult<PyObject> {
+    let data = if let Ok(host_file) = host.extract::<&str>() {
+        py.allow_threads(|| extract_bytes_from_image(host_file))
+    } else {
+        let (buffer, _) = to_pixel_buffer(py, host)?;
+        py.allow_threads(|| extract_bytes_from_carrier(&buffer))
+    }.map_err(to_py_error)?;
+    Ok(PyBytes::new(py, &data).to_object(py))
+}
+
+/// Exported version of image_capacity() for python module.
+///
+/// # Parameters:
+/// * host_file: Absolute path to image file to analyze.
+/// * bits_per_pixel: Optional bits per pixel level to report. Every level is reported if not
+///   given.
+///
+/// # Returns:
+/// * Dictionary with capacity report.
+#[pyfunction]
+fn capacity(py: Python, host_file: &str, bits_per_pixel: Option<u8>)-> PyResult<PyObject> {
+    let capacity = py.allow_threads(|| image_capacity(host_file, &CapacityOptions{bits_per_pixel}))
+        .map_err(to_py_error)?;
+    capacity_to_dict(py, &capacity)
+}
+
+/// Exported version of probe_image() for python module.
+///
+/// # Parameters:
+/// * host_file: Absolute path to image file to inspect.
+///
+/// # Returns:
+/// * Dictionary with probe report. Its payload key is None if no hidden data was found.
+#[pyfunction]
+fn info(py: Python, host_file: &str)-> PyResult<PyObject> {
+    let report = py.allow_threads(|| probe_image(host_file)).map_err(to_py_error)?;
+    report_to_dict(py, &report)
+}
+
+/// Python wrapper over a host image.
+///
+/// Rust ContainerImage borrows its path, so this wrapper keeps the path and opens the image
+/// again at every call. That way image is always saved before returning to python.
+#[pyclass(name = ContainerImage)]
+struct PyContainerImage {
+    host_file: String,
+    width: u32,
+    height: u32,
+}
+
+#[pymethods]
+impl PyContainerImage {
+    #[new]
+    #[allow(clippy::new_ret_no_self)] // Pyo3 constructors initialize given object instead.
+    fn new(obj: &PyRawObject, host_file: &str)-> PyResult<()> {
+        let (width, height) = ContainerImage::new(host_file)
+            .map(|host_image| host_image.dimensions())
+            .map_err(to_py_error)?;
+        obj.init(PyContainerImage{host_file: host_file.to_owned(), width, height});
+        Ok(())
+    }
+
+    #[getter]
+    fn host_file(&self)-> &str {
+        &self.host_file
+    }
+
+    #[getter]
+    fn width(&self)-> u32 {
+        self.width
+    }
+
+    #[getter]
+    fn height(&self)-> u32 {
+        self.height
+    }
+
+    /// Hide a file into this image, using up to given bits per pixel if any.
+    fn hide_file(&self, py: Python, file_to_hide: &str, bits_per_pixel: Option<u8>)-> PyResult<()> {
+        py.allow_threads(|| check_file_bits_per_pixel(file_to_hide, &self.host_file, bits_per_pixel)
+            .and_then(|_| hide_into_image(file_to_hide, &self.host_file)))
+            .map_err(to_py_error)
+    }
+
+    /// Extract a file hidden into this image.
+    fn unhide_file(&self, py: Python, hidden_file: &str)-> PyResult<()> {
+        py.allow_threads(|| extract_from_image(hidden_file, &self.host_file)).map_err(to_py_error)
+    }
+
+    /// Hide bytes into this image, using up to given bits per pixel if any.
+    fn hide_bytes(&self, py: Python, data: &PyBytes, bits_per_pixel: Option<u8>)-> PyResult<()> {
+        let data = data.as_bytes();
+        py.allow_threads(|| check_bytes_bits_per_pixel(data, &self.host_file, bits_per_pixel)
+            .and_then(|_| hide_bytes_into_image(data, &self.host_file)))
+            .map_err(to_py_error)
+    }
+
+    /// Extract bytes hidden into this image.
+    fn unhide_bytes(&self, py: Python)-> PyResult<PyObject> {
+        let data = py.allow_threads(|| extract_bytes_from_image(&self.host_file))
+            .map_err(to_py_error)?;
+        Ok(PyBytes::new(py, &data).to_object(py))
+    }
+
+    /// Get how many bytes can be hidden into this image, as a dictionary.
+    fn capacity(&self, py: Python, bits_per_pixel: Option<u8>)-> PyResult<PyObject> {
+        let options = CapacityOptions{bits_per_pixel};
+        let capacity = py.allow_threads(|| image_capacity(&self.host_file, &options))
+            .map_err(to_py_error)?;
+        capacity_to_dict(py, &capacity)
+    }
+
+    /// Look for hidden data into this image and report it as a dictionary.
+    fn info(&self, py: Python)-> PyResult<PyObject> {
+        let report = py.allow_threads(|| probe_image(&self.host_file)).map_err(to_py_error)?;
+        report_to_dict(py, &report)
+    }
+}
+
+/// Export to create a steganer python module.
+#[pymodule]
+fn steganer(py: Python, m: &PyModule)-> PyResult<()>{
+    m.add("SteganerError", py.get_type::<SteganerError>())?;
+    m.add("PayloadTooLargeError", py.get_type::<PayloadTooLargeError>())?;
+    m.add("UnsupportedFormatError", py.get_type::<UnsupportedFormatError>())?;
+    m.add("NoPayloadError", py.get_type::<NoPayloadError>())?;
+    m.add_wrapped(wrap_pyfunction!(unhide_from_image))?;
+    m.add_wrapped(wrap_pyfunction!(hide_inside_image))?;
+    m.add_wrapped(wrap_pyfunction!(hide_bytes))?;
+    m.add_wrapped(wrap_pyfunction!(unhide_bytes))?;
+    m.add_wrapped(wrap_pyfunction!(capacity))?;
+    m.add_wrapped(wrap_pyfunction!(info))?;
+    m.add_class::<PyContainerImage>()?;
+    Ok(())
+}
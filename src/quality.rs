@@ -0,0 +1,200 @@
+/// Module to measure how much a host image was degraded after hiding data into it.
+///
+/// Original cover image and its version with hidden data are compared sample by sample to get
+/// usual image quality metrics: MSE, PSNR and SSIM. Only red, green and blue channels are
+/// compared because steganer never modifies alpha channel.
+///
+/// # Usage example:
+/// ```ignore
+/// let report = hide_into_image_with_report("secret.txt", "cover.png")
+///                 .expect("Error hiding file");
+/// println!("PSNR: {} dB", report.psnr);
+/// ```
+use std::fmt;
+
+use image::RgbImage;
+
+/// Side of square windows SSIM is calculated over.
+const SSIM_WINDOW: u32 = 8;
+/// Highest value a channel sample can have.
+const MAX_SAMPLE_VALUE: f64 = 255.0;
+
+/// Report about differences between a cover image and that same image with hidden data.
+#[derive(Debug, PartialEq, Clone)]
+pub struct QualityReport {
+    /// Mean squared error over every red, green and blue sample.
+    pub mse: f64,
+    /// Peak signal to noise ratio in decibels. It is infinite if both images are equal.
+    pub psnr: f64,
+    /// Structural similarity index, between -1 and 1. It is 1 if both images are equal.
+    pub ssim: f64,
+    /// Biggest absolute difference found at red, green and blue channels.
+    pub max_channel_delta: [u8; 3],
+    /// How many channel samples changed their value.
+    pub modified_samples: u64,
+    /// How many channel samples were compared.
+    pub total_samples: u64,
+    /// Bits written at every pixel used to hide data.
+    pub bits_per_pixel: u8,
+    /// Hidden bits divided by image pixels.
+    pub embedding_rate: f64,
+}
+
+impl QualityReport {
+    /// Compare a cover image with its version with hidden data.
+    ///
+    /// # Parameters:
+    /// * cover: Original image.
+    /// * stego: Image with hidden data. It must have the same size as cover.
+    /// * hidden_bits: How many bits were hidden into stego image, header included.
+    /// * bits_per_pixel: Bits written at every pixel used to hide data.
+    ///
+    /// # Returns:
+    /// * Report with differences between both images.
+    pub fn compare(cover: &RgbImage, stego: &RgbImage, hidden_bits: u64, bits_per_pixel: u8)-> Self {
+        let mut squared_error_sum = 0_f64;
+        let mut max_channel_delta = [0_u8; 3];
+        let mut modified_samples = 0_u64;
+        for (cover_pixel, stego_pixel) in cover.pixels().zip(stego.pixels()) {
+            for channel in 0..3 {
+                let (a, b) = (cover_pixel[channel], stego_pixel[channel]);
+                let delta = std::cmp::max(a, b) - std::cmp::min(a, b);
+                if delta > 0 { modified_samples += 1; }
+                max_channel_delta[channel] = std::cmp::max(max_channel_delta[channel], delta);
+                squared_error_sum += delta as f64 * delta as f64;
+            }
+        }
+        let total_pixels = cover.width() as u64 * cover.height() as u64;
+        let total_samples = total_pixels * 3;
+        let mse = squared_error_sum / total_samples as f64;
+        let psnr = if mse == 0.0 {
+            f64::INFINITY
+        } else {
+            10.0 * (MAX_SAMPLE_VALUE * MAX_SAMPLE_VALUE / mse).log10()
+        };
+        QualityReport {
+            mse,
+            psnr,
+            ssim: ssim(cover, stego),
+            max_channel_delta,
+            modified_samples,
+            total_samples,
+            bits_per_pixel,
+            embedding_rate: hidden_bits as f64 / total_pixels as f64,
+        }
+    }
+
+    /// Render this report as a JSON object.
+    ///
+    /// Infinite PSNR, got when no sample was modified, is rendered as null.
+    pub fn to_json(&self)-> String {
+        let psnr = if self.psnr.is_finite() { format!("{}", self.psnr) } else { String::from("null") };
+        format!("{{\"mse\": {}, \"psnr\": {}, \"ssim\": {}, \"max_channel_delta\": [{}, {}, {}], \
+                 \"modified_samples\": {}, \"total_samples\": {}, \"bits_per_pixel\": {}, \
+                 \"embedding_rate\": {}}}",
+                self.mse, psnr, self.ssim,
+                self.max_channel_delta[0], self.max_channel_delta[1], self.max_channel_delta[2],
+                self.modified_samples, self.total_samples, self.bits_per_pixel, self.embedding_rate)
+    }
+}
+
+impl fmt::Display for QualityReport {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "MSE: {:.6}", self.mse)?;
+        writeln!(f, "PSNR: {:.2} dB", self.psnr)?;
+        writeln!(f, "SSIM: {:.6}", self.ssim)?;
+        writeln!(f, "Max channel delta (R, G, B): ({}, {}, {})",
+                 self.max_channel_delta[0], self.max_channel_delta[1], self.max_channel_delta[2])?;
+        writeln!(f, "Modified samples: {} of {} ({:.2}%)", self.modified_samples, self.total_samples,
+                 100.0 * self.modified_samples as f64 / self.total_samples as f64)?;
+        writeln!(f, "Bits per pixel: {}", self.bits_per_pixel)?;
+        write!(f, "Embedding rate: {:.4} bits per pixel", self.embedding_rate)
+    }
+}
+
+/// Get mean structural similarity index between two images.
+///
+/// SSIM is calculated at every non overlapping SSIM_WINDOW x SSIM_WINDOW window of every channel,
+/// and averaged afterwards. Pixels at right and bottom borders that do not fill a complete window
+/// are left out. If image is smaller than a window then the whole image is used as a window.
+fn ssim(cover: &RgbImage, stego: &RgbImage)-> f64 {
+    let c1 = (0.01 * MAX_SAMPLE_VALUE).powi(2);
+    let c2 = (0.03 * MAX_SAMPLE_VALUE).powi(2);
+    let (width, height) = cover.dimensions();
+    let window_width = std::cmp::min(SSIM_WINDOW, width);
+    let window_height = std::cmp::min(SSIM_WINDOW, height);
+    let mut ssim_sum = 0_f64;
+    let mut windows = 0_u64;
+    for window_y in (0..=(height - window_height)).step_by(window_height as usize) {
+        for window_x in (0..=(width - window_width)).step_by(window_width as usize) {
+            for channel in 0..3 {
+                let (mut sum_cover, mut sum_stego) = (0_f64, 0_f64);
+                let (mut sum_cover_sq, mut sum_stego_sq, mut sum_cross) = (0_f64, 0_f64, 0_f64);
+                for y in window_y..window_y + window_height {
+                    for x in window_x..window_x + window_width {
+                        let a = cover.get_pixel(x, y)[channel] as f64;
+                        let b = stego.get_pixel(x, y)[channel] as f64;
+                        sum_cover += a;
+                        sum_stego += b;
+                        sum_cover_sq += a * a;
+                        sum_stego_sq += b * b;
+                        sum_cross += a * b;
+                    }
+                }
+                let n = (window_width * window_height) as f64;
+                let mean_cover = sum_cover / n;
+                let mean_stego = sum_stego / n;
+                let variance_cover = sum_cover_sq / n - mean_cover * mean_cover;
+                let variance_stego = sum_stego_sq / n - mean_stego * mean_stego;
+                let covariance = sum_cross / n - mean_cover * mean_stego;
+                ssim_sum += ((2.0 * mean_cover * mean_stego + c1) * (2.0 * covariance + c2)) /
+                    ((mean_cover * mean_cover + mean_stego * mean_stego + c1) *
+                        (variance_cover + variance_stego + c2));
+                windows += 1;
+            }
+        }
+    }
+    if windows == 0 { 1.0 } else { ssim_sum / windows as f64 }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::{ImageBuffer, Rgb};
+
+    fn create_gradient_image()-> RgbImage {
+        ImageBuffer::from_fn(32, 32, |x, y| Rgb([(x * 8) as u8, (y * 8) as u8, ((x + y) * 4) as u8]))
+    }
+
+    #[test]
+    fn test_compare_equal_images() {
+        let cover = create_gradient_image();
+        let report = QualityReport::compare(&cover, &cover.clone(), 0, 1);
+        assert_eq!(0.0, report.mse);
+        assert!(report.psnr.is_infinite(), "PSNR for equal images should be infinite.");
+        assert!((report.ssim - 1.0).abs() < 1e-9,
+                "SSIM for equal images should be 1 but we got {}", report.ssim);
+        assert_eq!(0, report.modified_samples);
+    }
+
+    #[test]
+    fn test_compare_modified_images() {
+        let cover = create_gradient_image();
+        let mut stego = cover.clone();
+        // Change blue channel of two pixels.
+        stego.get_pixel_mut(0, 0)[2] ^= 0b_1;
+        stego.get_pixel_mut(5, 7)[2] ^= 0b_11;
+        let report = QualityReport::compare(&cover, &stego, 64, 1);
+        let expected_mse = (1.0 + 9.0) / (32.0 * 32.0 * 3.0);
+        assert!((report.mse - expected_mse).abs() < 1e-12,
+                "MSE was not what we were expecting. Expected {} but got {}", expected_mse, report.mse);
+        let expected_psnr = 10.0 * (255.0_f64 * 255.0 / expected_mse).log10();
+        assert!((report.psnr - expected_psnr).abs() < 1e-9,
+                "PSNR was not what we were expecting. Expected {} but got {}", expected_psnr, report.psnr);
+        assert!(report.ssim < 1.0 && report.ssim > 0.99,
+                "SSIM should be slightly under 1 but we got {}", report.ssim);
+        assert_eq!([0, 0, 3], report.max_channel_delta);
+        assert_eq!(2, report.modified_samples);
+        assert_eq!(64.0 / 1024.0, report.embedding_rate);
+    }
+}
@@ -0,0 +1,301 @@
+/// Module to look for payloads hidden by any LSB tool, not only by steganer.
+///
+/// Raw bit streams are extracted from an image trying every usual combination of channels, bit
+/// planes, bit orders and traversal orders. First bytes of every stream are checked looking for
+/// known file signatures and for printable text, both at stream start and after a 4 bytes length
+/// header, as many tools (steganer included) store payload length before payload. Found
+/// candidates are ranked by how likely they are to be real payloads.
+///
+/// # Usage example:
+/// ```ignore
+/// let report = scan_image("challenge.png")
+///                 .expect("Error scanning image");
+/// if let Some(best) = report.candidates.first() {
+///     println!("{}", best);
+/// }
+/// ```
+use std::fmt;
+
+use crate::*;
+
+/// How many bytes are extracted from every stream to look for payloads.
+const SCANNED_BYTES: usize = 1024;
+/// Size of length header some tools place before payload.
+const LENGTH_HEADER_BYTES: usize = 4;
+/// Shortest printable run considered as text.
+const MIN_TEXT_LENGTH: usize = 8;
+/// Printable run length that gets the highest text score.
+const FULL_SCORE_TEXT_LENGTH: usize = 64;
+/// Characters shown of found text.
+const PREVIEW_LENGTH: usize = 40;
+const CHANNEL_SETS: [&[Channel]; 5] = [&[Channel::Red], &[Channel::Green], &[Channel::Blue],
+                                       &[Channel::Red, Channel::Green, Channel::Blue],
+                                       &[Channel::Blue, Channel::Green, Channel::Red]];
+const BIT_SETS: [&[u8]; 7] = [&[0], &[1], &[2], &[3], &[1, 0], &[2, 1, 0], &[3, 2, 1, 0]];
+const BIT_ORDERS: [BitOrder; 2] = [BitOrder::MsbFirst, BitOrder::LsbFirst];
+const TRAVERSALS: [Traversal; 2] = [Traversal::RowMajor, Traversal::ColumnMajor];
+
+/// What was found at a bit stream.
+#[derive(Debug, PartialEq, Copy, Clone)]
+pub enum Finding {
+    /// A known file signature.
+    File(FileType),
+    /// A run of printable text.
+    Text,
+}
+
+impl fmt::Display for Finding {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Finding::File(file_type)=> write!(f, "{}", file_type),
+            Finding::Text=> write!(f, "text"),
+        }
+    }
+}
+
+/// A bit stream that may contain a payload.
+#[derive(Debug, PartialEq, Clone)]
+pub struct ScanCandidate {
+    /// Options to extract this stream with *dump_image()*.
+    pub options: DumpOptions,
+    /// Bytes from stream start where payload begins: 0, or 4 if it follows a length header.
+    pub offset: usize,
+    /// What was found.
+    pub finding: Finding,
+    /// How likely this candidate is to be a real payload, between 0 and 1.
+    pub score: f64,
+    /// Start of found text, if finding is text.
+    pub preview: String,
+}
+
+impl ScanCandidate {
+    /// Get dump command options to extract this candidate stream.
+    pub fn dump_arguments(&self)-> String {
+        let channels: String = self.options.channels.iter()
+            .map(|channel| channel_letter(*channel))
+            .collect();
+        let bits: Vec<String> = self.options.bits.iter().map(|bit| bit.to_string()).collect();
+        format!("--channels {} --bits {} --bit-order {} --traversal {}", channels, bits.join(","),
+                bit_order_name(self.options.bit_order), traversal_name(self.options.traversal))
+    }
+
+    /// Render this candidate as a JSON object.
+    fn to_json(&self)-> String {
+        let channels: String = self.options.channels.iter()
+            .map(|channel| channel_letter(*channel))
+            .collect();
+        let bits: Vec<String> = self.options.bits.iter().map(|bit| bit.to_string()).collect();
+        format!("{{\"channels\": \"{}\", \"bits\": [{}], \"bit_order\": \"{}\", \"traversal\": \"{}\", \
+                 \"offset\": {}, \"finding\": \"{}\", \"score\": {}, \"preview\": \"{}\"}}",
+                channels, bits.join(", "), bit_order_name(self.options.bit_order),
+                traversal_name(self.options.traversal), self.offset, self.finding, self.score,
+                probe::escape_json(&self.preview))
+    }
+}
+
+impl fmt::Display for ScanCandidate {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{:.2}  {}  offset {}  {}", self.score, self.dump_arguments(), self.offset,
+               self.finding)?;
+        if !self.preview.is_empty() {
+            write!(f, " \"{}\"", self.preview)?;
+        }
+        Ok(())
+    }
+}
+
+/// Result of scanning an image looking for payloads of any LSB tool.
+#[derive(Debug, PartialEq, Clone)]
+pub struct ScanReport {
+    /// Scanned image.
+    pub host_file: String,
+    /// How many bit streams were checked.
+    pub streams_scanned: usize,
+    /// Found candidates, best ones first.
+    pub candidates: Vec<ScanCandidate>,
+}
+
+impl ScanReport {
+    /// Render this report as a JSON object.
+    pub fn to_json(&self)-> String {
+        let candidates: Vec<String> = self.candidates.iter()
+            .map(|candidate| candidate.to_json())
+            .collect();
+        format!("{{\"host_file\": \"{}\", \"streams_scanned\": {}, \"candidates\": [{}]}}",
+                probe::escape_json(&self.host_file), self.streams_scanned, candidates.join(", "))
+    }
+}
+
+impl fmt::Display for ScanReport {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}: {} candidates found in {} streams.", self.host_file, self.candidates.len(),
+               self.streams_scanned)?;
+        for candidate in self.candidates.iter() {
+            write!(f, "\n{}", candidate)?;
+        }
+        Ok(())
+    }
+}
+
+fn channel_letter(channel: Channel)-> char {
+    match channel {
+        Channel::Red=> 'r',
+        Channel::Green=> 'g',
+        Channel::Blue=> 'b',
+    }
+}
+
+fn bit_order_name(bit_order: BitOrder)-> &'static str {
+    match bit_order {
+        BitOrder::MsbFirst=> "msb",
+        BitOrder::LsbFirst=> "lsb",
+    }
+}
+
+fn traversal_name(traversal: Traversal)-> &'static str {
+    match traversal {
+        Traversal::RowMajor=> "row",
+        Traversal::ColumnMajor=> "column",
+    }
+}
+
+/// Whether a byte is a printable ASCII character or usual whitespace.
+fn is_printable(byte: u8)-> bool {
+    (0x20..=0x7e).contains(&byte) || byte == b'\t' || byte == b'\n' || byte == b'\r'
+}
+
+/// Look for printable text at data start.
+///
+/// Runs of repeated or non alphanumeric characters are discarded, as they are usual at higher
+/// bit planes of flat image areas.
+///
+/// # Parameters:
+/// * data: Bytes to check.
+///
+/// # Returns:
+/// * Length of printable run at data start, if it looks like text.
+fn text_length(data: &[u8])-> Option<usize> {
+    let run_length = data.iter().take_while(|&&byte| is_printable(byte)).count();
+    if run_length < MIN_TEXT_LENGTH { return None; }
+    let run = &data[..run_length];
+    let text_like = run.iter()
+        .filter(|byte| byte.is_ascii_alphanumeric() || **byte == b' ')
+        .count();
+    let mut distinct = run.to_vec();
+    distinct.sort_unstable();
+    distinct.dedup();
+    if text_like * 10 >= run_length * 7 && distinct.len() >= 4 { Some(run_length) } else { None }
+}
+
+/// Check a bit stream start looking for payloads.
+///
+/// # Parameters:
+/// * data: First bytes of stream.
+/// * stream_capacity: How many bytes the whole stream has.
+///
+/// # Returns:
+/// * Offset, finding, score and preview of every candidate found.
+fn check_stream(data: &[u8], stream_capacity: u64)-> Vec<(usize, Finding, f64, String)> {
+    let mut findings = Vec::new();
+    let mut offsets = vec![(0, 0.0)];
+    if data.len() > LENGTH_HEADER_BYTES {
+        let length = u32::from_be_bytes([data[0], data[1], data[2], data[3]]) as u64;
+        // A plausible length header makes any finding after it more likely to be real.
+        if length > 0 && length <= stream_capacity.saturating_sub(LENGTH_HEADER_BYTES as u64) {
+            offsets.push((LENGTH_HEADER_BYTES, 0.1));
+        }
+    }
+    for (offset, bonus) in offsets {
+        let payload = &data[offset..];
+        match FileType::detect(payload) {
+            FileType::Unknown=> {
+                if let Some(length) = text_length(payload) {
+                    let score = 0.8 * std::cmp::min(length, FULL_SCORE_TEXT_LENGTH) as f64 /
+                        FULL_SCORE_TEXT_LENGTH as f64;
+                    let preview: String = payload[..std::cmp::min(length, PREVIEW_LENGTH)].iter()
+                        .map(|&byte| if byte.is_ascii_whitespace() { ' ' } else { byte as char })
+                        .collect();
+                    findings.push((offset, Finding::Text, score + bonus, preview));
+                }
+            },
+            file_type=> findings.push((offset, Finding::File(file_type), 0.9 + bonus, String::new())),
+        }
+    }
+    findings
+}
+
+/// Get every combination of dump options that is scanned.
+fn scanned_options()-> Vec<DumpOptions> {
+    let mut options = Vec::new();
+    for channels in CHANNEL_SETS.iter() {
+        for bits in BIT_SETS.iter() {
+            for bit_order in BIT_ORDERS.iter() {
+                for traversal in TRAVERSALS.iter() {
+                    options.push(DumpOptions{channels: channels.to_vec(), bits: bits.to_vec(),
+                                             bit_order: *bit_order, traversal: *traversal});
+                }
+            }
+        }
+    }
+    options
+}
+
+/// Scan an image looking for payloads hidden by any LSB tool.
+///
+/// Image is not modified.
+///
+/// # Parameters:
+/// * host_file: Absolute path to image file to scan.
+///
+/// # Returns:
+/// * Report with found candidates, best ones first.
+pub fn scan_image(host_file: &str)-> Result<ScanReport> {
+    let host_image = ContainerImage::new(host_file)?;
+    let (width, height) = host_image.dimensions();
+    let options = scanned_options();
+    let mut candidates = Vec::new();
+    for stream_options in options.iter() {
+        let stream_bits = width as u64 * height as u64 *
+            (stream_options.channels.len() * stream_options.bits.len()) as u64;
+        let data: Vec<u8> = host_image.lsb_stream(stream_options).take(SCANNED_BYTES).collect();
+        for (offset, finding, score, preview) in check_stream(&data, stream_bits / 8) {
+            candidates.push(ScanCandidate{options: stream_options.clone(), offset, finding,
+                                          score: score.min(1.0), preview});
+        }
+    }
+    candidates.sort_by(|a, b| b.score.partial_cmp(&a.score).unwrap_or(std::cmp::Ordering::Equal));
+    Ok(ScanReport{host_file: String::from(host_file), streams_scanned: options.len(), candidates})
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_text_length() {
+        assert_eq!(Some(26), text_length(b"Lorem ipsum dolor sit amet\x00\x01"));
+        // Too short.
+        assert_eq!(None, text_length(b"Lorem\x00ipsum dolor sit amet"));
+        // Printable but not text like.
+        assert_eq!(None, text_length(b"UUUUUUUUUUUUUUUU\x00"));
+        assert_eq!(None, text_length(b"~~??~~??}}{{}}{{\x00"));
+    }
+
+    #[test]
+    fn test_check_stream() {
+        // Gzip signature at stream start.
+        let findings = check_stream(&[0x1f, 0x8b, 0x08, 0x00, 0xff, 0xff], 1000);
+        assert_eq!(vec![(0, Finding::File(FileType::Gzip), 0.9, String::new())], findings);
+        // Text after a length header.
+        let mut data = vec![0, 0, 0, 26];
+        data.extend_from_slice(b"Lorem ipsum dolor sit amet\x00");
+        let findings = check_stream(&data, 1000);
+        assert_eq!(1, findings.len());
+        assert_eq!(4, findings[0].0);
+        assert_eq!(Finding::Text, findings[0].1);
+        assert_eq!("Lorem ipsum dolor sit amet", findings[0].3);
+        // Implausible length header is not taken into account.
+        let findings = check_stream(&[0xff, 0xff, 0xff, 0xff, 0x1f, 0x8b], 1000);
+        assert!(findings.is_empty());
+    }
+}
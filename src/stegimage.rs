@@ -4,16 +4,19 @@
 /// * PNG
 /// * BMP
 /// * PPM
+///
+/// Every pixel is a sample of carrier module, which implements hiding and extraction logic
+/// itself.
 use std::fmt;
 use std::iter::Iterator;
-use image::{DynamicImage, GenericImageView};
+use image::{DynamicImage, GenericImageView, RgbImage};
 
 use crate::*;
 use crate::bytetools::{mask, u24_to_bytes, bytes_to_u24};
-use crate::fileio::Chunk;
+use crate::carrier::{Carrier, HEADER_SAMPLE_LENGTH};
+use crate::visualize::Channel;
 
-const HEADER_PIXEL_LENGTH: u8 = 32;
-const SIZE_LENGTH: u8 = 32;
+const MAX_BITS_PER_PIXEL: u8 = 24;
 const SUPPORTED_EXTENSIONS: [&str; 3] = ["png", "bmp", "ppm"];
 
 /// Check if this file is supported as a valid host image.
@@ -57,18 +60,136 @@ impl fmt::Display for Position {
     }
 }
 
-/// Every ContainerImage that has been identified as host of a hidden image has a ReadingState
-/// type to manage hidden file extraction.
-struct ReadingState {
-    hidden_file_size: u32,
-    chunk_size: u8,
-    reading_position: u32
+/// Options to tune which capacity levels are reported by *ContainerImage::capacity()*.
+#[derive(Debug, Default, Clone)]
+pub struct CapacityOptions {
+    /// Report only this bits per pixel level. If None every level from 1 to 24 bits per pixel
+    /// is reported.
+    pub bits_per_pixel: Option<u8>,
 }
 
-impl ReadingState {
-    #[must_use]
-    pub fn new(hidden_file_size: u32, chunk_size: u8, reading_position: u32)-> Self{
-        ReadingState{hidden_file_size, chunk_size, reading_position}
+/// Pixel channels steganer hides data into.
+#[derive(Debug, PartialEq, Copy, Clone)]
+pub enum ChannelMode {
+    /// Red, green and blue channels. Alpha channel, if any, is always kept untouched. This is
+    /// the only mode steganer format supports, as its header does not store which channels
+    /// were used.
+    Rgb,
+}
+
+impl fmt::Display for ChannelMode {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ChannelMode::Rgb=> write!(f, "RGB"),
+        }
+    }
+}
+
+/// How many bytes can be hidden using a given amount of bits per pixel.
+#[derive(Debug, PartialEq, Clone)]
+pub struct CapacityLevel {
+    /// Bits overwritten at every pixel.
+    pub bits_per_pixel: u8,
+    /// Maximum size in bytes for a file hidden at this level.
+    pub max_payload_bytes: u64,
+}
+
+/// Report about how much data a host image can hold.
+///
+/// Only payload bytes are reported. Pixels used by steganer header are already discounted.
+/// Grayscale images are reported as the RGB images data is actually hidden into.
+/// Steganer does not encrypt or add error correction codes to hidden data, so there is no other
+/// overhead to take into account.
+#[derive(Debug, PartialEq, Clone)]
+pub struct Capacity {
+    /// Host image width in pixels.
+    pub width: u32,
+    /// Host image height in pixels.
+    pub height: u32,
+    /// Pixels reserved to store steganer header.
+    pub header_pixels: u32,
+    /// Pixels available to store hidden data.
+    pub usable_pixels: u64,
+    /// Channels modified when hiding data.
+    pub channels: ChannelMode,
+    /// Capacity for every requested bits per pixel level, sorted from lower to higher level.
+    pub levels: Vec<CapacityLevel>,
+}
+
+impl Capacity {
+    /// Get the biggest file, in bytes, this image can hold.
+    pub fn max_payload_bytes(&self)-> u64 {
+        self.levels.iter()
+            .map(|level| level.max_payload_bytes)
+            .max()
+            .unwrap_or(0)
+    }
+
+    /// Render this report as a JSON object.
+    pub fn to_json(&self)-> String {
+        let levels: Vec<String> = self.levels.iter()
+            .map(|level| format!("{{\"bits_per_pixel\": {}, \"max_payload_bytes\": {}}}",
+                                 level.bits_per_pixel, level.max_payload_bytes))
+            .collect();
+        format!("{{\"width\": {}, \"height\": {}, \"header_pixels\": {}, \"usable_pixels\": {}, \
+                 \"channels\": \"{}\", \"max_payload_bytes\": {}, \"levels\": [{}]}}",
+                self.width, self.height, self.header_pixels, self.usable_pixels,
+                self.channels, self.max_payload_bytes(), levels.join(", "))
+    }
+}
+
+impl fmt::Display for Capacity {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "Image size: {}x{} pixels ({} pixels reserved for header).",
+                 self.width, self.height, self.header_pixels)?;
+        writeln!(f, "Channels used: {}.", self.channels)?;
+        writeln!(f, "Bits per pixel    Max payload (bytes)")?;
+        for level in self.levels.iter() {
+            writeln!(f, "{:>14}    {:>19}", level.bits_per_pixel, level.max_payload_bytes)?;
+        }
+        write!(f, "Maximum payload: {} bytes.", self.max_payload_bytes())
+    }
+}
+
+/// Order bits are packed into bytes when dumping a raw bit stream.
+#[derive(Debug, PartialEq, Copy, Clone)]
+pub enum BitOrder {
+    /// First extracted bit is the most significant bit of its byte. Steganer uses this order.
+    MsbFirst,
+    /// First extracted bit is the least significant bit of its byte.
+    LsbFirst,
+}
+
+/// Order pixels are visited when dumping a raw bit stream.
+#[derive(Debug, PartialEq, Copy, Clone)]
+pub enum Traversal {
+    /// Pixels are visited row by row. Steganer uses this order.
+    RowMajor,
+    /// Pixels are visited column by column.
+    ColumnMajor,
+}
+
+/// Options to choose which bits are dumped from an image by *ContainerImage::lsb_stream()*.
+#[derive(Debug, PartialEq, Clone)]
+pub struct DumpOptions {
+    /// Channels bits are read from at every pixel, in this order.
+    pub channels: Vec<Channel>,
+    /// Bit planes read from every channel, in this order. 0 is least significant bit.
+    pub bits: Vec<u8>,
+    /// Order extracted bits are packed into bytes.
+    pub bit_order: BitOrder,
+    /// Order pixels are visited.
+    pub traversal: Traversal,
+}
+
+impl Default for DumpOptions {
+    fn default()-> Self {
+        DumpOptions {
+            channels: vec![Channel::Red, Channel::Green, Channel::Blue],
+            bits: vec![0],
+            bit_order: BitOrder::MsbFirst,
+            traversal: Traversal::RowMajor,
+        }
     }
 }
 
@@ -77,193 +198,196 @@ pub struct ContainerImage <'a> {
     image: DynamicImage,
     width: u32,
     height: u32,
-    reading_state: Option<ReadingState>,
     file_pathname: &'a str,
+    /// Set when any pixel has been overwritten, so image must be saved on drop.
+    modified: bool,
 }
 
 impl <'a> ContainerImage <'a>{
+    /// Open an image file as a carrier.
+    ///
+    /// # Parameters:
+    /// * file_pathname: Absolute path to image file.
+    ///
+    /// Grayscale and BGR images are converted to RGB, keeping their alpha channel if any, as
+    /// data is always hidden at red, green and blue channels. So, if data is hidden into them,
+    /// they are saved as RGB images.
+    ///
+    /// # Returns:
+    /// * Image ready to hide data into or extract data from.
+    /// * An error if file could not be read or decoded as an image.
     #[must_use]
     pub fn new(file_pathname: &'a str)-> Result<Self> {
         if let Ok(true) = supported_image(file_pathname) {
             let image = image::open(file_pathname)
-                .expect("Something wrong happened opening given image");
+                .chain_err(|| format!("Error opening host image {}", file_pathname))?;
+            let image = match image {
+                DynamicImage::ImageRgb8(_) | DynamicImage::ImageRgba8(_)=> image,
+                DynamicImage::ImageLumaA8(_) | DynamicImage::ImageBgra8(_)=>
+                    DynamicImage::ImageRgba8(image.to_rgba()),
+                DynamicImage::ImageLuma8(_) | DynamicImage::ImageBgr8(_)=>
+                    DynamicImage::ImageRgb8(image.to_rgb()),
+            };
             let (width, height) = image.dimensions();
-            Ok(ContainerImage{image, width, height, reading_state: None, file_pathname})
+            Ok(ContainerImage{image, width, height, file_pathname, modified: false})
         } else {
-            bail!("Image type not supported.")
+            bail!(ErrorKind::UnsupportedFormat("Image".to_owned(), file_pathname.to_owned()))
         }
 
     }
 
-    /// Prepare ContainerImage to host a hidden file.
-    ///
-    /// It is called when you know which file to hide. When you pass in its file size
-    /// it is encoded in ContainerImage header. Besides, file to hide size is used to calculate
-    /// how many bits should be hidden per pixel.
+    /// Report how many bytes can be hidden into this image.
     ///
-    /// This method should be called once, before hide_data() is called for the first time.
+    /// This method does not modify image, so it can be used to check a host image before
+    /// trying to hide anything into it.
     ///
     /// # Parameters:
-    /// * total_data_size: File to hide size in bytes
+    /// * options: Which bits per pixel levels to report.
     ///
     /// # Returns:
-    /// * Bits to be hidden per pixel.
-    pub fn setup_hiding(&mut self, total_data_size: u32) -> u8 {
-        self.encode_header(total_data_size);
-        self.get_chunk_size(total_data_size)
+    /// * Capacity report for this image.
+    pub fn capacity(&self, options: &CapacityOptions)-> Result<Capacity> {
+        let levels_to_report: Vec<u8> = match options.bits_per_pixel {
+            Some(bits) if bits == 0 || bits > MAX_BITS_PER_PIXEL =>
+                bail!("Bits per pixel must be between 1 and {}, but {} was requested.",
+                      MAX_BITS_PER_PIXEL, bits),
+            Some(bits)=> vec![bits],
+            None=> (1..=MAX_BITS_PER_PIXEL).collect(),
+        };
+        let usable_pixels = self.usable_pixels();
+        let levels = levels_to_report.iter()
+            .map(|&bits_per_pixel| {
+                // Hidden file size is stored in an u32 header, so bigger files can not be hidden.
+                let max_payload_bytes = std::cmp::min(usable_pixels * bits_per_pixel as u64 / 8,
+                                                      u32::MAX as u64);
+                CapacityLevel{bits_per_pixel, max_payload_bytes}
+            })
+            .collect();
+        Ok(Capacity{
+            width: self.width,
+            height: self.height,
+            header_pixels: HEADER_SAMPLE_LENGTH as u32,
+            usable_pixels,
+            channels: ChannelMode::Rgb,
+            levels,
+        })
     }
 
-    /// Identify this ContainerImage as hidden file host and prepare extraction.
-    ///
-    /// When you call this function, hidden file size is extracted from hidden and chunk size
-    /// is calculated so we can know how many bits from every pixel are actually hidden data.
-    ///
-    /// All that info is stored in a ReadingState type into ContainerImage. After
-    /// setup_extraction() creates a ReadingState instance into ContainerImage you can call
-    /// that ContainerImage as an Iterator to extract hidden data chunks.
-    pub fn setup_hidden_data_extraction(&mut self){
-        let hidden_file_size = self.decode_header();
-        let chunk_size = self.get_chunk_size(hidden_file_size);
-        let reading_state = ReadingState::new(hidden_file_size, chunk_size, 0);
-        self.reading_state = Some(reading_state);
+    /// Get how many pixels are left to hide data after reserving header pixels.
+    fn usable_pixels(&self)-> u64 {
+        (self.width as u64 * self.height as u64).saturating_sub(HEADER_SAMPLE_LENGTH as u64)
     }
 
-    /// Get needed chunk size to hide desired file into this image.
+    /// Get image size.
     ///
-    /// # Parameter:
-    /// * total_data_size: Total amount of bytes for data to be hidden.
+    /// # Returns:
+    /// * Image width and height in pixels.
+    pub fn dimensions(&self)-> (u32, u32) {
+        (self.width, self.height)
+    }
+
+    /// Get a copy of red, green and blue channels of this image as it is now.
     ///
     /// # Returns:
-    /// * Chunk size. Each chunk will be encoded in a pixel.
-    fn get_chunk_size(&self, total_data_size: u32)-> u8{
-        let usable_pixels_amount = (self.height * self.width) - HEADER_PIXEL_LENGTH as u32;
-        let total_data_size_in_bits = total_data_size * 8;
-        if total_data_size_in_bits > usable_pixels_amount * 24 {
-            panic!("File to be hidden is too big for this host image. Current is {} bytes \
-            but maximum for this image is {} bytes", total_data_size, usable_pixels_amount * 24 / 8)
-        } else {
-            let bits_per_pixel = (((total_data_size_in_bits) as f32) / usable_pixels_amount as f32).ceil() as u8;
-            bits_per_pixel
-        }
+    /// * RGB copy of this image.
+    pub fn to_rgb_image(&self)-> RgbImage {
+        self.image.to_rgb()
     }
 
-    /// First HEADER_PIXEL_LENGTH pixels of container image hides a u32 with encoded
-    /// data length. This functions encodes that u32 in those pixels.
+    /// Get an iterator over raw bits of this image, packed into bytes.
     ///
-    /// This way decoding function knows how many bytes decode from host image.
+    /// Steganer header is ignored, so any LSB embedding scheme can be inspected choosing its
+    /// channels, bit planes and traversal order.
     ///
     /// # Parameters:
-    /// * total_data_size: Total amount of bytes for data hidden.
-    fn encode_header(&mut self, total_data_size: u32){
-        let bits_per_pixel = SIZE_LENGTH / HEADER_PIXEL_LENGTH;
-        for i in 0..HEADER_PIXEL_LENGTH {
-            let mask_for_portion = !mask::<u32>(SIZE_LENGTH - bits_per_pixel, false) >> (bits_per_pixel * i);
-            let bits_portion = total_data_size & mask_for_portion;
-            let bits_normalized = (bits_portion as u64) >> (bits_per_pixel * (HEADER_PIXEL_LENGTH - 1 - i));
-            self.encode_bits(bits_normalized as u32, bits_per_pixel, i as u32, 0);
-        }
+    /// * options: Which bits to extract and in which order.
+    ///
+    /// # Returns:
+    /// * Iterator over extracted bytes.
+    pub fn lsb_stream<'b>(&'b self, options: &DumpOptions)-> LsbStream<'a, 'b> {
+        LsbStream{image: self, options: options.clone(), pixel_position: 0, pending_bits: 0,
+                  pending_length: 0}
     }
 
-    /// Read first HEADER_PIXEL_LENGTH pixels of container image to decode length of hidden
-    /// data.
+    /// Get red, green and blue channels of pixel defined by x and y coordinates.
     ///
-    /// # Returns:
-    /// * Length in bytes of hidden data file.
-    fn decode_header(&self)-> u32{
-        let mut size = 0u32;
-        let bits_per_pixel = SIZE_LENGTH / HEADER_PIXEL_LENGTH;
-        for i in 0..HEADER_PIXEL_LENGTH {
-            let partial_bits = self.decode_bits(i as u32, 0, bits_per_pixel);
-            let left_shift = (SIZE_LENGTH - 1) - (i * bits_per_pixel);
-            size += partial_bits << left_shift;
+    /// Alpha channel, if any, is ignored.
+    pub fn get_rgb(&self, x: u32, y: u32)-> [u8; 3] {
+        // I don't know if we have an image with alpha channel so both cases should be implemented.
+        if let Some(contained_image) = self.image.as_rgba8() {
+            let pixel = contained_image.get_pixel(x, y);
+            [pixel[0], pixel[1], pixel[2]]
+        } else {
+            let contained_image = self.image.as_rgb8()
+                .expect("Something wrong happened when accessing to inner image to decode data");
+            let pixel = contained_image.get_pixel(x, y);
+            [pixel[0], pixel[1], pixel[2]]
         }
-        size
     }
 
-    /// Encode given bits at pixel defined by x and y coordinates.
+    /// Overwrite red, green and blue channels of pixel defined by x and y coordinates.
     ///
-    /// # Parameters:
-    /// * bits: Data to be hidden.
-    /// * bits_length: How many bits at bits parameter are actually data to be hidden.
-    /// * x: X coordinate of pixel where data is going to be hidden.
-    /// * y: Y coordinate of pixel where data is going to be hidden.
-    fn encode_bits(&mut self, bits: u32, bits_length: u8, x: u32, y: u32){
+    /// Alpha channel, if any, is kept untouched. Image is marked as modified so it is saved
+    /// on drop.
+    pub fn set_rgb(&mut self, x: u32, y: u32, rgb: [u8; 3]) {
+        self.modified = true;
         // We don't know if host image is going to have an alpha channel or not. So
         // we must implement both cases.
         if let Some(contained_image) = self.image.as_mut_rgba8() {
             let pixel = contained_image.get_pixel_mut(x, y);
-            let modified_pixel_bytes = ContainerImage::overwrite_pixel(&pixel.data[..3], bits, bits_length);
-            *pixel = image::Rgba([modified_pixel_bytes[0],
-                modified_pixel_bytes[1],
-                modified_pixel_bytes[2],
+            *pixel = image::Rgba([rgb[0], rgb[1], rgb[2],
                 pixel[3]]); // We keep original Alpha channel.
         } else {
             let contained_image = self.image.as_mut_rgb8()
                 .expect("Something wrong happened when accessing to inner image to encode data");
             let pixel = contained_image.get_pixel_mut(x, y);
-            let modified_pixel_bytes = ContainerImage::overwrite_pixel(&pixel.data[..3], bits, bits_length);
-            *pixel = image::Rgb([modified_pixel_bytes[0],
-                modified_pixel_bytes[1],
-                modified_pixel_bytes[2]]);
+            *pixel = image::Rgb(rgb);
         }
     }
 
-    /// Called by self.encode_bits() to get which value should have host pixel after data hidding.
-    fn overwrite_pixel(rgb: &[u8], bits: u32, bits_length: u8)-> [u8; 3]{
-        let original_pixel_value: u32 = ((rgb[0] as u32) << 16) + ((rgb[1] as u32) << 8) + (rgb[2] as u32);
-        let modified_pixel_value = (original_pixel_value & mask::<u32>(bits_length, true)) + bits;
-        let modified_pixel_bytes = u24_to_bytes(modified_pixel_value);
-        modified_pixel_bytes
-    }
-
-    /// Decode bits hidden into given pixel defined by x and y coordinates.
-    ///
-    /// # Parameters:
-    /// * x: X coordinate of pixel where data is going to be hidden.
-    /// * y: Y coordinate of pixel where data is going to be hidden.
-    /// * bits_length: How many bits at pixel are actually hiden data.
+    /// Get raw pixels of this image, row by row from top left corner.
     ///
     /// # Returns:
-    /// * Recovered bits are returned into a u32.
-    fn decode_bits(&self, x: u32, y: u32, bits_length: u8)-> u32{
-        // I don't know if we have an image with alpha channel so both cases should be implemented.
+    /// * Pixel bytes and how many bytes every pixel has, 3 for RGB images or 4 for RGBA ones.
+    pub(crate) fn raw_pixels(&self)-> (&[u8], usize) {
         if let Some(contained_image) = self.image.as_rgba8() {
-            let pixel = contained_image.get_pixel(x, y);
-            ContainerImage::extract_hidden_data(&[pixel[0], pixel[1], pixel[2]], bits_length)
+            (contained_image, 4)
         } else {
             let contained_image = self.image.as_rgb8()
-                .expect("Something wrong happened when accessing to inner image to encode data");
-            let pixel = contained_image.get_pixel(x, y);
-            ContainerImage::extract_hidden_data(&[pixel[0], pixel[1], pixel[2]], bits_length)
+                .expect("Something wrong happened when accessing to inner image to decode data");
+            (contained_image, 3)
         }
     }
 
-    /// Called by self.decode_bits() to get portion of pixel data that contains hidden bits.
-    fn extract_hidden_data(pixel: &[u8; 3], bits_length: u8)-> u32{
-        let pixel_value = bytes_to_u24(pixel);
-        let recovered_bits = pixel_value & mask::<u32>(bits_length, false);
-        recovered_bits
-    }
-
-    /// Hide a chunk inside host image.
+    /// Get raw pixels of this image to overwrite them, row by row from top left corner.
+    ///
+    /// Image is marked as modified so it is saved on drop.
     ///
-    /// chunk.order is used to decide which pixel is going to hide chunk.data.
-    pub fn hide_data(&mut self, chunk: &Chunk){
-        let Position{x, y} = self.get_coordinates(chunk.order);
-        self.encode_bits(chunk.data, chunk.length, x, y);
+    /// # Returns:
+    /// * Pixel bytes and how many bytes every pixel has, 3 for RGB images or 4 for RGBA ones.
+    pub(crate) fn raw_pixels_mut(&mut self)-> (&mut [u8], usize) {
+        self.modified = true;
+        if self.image.as_rgba8().is_some() {
+            let contained_image = self.image.as_mut_rgba8()
+                .expect("Something wrong happened when accessing to inner image to encode data");
+            (contained_image, 4)
+        } else {
+            let contained_image = self.image.as_mut_rgb8()
+                .expect("Something wrong happened when accessing to inner image to encode data");
+            (contained_image, 3)
+        }
     }
 
-    /// Get pixel coordinates where nth chunk should be encoded.
+    /// Get pixel coordinates of nth carrier sample.
     ///
     /// # Parameters:
-    /// * position: Position of data chunk inside file to be hidden.
+    /// * position: Sample position, counting pixels row by row from top left corner.
     ///
     /// # Returns:
-    /// * Position of image pixel where this chunk should be stored.
-    fn get_coordinates(&self, position: u32)-> Position{
-        let offset_position = HEADER_PIXEL_LENGTH as u32 + position;
-        let x = offset_position % self.width;
-        let y = offset_position / self.width;
+    /// * Position of image pixel for that sample.
+    fn get_coordinates(&self, position: u64)-> Position{
+        let x = (position % self.width as u64) as u32;
+        let y = (position / self.width as u64) as u32;
         Position{x, y}
     }
 
@@ -274,45 +398,188 @@ impl <'a> ContainerImage <'a>{
     }
 }
 
-/// Iterator to extract hidden file content a chunk at a time.
+/// Iterator over raw bits of a ContainerImage, created by *ContainerImage::lsb_stream()*.
+pub struct LsbStream<'a, 'b> {
+    image: &'b ContainerImage<'a>,
+    options: DumpOptions,
+    pixel_position: u64,
+    /// Extracted bits not yet returned, right justified.
+    pending_bits: u64,
+    pending_length: u8,
+}
+
+impl <'a, 'b> LsbStream<'a, 'b> {
+    /// Read requested bits from next pixel and append them to pending bits.
+    fn read_next_pixel(&mut self) {
+        let (width, height) = self.image.dimensions();
+        let (x, y) = match self.options.traversal {
+            Traversal::RowMajor=> ((self.pixel_position % width as u64) as u32,
+                                   (self.pixel_position / width as u64) as u32),
+            Traversal::ColumnMajor=> ((self.pixel_position / height as u64) as u32,
+                                      (self.pixel_position % height as u64) as u32),
+        };
+        let rgb = self.image.get_rgb(x, y);
+        for channel in self.options.channels.iter() {
+            for bit in self.options.bits.iter() {
+                let extracted_bit = (rgb[channel.index()] >> bit) & 1;
+                self.pending_bits = (self.pending_bits << 1) | extracted_bit as u64;
+                self.pending_length += 1;
+            }
+        }
+        self.pixel_position += 1;
+    }
+}
+
+/// Iterator to dump raw bits of an image a byte at a time.
 ///
-/// Iterator will try to fill data attribute of Chunk. If it can not fill it, because it is
-/// extracting last few bits then those bits are left justified to data attribute and length
-/// attribute is set to how many files it was able to read.
-impl <'a> Iterator for ContainerImage <'a>{
-    type Item = Chunk;
+/// If there are not enough bits left to fill last byte, it is padded with zeros.
+impl <'a, 'b> Iterator for LsbStream<'a, 'b> {
+    type Item = u8;
 
     fn next(&mut self) -> Option<Self::Item> {
-        if let Some(state) = &self.reading_state {
-            let bit_position = state.reading_position * state.chunk_size as u32;
-            if bit_position < (state.hidden_file_size * 8) {
-                let reading_coordinates = self.get_coordinates(state.reading_position);
-                let extracted_bits = self.decode_bits(reading_coordinates.x, reading_coordinates.y, state.chunk_size);
-                let returned_chunk = Chunk::new(extracted_bits, state.chunk_size, state.reading_position);
-                let next_reading_position = state.reading_position + 1;
-                let new_state = ReadingState::new(state.hidden_file_size,
-                                                  state.chunk_size,
-                                                  next_reading_position);
-                self.reading_state = Some(new_state);
-                Some(returned_chunk)
-            } else { // No more hidden data left in container image.
-                None
-            }
-        } else {
-            panic!("You tried to use this ContainerImage as an Iterator before calling setup_hidden_data_extraction().");
+        let (width, height) = self.image.dimensions();
+        let total_pixels = width as u64 * height as u64;
+        while self.pending_length < 8 && self.pixel_position < total_pixels {
+            self.read_next_pixel();
+        }
+        if self.pending_length == 0 {
+            return None;
+        }
+        let byte = if self.pending_length >= 8 {
+            self.pending_length -= 8;
+            (self.pending_bits >> self.pending_length) as u8
+        } else { // Last few bits.
+            let padded = (self.pending_bits << (8 - self.pending_length)) as u8;
+            self.pending_length = 0;
+            padded
+        };
+        self.pending_bits &= mask::<u64>(self.pending_length, false);
+        match self.options.bit_order {
+            BitOrder::MsbFirst=> Some(byte),
+            BitOrder::LsbFirst=> Some(byte.reverse_bits()),
         }
     }
 }
 
+/// Every pixel is a carrier sample, with its red, green and blue channels packed into 24 bits,
+/// so up to 8 bits per pixel only blue channel is modified.
+impl <'a> Carrier for ContainerImage <'a> {
+    fn sample_count(&self)-> u64 {
+        self.width as u64 * self.height as u64
+    }
+
+    fn sample_bits(&self)-> u8 {
+        MAX_BITS_PER_PIXEL
+    }
+
+    fn read_sample(&self, position: u64)-> u64 {
+        let Position{x, y} = self.get_coordinates(position);
+        bytes_to_u24(&self.get_rgb(x, y)) as u64
+    }
+
+    fn write_sample(&mut self, position: u64, value: u64) {
+        let Position{x, y} = self.get_coordinates(position);
+        self.set_rgb(x, y, u24_to_bytes(value as u32 & mask::<u32>(MAX_BITS_PER_PIXEL, false)));
+    }
+
+    /// Image crate works in memory so changes should be written before disposing
+    /// ContainerImage. Images that have not been modified are not written again.
+    fn save(&mut self)-> Result<()> {
+        if self.modified {
+            self.image.save(self.file_pathname)
+                .chain_err(|| format!("Error saving host image {}", self.file_pathname))?;
+            self.modified = false;
+        }
+        Ok(())
+    }
+}
+
 /// Save to file every change done over image.
-///
-/// Image crate works in memory so changes should be written before disposing ContainerImage.
 impl <'a> Drop for ContainerImage <'a> {
     fn drop(&mut self) {
-        if let None = &self.reading_state {
-            self.image.save(self.file_pathname)
-                .expect("Image could not be saved");
+        self.save().expect("Image could not be saved");
+    }
+}
+
+/// Raw RGB or RGBA pixels kept in memory, used to hide data into images not backed by a file.
+///
+/// Pixels are laid out row by row from top left corner, with every channel of a pixel in
+/// consecutive bytes. Samples are placed exactly as in ContainerImage, so data hidden into a
+/// buffer can be extracted from an image file saved with those same pixels, and vice versa.
+pub struct PixelBuffer {
+    pixels: Vec<u8>,
+    width: u32,
+    height: u32,
+    channels: u8,
+}
+
+impl PixelBuffer {
+    /// Wrap raw pixels.
+    ///
+    /// # Parameters:
+    /// * pixels: Pixel bytes, row by row.
+    /// * width: Image width in pixels.
+    /// * height: Image height in pixels.
+    /// * channels: 3 for RGB pixels or 4 for RGBA ones. Alpha channel is kept untouched.
+    ///
+    /// # Returns:
+    /// * Pixel buffer.
+    /// * An error if channels are not supported or if pixels length does not match image size.
+    pub fn new(pixels: Vec<u8>, width: u32, height: u32, channels: u8)-> Result<Self> {
+        if channels != 3 && channels != 4 {
+            bail!(ErrorKind::UnsupportedFormat("Image".to_owned(),
+                                               format!("in memory image with {} channels", channels)));
+        }
+        let expected_length = width as u64 * height as u64 * channels as u64;
+        if pixels.len() as u64 != expected_length {
+            bail!("Pixel buffer has {} bytes but a {}x{} image with {} channels needs {} bytes.",
+                  pixels.len(), width, height, channels, expected_length);
         }
+        Ok(PixelBuffer{pixels, width, height, channels})
+    }
+
+    /// Get image size.
+    ///
+    /// # Returns:
+    /// * Image width and height in pixels.
+    pub fn dimensions(&self)-> (u32, u32) {
+        (self.width, self.height)
+    }
+
+    /// Give back wrapped pixels, with any change done while hiding.
+    pub fn into_pixels(self)-> Vec<u8> {
+        self.pixels
+    }
+
+    /// Get position at buffer of nth sample first byte.
+    fn sample_offset(&self, position: u64)-> usize {
+        (position * self.channels as u64) as usize
+    }
+}
+
+impl Carrier for PixelBuffer {
+    fn sample_count(&self)-> u64 {
+        self.width as u64 * self.height as u64
+    }
+
+    fn sample_bits(&self)-> u8 {
+        MAX_BITS_PER_PIXEL
+    }
+
+    fn read_sample(&self, position: u64)-> u64 {
+        let offset = self.sample_offset(position);
+        bytes_to_u24(&[self.pixels[offset], self.pixels[offset + 1], self.pixels[offset + 2]]) as u64
+    }
+
+    fn write_sample(&mut self, position: u64, value: u64) {
+        let offset = self.sample_offset(position);
+        let rgb = u24_to_bytes(value as u32 & mask::<u32>(MAX_BITS_PER_PIXEL, false));
+        self.pixels[offset..offset + 3].copy_from_slice(&rgb);
+    }
+
+    /// Pixels only live in memory, so there is nothing to save.
+    fn save(&mut self)-> Result<()> {
+        Ok(())
     }
 }
 
@@ -324,6 +591,9 @@ mod tests {
     use image::{ImageBuffer, GenericImageView};
 
     use crate::bytetools::get_bits;
+    use crate::carrier::{chunk_size, decode_bits, decode_header, encode_bits, encode_header,
+                         hidden_chunks, SIZE_LENGTH};
+    use crate::fileio::Chunk;
     use test_common::fs::tmp::TestEnvironment;
 
     enum TestColors {
@@ -355,6 +625,51 @@ mod tests {
         test_image_path
     }
 
+    #[test]
+    fn test_lsb_stream() {
+        let test_env = TestEnvironment::new();
+        let test_image_path = test_env.path().join("test_image.png");
+        // 4x2 image. Red LSB is set only at first row and blue LSB only at first column.
+        let test_image = ImageBuffer::from_fn(4, 2, |x, y| {
+            image::Rgb([if y == 0 {1_u8} else {0}, 0b_10, if x == 0 {1_u8} else {0}])
+        });
+        test_image.save(&test_image_path)
+            .expect("Something wrong happened saving test image");
+        let container = ContainerImage::new(test_image_path.to_str()
+            .expect("Something wrong happened converting test image path to str")).unwrap();
+        let red_rows: Vec<u8> = container.lsb_stream(&DumpOptions{channels: vec![Channel::Red],
+                                                                  ..DumpOptions::default()}).collect();
+        assert_eq!(vec![0b_1111_0000], red_rows);
+        let red_columns: Vec<u8> = container.lsb_stream(&DumpOptions{channels: vec![Channel::Red],
+                                                                     traversal: Traversal::ColumnMajor,
+                                                                     ..DumpOptions::default()}).collect();
+        assert_eq!(vec![0b_1010_1010], red_columns);
+        let red_rows_lsb_first: Vec<u8> = container.lsb_stream(&DumpOptions{channels: vec![Channel::Red],
+                                                                            bit_order: BitOrder::LsbFirst,
+                                                                            ..DumpOptions::default()}).collect();
+        assert_eq!(vec![0b_0000_1111], red_rows_lsb_first);
+        // Green and blue with two planes each give 4 bits per pixel.
+        let green_blue: Vec<u8> = container.lsb_stream(&DumpOptions{channels: vec![Channel::Green, Channel::Blue],
+                                                                    bits: vec![1, 0],
+                                                                    ..DumpOptions::default()}).collect();
+        assert_eq!(vec![0b_1001_1000, 0b_1000_1000, 0b_1001_1000, 0b_1000_1000], green_blue);
+    }
+
+    #[test]
+    fn test_lsb_stream_padding() {
+        let test_env = TestEnvironment::new();
+        let test_image_path = test_env.path().join("test_image.png");
+        let test_image = ImageBuffer::from_fn(3, 1, |_, _| image::Rgb([1_u8, 0, 0]));
+        test_image.save(&test_image_path)
+            .expect("Something wrong happened saving test image");
+        let container = ContainerImage::new(test_image_path.to_str()
+            .expect("Something wrong happened converting test image path to str")).unwrap();
+        // Only 3 bits are available, so they are padded with zeros to fill a byte.
+        let red: Vec<u8> = container.lsb_stream(&DumpOptions{channels: vec![Channel::Red],
+                                                             ..DumpOptions::default()}).collect();
+        assert_eq!(vec![0b_1110_0000], red);
+    }
+
     #[test]
     fn test_supported_image() {
         // Check supported images.
@@ -386,27 +701,81 @@ mod tests {
         let container = ContainerImage::new(test_image_path.to_str()
             .expect("Something wrong happened converting test image path to str")).unwrap();
         // Temporary test image has 512x512 = 262.144 pixels.
-        // But we use first HEADER_PIXEL_LENGTH bits for header, so we can use
-        // 262.144 - HEADER_PIXEL_LENGTH to hide data.
-        let chunk_size = container.get_chunk_size(8156); // Size of resources/genesis.txt is 8156.
-        let expected_chunk_size = ((8156_f64 * 8_f64) / ((512_f64*512_f64) - HEADER_PIXEL_LENGTH as f64)).ceil() as u8;
+        // But we use first HEADER_SAMPLE_LENGTH bits for header, so we can use
+        // 262.144 - HEADER_SAMPLE_LENGTH to hide data.
+        let chunk_size = chunk_size(&container, 8156).unwrap(); // Size of resources/genesis.txt is 8156.
+        let expected_chunk_size = ((8156_f64 * 8_f64) / ((512_f64*512_f64) - HEADER_SAMPLE_LENGTH as f64)).ceil() as u8;
         assert_eq!(expected_chunk_size, chunk_size,
                    "Recovered chunk size was not what we were expecting. Expected {} but got {}",
                    expected_chunk_size, chunk_size);
     }
 
     #[test]
-    #[should_panic]
     fn test_get_chunk_size_file_too_big() {
         let (_test_env, test_image_path) = create_test_image(TestColors::BLACK);
         let container = ContainerImage::new(test_image_path.to_str()
             .expect("Something wrong happened converting test image path to str")).unwrap();
         // Temporary test image has 512x512 = 262.144 pixels.
-        // But we use first HEADER_PIXEL_LENGTH bits for header, so we can use
-        // 262.144 - HEADER_PIXEL_LENGTH to hide data = 262.112 pixels.
+        // But we use first HEADER_SAMPLE_LENGTH bits for header, so we can use
+        // 262.144 - HEADER_SAMPLE_LENGTH to hide data = 262.112 pixels.
         // Every pixel can hide up to 24 bits os hidden data, so this
         // image can hide up to 6.290.688 bits = 786.336 bytes.
-        let _ = container.get_chunk_size(800000);
+        assert!(chunk_size(&container, 800000).is_err());
+    }
+
+    #[test]
+    fn test_capacity() {
+        let (_test_env, test_image_path) = create_test_image(TestColors::BLACK);
+        let container = ContainerImage::new(test_image_path.to_str()
+            .expect("Something wrong happened converting test image path to str")).unwrap();
+        let capacity = container.capacity(&CapacityOptions::default())
+            .expect("Error getting image capacity");
+        // Temporary test image has 512x512 = 262.144 pixels, minus HEADER_SAMPLE_LENGTH pixels
+        // for header we have 262.112 usable pixels.
+        assert_eq!(262_112, capacity.usable_pixels,
+                   "Usable pixels were not what we were expecting. Expected {} but got {}",
+                   262_112, capacity.usable_pixels);
+        assert_eq!(MAX_BITS_PER_PIXEL as usize, capacity.levels.len(),
+                   "We expected a capacity level for every bits per pixel amount.");
+        assert_eq!(ChannelMode::Rgb, capacity.channels);
+        assert!(capacity.to_json().contains("\"channels\": \"RGB\""));
+        let expected_first_level = CapacityLevel{bits_per_pixel: 1, max_payload_bytes: 32_764};
+        assert_eq!(expected_first_level, capacity.levels[0],
+                   "First capacity level was not what we were expecting. Expected {:?} but got {:?}",
+                   expected_first_level, capacity.levels[0]);
+        // Same amount chunk_size() fails with when given file is too big.
+        assert_eq!(786_336, capacity.max_payload_bytes(),
+                   "Maximum payload was not what we were expecting. Expected {} but got {}",
+                   786_336, capacity.max_payload_bytes());
+    }
+
+    #[test]
+    fn test_capacity_single_level() {
+        let (_test_env, test_image_path) = create_test_image(TestColors::BLACK);
+        let container = ContainerImage::new(test_image_path.to_str()
+            .expect("Something wrong happened converting test image path to str")).unwrap();
+        let options = CapacityOptions{bits_per_pixel: Some(3)};
+        let capacity = container.capacity(&options)
+            .expect("Error getting image capacity");
+        assert_eq!(vec![CapacityLevel{bits_per_pixel: 3, max_payload_bytes: 98_292}], capacity.levels);
+        let wrong_options = CapacityOptions{bits_per_pixel: Some(MAX_BITS_PER_PIXEL + 1)};
+        assert!(container.capacity(&wrong_options).is_err(),
+                "Capacity should fail with a bits per pixel level over {}", MAX_BITS_PER_PIXEL);
+    }
+
+    #[test]
+    fn test_capacity_does_not_modify_image() {
+        let (_test_env, test_image_path) = create_test_image(TestColors::BLACK);
+        let original_content = std::fs::read(&test_image_path)
+            .expect("Error reading test image");
+        {
+            let container = ContainerImage::new(test_image_path.to_str()
+                .expect("Something wrong happened converting test image path to str")).unwrap();
+            let _ = container.capacity(&CapacityOptions::default());
+        } // Here container is dropped. It should not be saved again because it was not modified.
+        let current_content = std::fs::read(&test_image_path)
+            .expect("Error reading test image");
+        assert!(original_content == current_content, "Test image was modified after checking its capacity.");
     }
 
     #[test]
@@ -415,13 +784,13 @@ mod tests {
         let (_test_env, test_image_path) = create_test_image(TestColors::BLACK);
         let mut container = ContainerImage::new(test_image_path.to_str()
             .expect("Something wrong happened converting test image path to str")).unwrap();
-        container.encode_header(encoded_size);
+        encode_header(&mut container, encoded_size);
         let mut recovered_size: u64 = 0;
-        let bits_per_pixel = SIZE_LENGTH / HEADER_PIXEL_LENGTH;
-        for i in 0..HEADER_PIXEL_LENGTH {
+        let bits_per_pixel = SIZE_LENGTH / HEADER_SAMPLE_LENGTH;
+        for i in 0..HEADER_SAMPLE_LENGTH {
             let pixel = container.get_image().get_pixel(i as u32,0);
             let pixel_hidden_bits = bytes_to_u24(&[pixel[0], pixel[1], pixel[2]]) & mask::<u32>(bits_per_pixel, false);
-            recovered_size += (pixel_hidden_bits as u64) << (bits_per_pixel * (HEADER_PIXEL_LENGTH - 1 - i));
+            recovered_size += (pixel_hidden_bits as u64) << (bits_per_pixel * (HEADER_SAMPLE_LENGTH - 1 - i));
         }
         assert_eq!(recovered_size as u32, encoded_size,
             "Error recovering encoded header: Expected {} but recovered {}",
@@ -434,8 +803,8 @@ mod tests {
         let (_test_env, test_image_path) = create_test_image(TestColors::BLACK);
         let mut container = ContainerImage::new(test_image_path.to_str()
             .expect("Something wrong happened converting test image path to str")).unwrap();
-        let bits_per_pixel = SIZE_LENGTH / HEADER_PIXEL_LENGTH;
-        for i in 0..HEADER_PIXEL_LENGTH {
+        let bits_per_pixel = SIZE_LENGTH / HEADER_SAMPLE_LENGTH;
+        for i in 0..HEADER_SAMPLE_LENGTH {
             // First encode header manually.
             let bits = get_bits(encoded_size, i * bits_per_pixel, bits_per_pixel) as u32;
             let pixel = container.get_image().as_mut_rgb8()
@@ -447,19 +816,19 @@ mod tests {
             *pixel = image::Rgb([modified_bytes[0], modified_bytes[1], modified_bytes[2]]);
         }
         // Now decode with tested function.
-        let decoded_size = container.decode_header();
+        let decoded_size = decode_header(&container).unwrap();
         assert_eq!(decoded_size, encoded_size, "Error decoding header: Expected {} but recovered {}",
                    encoded_size, decoded_size);
     }
 
     #[test]
     fn test_encode_less_than_8_bits() {
-        let test_bits: u32 = 0b_10110;
+        let test_bits: u64 = 0b_10110;
         let test_bits_length: u8 = 5;
         let (_test_env, test_image_path) = create_test_image(TestColors::BLACK);
         let mut container = ContainerImage::new(test_image_path.to_str()
             .expect("Something wrong happened converting test image path to str")).unwrap();
-        container.encode_bits(test_bits, test_bits_length, 0, 0);
+        encode_bits(&mut container, 0, test_bits, test_bits_length);
         let pixel = container.get_image().get_pixel(0,0);
         assert_eq!(pixel.data[2], test_bits as u8,
                    "Error encoding less than 8 bits. Expected {} but encoded {}",
@@ -468,17 +837,17 @@ mod tests {
 
     #[test]
     fn test_encode_up_to_16_bits() {
-        let mut test_bits: u32 = 0;
+        let mut test_bits: u64 = 0;
         let expected_upper_byte: u8 = 0b_00110100;
         let expected_lower_byte: u8 = 0b_00010110;
-        test_bits = test_bits + (expected_upper_byte as u32);
+        test_bits = test_bits + (expected_upper_byte as u64);
         test_bits = test_bits << 8;
-        test_bits = test_bits + (expected_lower_byte as u32);
+        test_bits = test_bits + (expected_lower_byte as u64);
         let test_bits_length: u8 = 14;
         let (_test_env, test_image_path) = create_test_image(TestColors::BLACK);
         let mut container = ContainerImage::new(test_image_path.to_str()
             .expect("Something wrong happened converting test image path to str")).unwrap();
-        container.encode_bits(test_bits, test_bits_length, 0, 0);
+        encode_bits(&mut container, 0, test_bits, test_bits_length);
         let mut pixel = container.get_image().get_pixel(0,0);
         pixel = container.get_image().get_pixel(0,0);
         assert_eq!(pixel.data[1], expected_upper_byte,
@@ -491,18 +860,18 @@ mod tests {
 
     #[test]
     fn test_encode_up_to_24_bits() {
-        let mut test_bits: u32 = 0;
+        let mut test_bits: u64 = 0;
         let expected_upper_byte: u8 = 0b_00000110;
         let expected_middle_byte: u8 = 0b_00110100;
         let expected_lower_byte: u8 = 0b_00010110;
-        test_bits = test_bits + ((expected_upper_byte as u32) << 16) +
-            ((expected_middle_byte as u32) << 8) +
-            (expected_lower_byte as u32);
+        test_bits = test_bits + ((expected_upper_byte as u64) << 16) +
+            ((expected_middle_byte as u64) << 8) +
+            (expected_lower_byte as u64);
         let test_bits_length: u8 = 19;
         let (_test_env, test_image_path) = create_test_image(TestColors::BLACK);
         let mut container = ContainerImage::new(test_image_path.to_str()
             .expect("Something wrong happened converting test image path to str")).unwrap();
-        container.encode_bits(test_bits, test_bits_length, 0, 0);
+        encode_bits(&mut container, 0, test_bits, test_bits_length);
         let mut pixel = container.get_image().get_pixel(0,0);
         pixel = container.get_image().get_pixel(0,0);
         assert_eq!(pixel.data[0], expected_upper_byte,
@@ -518,13 +887,13 @@ mod tests {
 
     #[test]
     fn test_encode_less_than_8_bits_masked() {
-        let test_bits: u32 = 0b_10110;
+        let test_bits: u64 = 0b_10110;
         let expected_recovered_bits: u8 = 0b_111_10110;
         let test_bits_length: u8 = 5;
         let (_test_env, test_image_path) = create_test_image(TestColors::WHITE);
         let mut container = ContainerImage::new(test_image_path.to_str()
             .expect("Something wrong happened converting test image path to str")).unwrap();
-        container.encode_bits(test_bits, test_bits_length, 0, 0);
+        encode_bits(&mut container, 0, test_bits, test_bits_length);
         let pixel = container.get_image().get_pixel(0,0);
         assert_eq!(pixel.data[2], expected_recovered_bits,
                    "Error encoding less than 8 bits masked. Expected {} but encoded {}",
@@ -533,18 +902,18 @@ mod tests {
 
     #[test]
     fn test_encode_up_to_16_bits_masked() {
-        let mut test_bits: u32 = 0;
+        let mut test_bits: u64 = 0;
         let expected_upper_byte: u8 = 0b_00110100;
         let expected_lower_byte: u8 = 0b_00010110;
-        test_bits = test_bits + (expected_upper_byte as u32);
+        test_bits = test_bits + (expected_upper_byte as u64);
         test_bits = test_bits << 8;
-        test_bits = test_bits + (expected_lower_byte as u32);
+        test_bits = test_bits + (expected_lower_byte as u64);
         let test_bits_length: u8 = 14;
         let expected_recovered_upper_byte: u8 = 0b_11_110100;
         let (_test_env, test_image_path) = create_test_image(TestColors::WHITE);
         let mut container = ContainerImage::new(test_image_path.to_str()
             .expect("Something wrong happened converting test image path to str")).unwrap();
-        container.encode_bits(test_bits, test_bits_length, 0, 0);
+        encode_bits(&mut container, 0, test_bits, test_bits_length);
         let mut pixel = container.get_image().get_pixel(0,0);
         pixel = container.get_image().get_pixel(0,0);
         assert_eq!(pixel.data[1], expected_recovered_upper_byte,
@@ -557,20 +926,20 @@ mod tests {
 
     #[test]
     fn test_encode_up_to_24_bits_masked() {
-        let mut test_bits: u32 = 0;
+        let mut test_bits: u64 = 0;
         let expected_upper_byte: u8 = 0b_00000110;
         let expected_middle_byte: u8 = 0b_00110100;
         let expected_lower_byte: u8 = 0b_00010110;
-        test_bits = test_bits + ((expected_upper_byte as u32) << 16) +
-            ((expected_middle_byte as u32) << 8) +
-            (expected_lower_byte as u32);
+        test_bits = test_bits + ((expected_upper_byte as u64) << 16) +
+            ((expected_middle_byte as u64) << 8) +
+            (expected_lower_byte as u64);
         let test_bits_length: u8 = 19;
         let expected_recovered_upper_byte: u8 = 0b_11111_110;
         let (_test_env, test_image_path) = create_test_image(TestColors::WHITE);
         let mut container = ContainerImage::new(test_image_path.to_str()
             .expect("Something wrong happened converting test image path to str")).unwrap();
         let mut pixel = container.get_image().get_pixel(0,0);
-        container.encode_bits(test_bits, test_bits_length, 0, 0);
+        encode_bits(&mut container, 0, test_bits, test_bits_length);
         pixel = container.get_image().get_pixel(0,0);
         assert_eq!(pixel.data[0], expected_recovered_upper_byte,
                    "Error encoding more than 16 bits. Upper byte expected {} but encoded {}",
@@ -585,12 +954,12 @@ mod tests {
 
     #[test]
     fn test_decode_less_than_8_bits() {
-        let test_bits: u32 = 0b_10110;
+        let test_bits: u64 = 0b_10110;
         let test_bits_length: u8 = 5;
-        let (_test_env, test_image_path) = create_test_image_with_custom_color(test_bits);
+        let (_test_env, test_image_path) = create_test_image_with_custom_color(test_bits as u32);
         let container = ContainerImage::new(test_image_path.to_str()
             .expect("Something wrong happened converting test image path to str")).unwrap();
-        let recovered_bits = container.decode_bits( 0, 0, test_bits_length);
+        let recovered_bits = decode_bits(&container, 0, test_bits_length);
         assert_eq!(test_bits, recovered_bits,
                    "Error decoding less than 8 bits. Expected {} but encoded {}",
                    test_bits, recovered_bits);
@@ -598,18 +967,18 @@ mod tests {
 
     #[test]
     fn test_decode_up_to_16_bits() {
-        let mut test_bits: u32 = 0;
+        let mut test_bits: u64 = 0;
         let expected_upper_byte: u8 = 0b_00110100;
         let expected_lower_byte: u8 = 0b_00010110;
-        test_bits = test_bits + (expected_upper_byte as u32);
+        test_bits = test_bits + (expected_upper_byte as u64);
         test_bits = test_bits << 8;
-        test_bits = test_bits + (expected_lower_byte as u32);
+        test_bits = test_bits + (expected_lower_byte as u64);
         let test_bits_length: u8 = 14;
-        let (_test_env, test_image_path) = create_test_image_with_custom_color(test_bits);
+        let (_test_env, test_image_path) = create_test_image_with_custom_color(test_bits as u32);
         let container = ContainerImage::new(test_image_path.to_str()
             .expect("Something wrong happened converting test image path to str")).unwrap();
-        let recovered_bits = container.decode_bits( 0, 0, test_bits_length);
-        let recovered_bytes = u24_to_bytes(recovered_bits);
+        let recovered_bits = decode_bits(&container, 0, test_bits_length);
+        let recovered_bytes = u24_to_bytes(recovered_bits as u32);
         assert_eq!(expected_upper_byte, recovered_bytes[1],
                    "Error decoding more than 8 bits. Upper byte expected {} but encoded {}",
                    expected_upper_byte, recovered_bytes[1]);
@@ -620,19 +989,19 @@ mod tests {
 
     #[test]
     fn test_decode_up_to_24_bits() {
-        let mut test_bits: u32 = 0;
+        let mut test_bits: u64 = 0;
         let expected_upper_byte: u8 = 0b_00000110;
         let expected_middle_byte: u8 = 0b_00110100;
         let expected_lower_byte: u8 = 0b_00010110;
-        test_bits = test_bits + ((expected_upper_byte as u32) << 16) +
-            ((expected_middle_byte as u32) << 8) +
-            (expected_lower_byte as u32);
+        test_bits = test_bits + ((expected_upper_byte as u64) << 16) +
+            ((expected_middle_byte as u64) << 8) +
+            (expected_lower_byte as u64);
         let test_bits_length: u8 = 19;
-        let (_test_env, test_image_path) = create_test_image_with_custom_color(test_bits);
+        let (_test_env, test_image_path) = create_test_image_with_custom_color(test_bits as u32);
         let container = ContainerImage::new(test_image_path.to_str()
             .expect("Something wrong happened converting test image path to str")).unwrap();
-        let recovered_bits = container.decode_bits( 0, 0, test_bits_length,);
-        let recovered_bytes = u24_to_bytes(recovered_bits);
+        let recovered_bits = decode_bits(&container, 0, test_bits_length);
+        let recovered_bytes = u24_to_bytes(recovered_bits as u32);
         assert_eq!(expected_upper_byte, recovered_bytes[0],
                    "Error decoding more than 16 bits. Upper byte expected {} but decoded {}",
                    expected_upper_byte, recovered_bytes[0]);
@@ -650,23 +1019,23 @@ mod tests {
         let position_first_row = 5;
         let position_second_row = 570;
         let position_third_row = 1100;
-        let expected_first_row_coordinates = Position{x: (HEADER_PIXEL_LENGTH + 5) as u32, y: 0};
-        let expected_second_row_coordinates = Position{x: (position_second_row as u32 - test_image_width + HEADER_PIXEL_LENGTH as u32), y: 1};
-        let expected_third_row_coordinates = Position{x: (position_third_row as u32 - (test_image_width * 2) + HEADER_PIXEL_LENGTH as u32), y: 2};
+        let expected_first_row_coordinates = Position{x: (HEADER_SAMPLE_LENGTH + 5) as u32, y: 0};
+        let expected_second_row_coordinates = Position{x: (position_second_row as u32 - test_image_width + HEADER_SAMPLE_LENGTH as u32), y: 1};
+        let expected_third_row_coordinates = Position{x: (position_third_row as u32 - (test_image_width * 2) + HEADER_SAMPLE_LENGTH as u32), y: 2};
         // Test environment build.
         let (_test_env, test_image_path) = create_test_image(TestColors::BLACK);
         let container = ContainerImage::new(test_image_path.to_str()
             .expect("Something wrong happened converting test image path to str")).unwrap();
         // Tests.
-        let recovered_position_first_row = container.get_coordinates(position_first_row);
+        let recovered_position_first_row = container.get_coordinates(HEADER_SAMPLE_LENGTH as u64 + position_first_row);
         assert_eq!(expected_first_row_coordinates, recovered_position_first_row,
                    "Recovered position for first row was not what we were expecting. Expected {} but got {}",
                    &expected_first_row_coordinates, recovered_position_first_row);
-        let recovered_position_second_row = container.get_coordinates(position_second_row);
+        let recovered_position_second_row = container.get_coordinates(HEADER_SAMPLE_LENGTH as u64 + position_second_row);
         assert_eq!(expected_second_row_coordinates, recovered_position_second_row,
                    "Recovered position for second row was not what we were expecting. Expected {} but got {}",
                    &expected_second_row_coordinates, recovered_position_second_row);
-        let recovered_position_third_row = container.get_coordinates(position_third_row);
+        let recovered_position_third_row = container.get_coordinates(HEADER_SAMPLE_LENGTH as u64 + position_third_row);
         assert_eq!(expected_third_row_coordinates, recovered_position_third_row,
                    "Recovered position for third row was not what we were expecting. Expected {} but got {}",
                    &expected_third_row_coordinates, recovered_position_third_row);
@@ -677,14 +1046,15 @@ mod tests {
         let hidden_data = 0b_111000111_u32;
         let hidden_data_length = 9;
         let position = 5_u8;
-        let chunk = Chunk::new(hidden_data, hidden_data_length, position as u32);
+        let chunk = Chunk::new(hidden_data as u64, hidden_data_length, position as u32);
         // Test environment build.
         let (_test_env, test_image_path) = create_test_image(TestColors::BLACK);
         let mut container = ContainerImage::new(test_image_path.to_str()
             .expect("Something wrong happened converting test image path to str")).unwrap();
         // Test:
-        container.hide_data(&chunk);
-        let pixel = container.get_image().get_pixel((HEADER_PIXEL_LENGTH + position) as u32, 0);
+        encode_bits(&mut container, HEADER_SAMPLE_LENGTH as u64 + chunk.order as u64, chunk.data,
+                    chunk.length);
+        let pixel = container.get_image().get_pixel((HEADER_SAMPLE_LENGTH + position) as u32, 0);
         assert_eq!(0b_1_u8, pixel.data[1],
                    "Recovered data for upper byte was not what we were expecting. Expected {:#b} but got {:#b}",
                    0b_1_u8, pixel.data[1]);
@@ -699,19 +1069,20 @@ mod tests {
         let hidden_data = 0b_0000_0000_0000_0000_1010_0101_1100_0111_u32;
         let hidden_data_length = 24;
         let position = 0_u8;
-        let chunk = Chunk::new(hidden_data, hidden_data_length, position as u32);
+        let chunk = Chunk::new(hidden_data as u64, hidden_data_length, position as u32);
         // Test environment build.
         let (_test_env, test_image_path) = create_test_image(TestColors::BLACK);
         let mut container = ContainerImage::new(test_image_path.to_str()
             .expect("Something wrong happened converting test image path to str")).unwrap();
         // Test:
-        container.encode_header(header);
-        container.hide_data(&chunk);
-        let recovered_header = container.decode_header();
+        encode_header(&mut container, header);
+        encode_bits(&mut container, HEADER_SAMPLE_LENGTH as u64 + chunk.order as u64, chunk.data,
+                    chunk.length);
+        let recovered_header = decode_header(&container).unwrap();
         assert_eq!(header, recovered_header,
                    "Recovered data for header was not what we were expecting. Expected {:#b} but got {:#b}",
                    header, recovered_header);
-        let pixel = container.get_image().get_pixel((HEADER_PIXEL_LENGTH + position) as u32, 0);
+        let pixel = container.get_image().get_pixel((HEADER_SAMPLE_LENGTH + position) as u32, 0);
         assert_eq!(0b_0000_0000_u8, pixel.data[0],
                    "Recovered data for upper byte was not what we were expecting. Expected {:#b} but got {:#b}",
                    0b_0000_0000_u8, pixel.data[0]);
@@ -735,7 +1106,8 @@ mod tests {
         let mut container = ContainerImage::new(test_image_path.to_str()
             .expect("Something wrong happened converting test image path to str")).unwrap();
         // Populate test environment with hidden data.
-        let chunk_size = container.setup_hiding(hidden_data_size as u32);
+        let chunk_size = chunk_size(&container, hidden_data_size as u32).unwrap();
+        encode_header(&mut container, hidden_data_size as u32);
         let mut position = 0_u32;
         for data in hidden_data.iter() {
             let data_bytes = u24_to_bytes(*data);
@@ -743,17 +1115,18 @@ mod tests {
             for _ in 0..(24/chunk_size) {
                 let data_chunk = bit_reader.read_u32(chunk_size)
                     .expect("Error reading data chunk.");
-                let chunk = Chunk::new(data_chunk, chunk_size, position as u32);
-                container.hide_data(&chunk);
+                let chunk = Chunk::new(data_chunk as u64, chunk_size, position);
+                encode_bits(&mut container, HEADER_SAMPLE_LENGTH as u64 + chunk.order as u64,
+                            chunk.data, chunk.length);
                 position += 1;
             }
         }
         // Test.
         let mut recovered_data: [u32; 3] = [0; 3];
-        container.setup_hidden_data_extraction();
-        for (i, chunk) in container.enumerate() {
+        let chunks = hidden_chunks(&container).expect("Error reading hidden data header");
+        for (i, chunk) in chunks.enumerate() {
             let u24_index = i / 24;
-            recovered_data[u24_index] = (recovered_data[u24_index] << chunk_size) + chunk.data;
+            recovered_data[u24_index] = (recovered_data[u24_index] << chunk_size) + chunk.data as u32;
         }
         assert_eq!(hidden_data, recovered_data,
                    "ContainerImage iterator did not recover expected data. Expected {:#?} but recovered {:#?}",
@@ -768,19 +1141,33 @@ mod tests {
         {
             let mut container = ContainerImage::new(test_image_path.to_str()
                 .expect("Something wrong happened converting test image path to str")).unwrap();
-            let _ = container.setup_hiding(dummy_size);
+            encode_header(&mut container, dummy_size);
         } // Here container should be written to disk, with dummy_size encoded at its header, before dropping container.
         // Now try to recover encoded size.
-        let mut container = ContainerImage::new(test_image_path.to_str()
+        let container = ContainerImage::new(test_image_path.to_str()
             .expect("Something wrong happened converting test image path to str")).unwrap();
-        container.setup_hidden_data_extraction();
-        if let Some(state) = &container.reading_state {
-            let extracted_size = state.hidden_file_size;
-            assert_eq!(dummy_size, extracted_size,
-                       "Recovered size is not what we were expecting. Expected {} but recovered {}.",
-                       dummy_size, extracted_size);
-        } else {
-            assert!(false, "No reading state recovered");
-        }
+        let extracted_size = decode_header(&container).unwrap();
+        assert_eq!(dummy_size, extracted_size,
+                   "Recovered size is not what we were expecting. Expected {} but recovered {}.",
+                   dummy_size, extracted_size);
+    }
+
+    #[test]
+    fn test_pixel_buffer_matches_container_image() {
+        let test_env = TestEnvironment::new();
+        let image_path = test_env.path().join("buffer.png");
+        let (width, height) = (20_u32, 10_u32);
+        let pixels: Vec<u8> = (0..width * height * 4).map(|i| (i * 7) as u8).collect();
+        let mut buffer = PixelBuffer::new(pixels.clone(), width, height, 4).unwrap();
+        hide_bytes_into_carrier(b"Meet at noon", &mut buffer).expect("Error hiding into buffer");
+        let hidden_pixels = buffer.into_pixels();
+        // Alpha channel is kept untouched.
+        assert!(hidden_pixels.iter().zip(pixels.iter()).skip(3).step_by(4).all(|(a, b)| a == b));
+        image::save_buffer(&image_path, &hidden_pixels, width, height, image::ColorType::RGBA(8))
+            .expect("Error saving test image");
+        let container = ContainerImage::new(image_path.to_str().unwrap()).unwrap();
+        assert_eq!(b"Meet at noon".to_vec(), extract_bytes_from_carrier(&container).unwrap());
+        assert!(PixelBuffer::new(vec![0; 8], 2, 2, 2).is_err(), "Two channels are not supported.");
+        assert!(PixelBuffer::new(vec![0; 8], 2, 2, 3).is_err(), "Wrong length was accepted.");
     }
 }
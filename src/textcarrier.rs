@@ -0,0 +1,381 @@
+/// Module to hide data inside plain text files without changing their visible text.
+///
+/// Two encodings are available:
+/// * Whitespace: data is hidden as trailing spaces and tabs at the end of every line, like SNOW
+///   tool does. Every line hides up to 32 bits as a binary number, where a space is a 0 and a
+///   tab is a 1.
+/// * Zero width: data is hidden as zero width characters placed after every space between
+///   words. Every gap hides 2 bits: nothing is 0, a ZERO WIDTH SPACE is 1, a ZERO WIDTH
+///   NON-JOINER is 2 and a ZERO WIDTH JOINER is 3.
+///
+/// Every line, or every gap between words, is a slot. Slot bits are offered to carrier module
+/// as 1 bit samples, most significant bit first, so steganer header only takes a line or a few
+/// gaps. Slots that are not modified keep their original text.
+///
+/// Text must be UTF-8 encoded.
+///
+/// # Usage example:
+/// ```ignore
+/// let mut host_text = ContainerText::new("notes.txt", TextEncoding::ZeroWidth)
+///                         .expect("Error opening host text");
+/// hide_into_carrier("secret.txt", &mut host_text)
+///     .expect("Error hiding file");
+/// ```
+use std::fmt;
+use std::fs;
+use std::path::Path;
+use std::str::FromStr;
+
+use crate::*;
+use crate::carrier::{max_payload_bytes, Carrier};
+
+const SUPPORTED_EXTENSIONS: [&str; 1] = ["txt"];
+/// Bits hidden at every line with whitespace encoding.
+const WHITESPACE_SLOT_BITS: u8 = 32;
+/// Bits hidden at every gap between words with zero width encoding.
+const ZERO_WIDTH_SLOT_BITS: u8 = 2;
+/// Zero width characters for values 1, 2 and 3 of a gap between words.
+const ZERO_WIDTH_CHARACTERS: [char; 3] = ['\u{200B}', '\u{200C}', '\u{200D}'];
+
+/// Check if this file is supported as a valid host text.
+///
+/// # Parameters:
+/// * filename: Host text filename.
+///
+/// # Returns:
+/// * True if this text type is supported and false if not.
+pub(crate) fn supported_text(filename: &str)-> bool {
+    match Path::new(filename).extension().and_then(|extension| extension.to_str()) {
+        Some(extension)=> SUPPORTED_EXTENSIONS.contains(&extension.to_lowercase().as_str()),
+        None=> false,
+    }
+}
+
+/// How data is hidden into a text.
+#[derive(Debug, PartialEq, Copy, Clone)]
+pub enum TextEncoding {
+    /// Trailing spaces and tabs at the end of every line.
+    Whitespace,
+    /// Zero width characters after every space between words.
+    ZeroWidth,
+}
+
+impl TextEncoding {
+    /// Bits hidden at every slot with this encoding.
+    fn slot_bits(self)-> u8 {
+        match self {
+            TextEncoding::Whitespace=> WHITESPACE_SLOT_BITS,
+            TextEncoding::ZeroWidth=> ZERO_WIDTH_SLOT_BITS,
+        }
+    }
+
+    /// Get text that hides given value at a slot.
+    fn encode(self, value: u32)-> String {
+        match self {
+            TextEncoding::Whitespace=> {
+                let significant_bits = 32 - value.leading_zeros();
+                (0..significant_bits).rev()
+                    .map(|bit| if (value >> bit) & 1 == 1 { '\t' } else { ' ' })
+                    .collect()
+            },
+            TextEncoding::ZeroWidth=> match value {
+                0=> String::new(),
+                _=> ZERO_WIDTH_CHARACTERS[value as usize - 1].to_string(),
+            },
+        }
+    }
+
+    /// Get value hidden at a slot text.
+    fn decode(self, text: &str)-> u32 {
+        match self {
+            TextEncoding::Whitespace=> text.chars()
+                .fold(0_u64, |value, character| {
+                    ((value << 1) | (character == '\t') as u64) & u32::MAX as u64
+                }) as u32,
+            TextEncoding::ZeroWidth=> text.chars().next()
+                .and_then(|character| ZERO_WIDTH_CHARACTERS.iter().position(|&zw| zw == character))
+                .map_or(0, |index| index as u32 + 1),
+        }
+    }
+}
+
+impl FromStr for TextEncoding {
+    type Err = String;
+
+    fn from_str(text: &str)-> std::result::Result<Self, Self::Err> {
+        match text.to_lowercase().as_str() {
+            "whitespace"=> Ok(TextEncoding::Whitespace),
+            "zero-width"=> Ok(TextEncoding::ZeroWidth),
+            _=> Err(format!("Unknown text encoding {}. Use whitespace or zero-width.", text)),
+        }
+    }
+}
+
+impl fmt::Display for TextEncoding {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            TextEncoding::Whitespace=> write!(f, "whitespace"),
+            TextEncoding::ZeroWidth=> write!(f, "zero-width"),
+        }
+    }
+}
+
+/// A place of text where a value can be hidden.
+struct Slot {
+    value: u32,
+    /// Text that hides value. It is kept as found until value is changed.
+    text: String,
+}
+
+/// Split a text into visible segments and slots for given encoding.
+///
+/// # Returns:
+/// * Visible segments. There is always one segment more than slots, and every slot goes right
+///   after segment with its same index.
+/// * Slots found.
+fn split_text(text: &str, encoding: TextEncoding)-> (Vec<String>, Vec<Slot>) {
+    let mut segments = Vec::new();
+    let mut slots = Vec::new();
+    let mut current_segment = String::new();
+    match encoding {
+        TextEncoding::Whitespace=> {
+            for line in text.split_inclusive('\n') {
+                let line_ending_start = line.trim_end_matches(['\r', '\n']).len();
+                let (content, line_ending) = line.split_at(line_ending_start);
+                let visible_content = content.trim_end_matches([' ', '\t']);
+                let trailing = &content[visible_content.len()..];
+                current_segment.push_str(visible_content);
+                segments.push(current_segment);
+                slots.push(Slot{value: encoding.decode(trailing), text: trailing.to_owned()});
+                current_segment = line_ending.to_owned();
+            }
+        },
+        TextEncoding::ZeroWidth=> {
+            let mut characters = text.chars().peekable();
+            while let Some(character) = characters.next() {
+                current_segment.push(character);
+                if character == ' ' {
+                    let hidden = match characters.peek() {
+                        Some(next) if ZERO_WIDTH_CHARACTERS.contains(next)=> characters.next(),
+                        _=> None,
+                    };
+                    let slot_text = hidden.map(String::from).unwrap_or_default();
+                    segments.push(current_segment);
+                    slots.push(Slot{value: encoding.decode(&slot_text), text: slot_text});
+                    current_segment = String::new();
+                }
+            }
+        },
+    }
+    segments.push(current_segment);
+    (segments, slots)
+}
+
+/// How much data can be hidden into a text with every encoding.
+#[derive(Debug, PartialEq, Clone)]
+pub struct TextCapacity {
+    /// Lines of text, every one hides a slot with whitespace encoding.
+    pub lines: u64,
+    /// Gaps between words, every one hides a slot with zero width encoding.
+    pub word_gaps: u64,
+    /// Maximum payload with whitespace encoding, in bytes.
+    pub whitespace_bytes: u64,
+    /// Maximum payload with zero width encoding, in bytes.
+    pub zero_width_bytes: u64,
+}
+
+impl TextCapacity {
+    /// Render this report as a JSON object.
+    pub fn to_json(&self)-> String {
+        format!("{{\"lines\": {}, \"word_gaps\": {}, \"whitespace_bytes\": {}, \
+                 \"zero_width_bytes\": {}}}",
+                self.lines, self.word_gaps, self.whitespace_bytes, self.zero_width_bytes)
+    }
+}
+
+impl fmt::Display for TextCapacity {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "Text size: {} lines and {} gaps between words.", self.lines, self.word_gaps)?;
+        writeln!(f, "Encoding      Max payload (bytes)")?;
+        writeln!(f, "whitespace    {:>19}", self.whitespace_bytes)?;
+        write!(f, "zero-width    {:>19}", self.zero_width_bytes)
+    }
+}
+
+/// A plain text file used to hide data without changing its visible text.
+///
+/// Text is kept in memory and file is written back when *save()* is called or when this type
+/// is dropped, but only if any slot was modified.
+pub struct ContainerText<'a> {
+    encoding: TextEncoding,
+    segments: Vec<String>,
+    slots: Vec<Slot>,
+    file_pathname: &'a str,
+    /// Set when any slot has been overwritten, so text must be saved on drop.
+    modified: bool,
+}
+
+impl <'a> ContainerText<'a> {
+    /// Open a text file as a carrier.
+    ///
+    /// # Parameters:
+    /// * file_pathname: Absolute path to text file.
+    /// * encoding: How data is hidden into text.
+    ///
+    /// # Returns:
+    /// * Text ready to hide data into or extract data from.
+    /// * An error if file could not be read or is not valid UTF-8 text.
+    pub fn new(file_pathname: &'a str, encoding: TextEncoding)-> Result<Self> {
+        if !supported_text(file_pathname) {
+            bail!(ErrorKind::UnsupportedFormat("Text".to_owned(), file_pathname.to_owned()));
+        }
+        let text = fs::read_to_string(file_pathname)
+            .chain_err(|| format!("Error reading host text {}. It must be UTF-8 encoded.",
+                                  file_pathname))?;
+        let (segments, slots) = split_text(&text, encoding);
+        Ok(ContainerText{encoding, segments, slots, file_pathname, modified: false})
+    }
+
+    /// Get current text, with hidden data if any.
+    fn to_text(&self)-> String {
+        let mut text = String::new();
+        for (segment, slot) in self.segments.iter().zip(self.slots.iter()) {
+            text.push_str(segment);
+            text.push_str(&slot.text);
+        }
+        if let Some(last_segment) = self.segments.last() {
+            text.push_str(last_segment);
+        }
+        text
+    }
+}
+
+impl <'a> Carrier for ContainerText<'a> {
+    fn sample_count(&self)-> u64 {
+        self.slots.len() as u64 * self.encoding.slot_bits() as u64
+    }
+
+    fn sample_bits(&self)-> u8 {
+        1
+    }
+
+    fn read_sample(&self, position: u64)-> u64 {
+        let slot_bits = self.encoding.slot_bits() as u64;
+        let shift = slot_bits - 1 - position % slot_bits;
+        ((self.slots[(position / slot_bits) as usize].value >> shift) & 1) as u64
+    }
+
+    fn write_sample(&mut self, position: u64, value: u64) {
+        let slot_bits = self.encoding.slot_bits() as u64;
+        let shift = slot_bits - 1 - position % slot_bits;
+        let encoding = self.encoding;
+        let slot = &mut self.slots[(position / slot_bits) as usize];
+        let new_value = (slot.value & !(1 << shift)) | (((value & 1) as u32) << shift);
+        if new_value != slot.value {
+            slot.value = new_value;
+            slot.text = encoding.encode(new_value);
+            self.modified = true;
+        }
+    }
+
+    fn save(&mut self)-> Result<()> {
+        if self.modified {
+            fs::write(self.file_pathname, self.to_text())
+                .chain_err(|| format!("Error saving host text {}", self.file_pathname))?;
+            self.modified = false;
+        }
+        Ok(())
+    }
+}
+
+/// Save to file every change done over text.
+impl <'a> Drop for ContainerText<'a> {
+    fn drop(&mut self) {
+        self.save().expect("Text could not be saved");
+    }
+}
+
+/// Get how much data can be hidden into a text file with every encoding.
+///
+/// Text file is not modified.
+///
+/// # Parameters:
+/// * host_file: Absolute path to text file to check.
+///
+/// # Returns:
+/// * Capacity report for this text.
+pub fn text_capacity(host_file: &str)-> Result<TextCapacity> {
+    let whitespace_text = ContainerText::new(host_file, TextEncoding::Whitespace)?;
+    let zero_width_text = ContainerText::new(host_file, TextEncoding::ZeroWidth)?;
+    Ok(TextCapacity{
+        lines: whitespace_text.slots.len() as u64,
+        word_gaps: zero_width_text.slots.len() as u64,
+        whitespace_bytes: max_payload_bytes(&whitespace_text),
+        zero_width_bytes: max_payload_bytes(&zero_width_text),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use test_common::fs::tmp::TestEnvironment;
+
+    /// Remove anything that is not visible from a text.
+    fn visible_text(text: &str)-> String {
+        text.lines()
+            .map(|line| line.trim_end_matches([' ', '\t']).replace(&ZERO_WIDTH_CHARACTERS[..], ""))
+            .collect::<Vec<String>>()
+            .join("\n")
+    }
+
+    #[test]
+    fn test_encode_and_decode() {
+        assert_eq!("\t \t\t", TextEncoding::Whitespace.encode(0b1011));
+        assert_eq!("", TextEncoding::Whitespace.encode(0));
+        assert_eq!(0b1011, TextEncoding::Whitespace.decode("\t \t\t"));
+        // Trailing spaces of a clean text decode to 0.
+        assert_eq!(0, TextEncoding::Whitespace.decode("   "));
+        assert_eq!("\u{200D}", TextEncoding::ZeroWidth.encode(3));
+        assert_eq!(2, TextEncoding::ZeroWidth.decode("\u{200C}"));
+        assert_eq!(0, TextEncoding::ZeroWidth.decode(""));
+    }
+
+    #[test]
+    fn test_split_text() {
+        let text = "Lorem ipsum  \r\ndolor\u{200B} sit\u{200C}\n";
+        let (segments, slots) = split_text(text, TextEncoding::Whitespace);
+        assert_eq!(vec!["Lorem ipsum", "\r\ndolor\u{200B} sit\u{200C}", "\n"], segments);
+        assert_eq!(vec!["  ", ""], slots.iter().map(|slot| slot.text.as_str()).collect::<Vec<&str>>());
+        let (segments, slots) = split_text("Lorem ipsum \u{200D}dolor", TextEncoding::ZeroWidth);
+        assert_eq!(vec!["Lorem ", "ipsum ", "dolor"], segments);
+        assert_eq!(vec![0, 3], slots.iter().map(|slot| slot.value).collect::<Vec<u32>>());
+    }
+
+    #[test]
+    fn test_hiding_keeps_visible_text() {
+        let test_env = TestEnvironment::new();
+        let host_path = test_env.path().join("host.txt");
+        let hidden_path = test_env.path().join("hidden.txt");
+        let recovered_path = test_env.path().join("recovered.txt");
+        let text: String = (0..40).map(|i| format!("Line {} of a plain text file.\n", i)).collect();
+        fs::write(&hidden_path, "Meet at noon").expect("Error writing hidden file");
+        for &encoding in [TextEncoding::Whitespace, TextEncoding::ZeroWidth].iter() {
+            fs::write(&host_path, &text).expect("Error writing host text");
+            {
+                let mut host_text = ContainerText::new(host_path.to_str().unwrap(), encoding)
+                    .expect("Error opening host text");
+                hide_into_carrier(hidden_path.to_str().unwrap(), &mut host_text)
+                    .expect("Error hiding into text");
+            }
+            let stego_text = fs::read_to_string(&host_path).expect("Error reading host text");
+            assert_ne!(text, stego_text);
+            assert_eq!(visible_text(&text), visible_text(&stego_text),
+                       "Visible text changed with {} encoding.", encoding);
+            let host_text = ContainerText::new(host_path.to_str().unwrap(), encoding)
+                .expect("Error opening host text");
+            extract_from_carrier(recovered_path.to_str().unwrap(), &host_text)
+                .expect("Error extracting from text");
+            assert_eq!("Meet at noon", fs::read_to_string(&recovered_path).unwrap());
+        }
+    }
+}
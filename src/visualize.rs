@@ -0,0 +1,242 @@
+/// Module to render images that show where data is hidden inside a host image.
+///
+/// Two kinds of images can be rendered:
+/// * Bit planes: a single bit of a single channel is shown as a black and white image. Hidden
+///   data usually looks like noise at least significant bit planes.
+/// * Diff heatmaps: a cover image is compared with its version with hidden data and every
+///   modified pixel is painted with a color that gets hotter the bigger its modification is.
+///
+/// Output image format is guessed from output file extension.
+///
+/// # Usage example:
+/// ```ignore
+/// render_bit_plane("stego.png", Channel::Blue, 0, "plane.png")
+///     .expect("Error rendering bit plane");
+/// ```
+use std::fmt;
+use std::str::FromStr;
+
+use image::{GrayImage, Luma, Rgb, RgbImage};
+
+use crate::*;
+
+/// Highest bit plane index of a channel.
+const MAX_BIT: u8 = 7;
+
+/// Color channels of a host image.
+#[derive(Debug, PartialEq, Copy, Clone)]
+pub enum Channel {
+    Red,
+    Green,
+    Blue,
+}
+
+impl Channel {
+    /// Get this channel position at an RGB pixel.
+    pub(crate) fn index(self)-> usize {
+        match self {
+            Channel::Red=> 0,
+            Channel::Green=> 1,
+            Channel::Blue=> 2,
+        }
+    }
+}
+
+impl FromStr for Channel {
+    type Err = String;
+
+    fn from_str(text: &str)-> std::result::Result<Self, Self::Err> {
+        match text.to_lowercase().as_str() {
+            "r" | "red"=> Ok(Channel::Red),
+            "g" | "green"=> Ok(Channel::Green),
+            "b" | "blue"=> Ok(Channel::Blue),
+            _=> Err(format!("Unknown channel {}. Use r, g or b.", text)),
+        }
+    }
+}
+
+impl fmt::Display for Channel {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match self {
+            Channel::Red=> "red",
+            Channel::Green=> "green",
+            Channel::Blue=> "blue",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+/// Summary of a rendered diff heatmap.
+#[derive(Debug, PartialEq, Clone)]
+pub struct DiffReport {
+    /// How many pixels have any channel modified.
+    pub modified_pixels: u64,
+    /// How many pixels were compared.
+    pub total_pixels: u64,
+    /// Biggest modification found at any pixel, adding absolute differences of its channels.
+    pub max_pixel_delta: u16,
+}
+
+impl fmt::Display for DiffReport {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "Modified pixels: {} of {} ({:.2}%)", self.modified_pixels, self.total_pixels,
+                 100.0 * self.modified_pixels as f64 / self.total_pixels as f64)?;
+        write!(f, "Max pixel delta: {}", self.max_pixel_delta)
+    }
+}
+
+/// Render a single bit plane of a channel as a black and white image.
+///
+/// # Parameters:
+/// * host_image: Image to get bit plane from.
+/// * channel: Channel to get bit plane from.
+/// * bit: Bit plane index, 0 is least significant bit.
+///
+/// # Returns:
+/// * Image with white pixels where bit is set and black ones where it is not.
+fn bit_plane(host_image: &ContainerImage, channel: Channel, bit: u8)-> GrayImage {
+    let (width, height) = host_image.dimensions();
+    GrayImage::from_fn(width, height, |x, y| {
+        let sample = host_image.get_rgb(x, y)[channel.index()];
+        Luma([if (sample >> bit) & 1 == 1 { 255 } else { 0 }])
+    })
+}
+
+/// Get a color from black to red, yellow and white as intensity grows.
+///
+/// # Parameters:
+/// * intensity: Value between 0 and 1.
+fn heat_color(intensity: f64)-> Rgb<u8> {
+    let scaled = (intensity.clamp(0.0, 1.0) * 3.0 * 255.0) as u32;
+    let red = std::cmp::min(scaled, 255) as u8;
+    let green = std::cmp::min(scaled.saturating_sub(255), 255) as u8;
+    let blue = std::cmp::min(scaled.saturating_sub(510), 255) as u8;
+    Rgb([red, green, blue])
+}
+
+/// Render a heatmap of differences between two images.
+///
+/// Unmodified pixels are black. Modified ones are painted from dark red to white, scaled to the
+/// biggest modification found, so even single bit modifications are visible.
+///
+/// # Parameters:
+/// * cover_image: Original image.
+/// * stego_image: Image with hidden data. It must have the same size as cover image.
+///
+/// # Returns:
+/// * Heatmap image and a summary of found differences.
+fn diff_heatmap(cover_image: &ContainerImage, stego_image: &ContainerImage)-> (RgbImage, DiffReport) {
+    let (width, height) = cover_image.dimensions();
+    let mut deltas = Vec::with_capacity(width as usize * height as usize);
+    for y in 0..height {
+        for x in 0..width {
+            let cover_rgb = cover_image.get_rgb(x, y);
+            let stego_rgb = stego_image.get_rgb(x, y);
+            let delta: u16 = cover_rgb.iter().zip(stego_rgb.iter())
+                .map(|(&a, &b)| (std::cmp::max(a, b) - std::cmp::min(a, b)) as u16)
+                .sum();
+            deltas.push(delta);
+        }
+    }
+    let max_pixel_delta = deltas.iter().cloned().max().unwrap_or(0);
+    let modified_pixels = deltas.iter().filter(|&&delta| delta > 0).count() as u64;
+    let heatmap = RgbImage::from_fn(width, height, |x, y| {
+        match deltas[(y * width + x) as usize] {
+            0=> Rgb([0, 0, 0]),
+            // Keep minimum intensity visible against black background.
+            delta=> heat_color(0.2 + 0.8 * delta as f64 / max_pixel_delta as f64),
+        }
+    });
+    let report = DiffReport{modified_pixels, total_pixels: deltas.len() as u64, max_pixel_delta};
+    (heatmap, report)
+}
+
+/// Render a single bit plane of an image channel as a black and white image.
+///
+/// Host image is not modified.
+///
+/// # Parameters:
+/// * host_file: Absolute path to image file to get bit plane from.
+/// * channel: Channel to get bit plane from.
+/// * bit: Bit plane index, from 0 (least significant bit) to 7.
+/// * output_file: Absolute path to image file to render bit plane into.
+pub fn render_bit_plane(host_file: &str, channel: Channel, bit: u8, output_file: &str)-> Result<()> {
+    if bit > MAX_BIT {
+        bail!("Bit plane must be between 0 and {}, but {} was requested.", MAX_BIT, bit);
+    }
+    let host_image = ContainerImage::new(host_file)?;
+    bit_plane(&host_image, channel, bit).save(output_file)
+        .chain_err(|| format!("Error saving bit plane image to {}", output_file))?;
+    Ok(())
+}
+
+/// Render a heatmap of modifications made to a cover image to hide data into it.
+///
+/// Neither cover nor stego images are modified.
+///
+/// # Parameters:
+/// * cover_file: Absolute path to original image file.
+/// * stego_file: Absolute path to image file with hidden data.
+/// * output_file: Absolute path to image file to render heatmap into.
+///
+/// # Returns:
+/// * Summary of differences found.
+pub fn render_diff(cover_file: &str, stego_file: &str, output_file: &str)-> Result<DiffReport> {
+    let cover_image = ContainerImage::new(cover_file)?;
+    let stego_image = ContainerImage::new(stego_file)?;
+    if cover_image.dimensions() != stego_image.dimensions() {
+        bail!("Images have different sizes: {:?} and {:?}.",
+              cover_image.dimensions(), stego_image.dimensions());
+    }
+    let (heatmap, report) = diff_heatmap(&cover_image, &stego_image);
+    heatmap.save(output_file)
+        .chain_err(|| format!("Error saving heatmap image to {}", output_file))?;
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::ImageBuffer;
+    use test_common::fs::tmp::TestEnvironment;
+
+    #[test]
+    fn test_render_bit_plane() {
+        let test_env = TestEnvironment::new();
+        let test_image_path = test_env.path().join("test_image.png");
+        let output_path = test_env.path().join("plane.png");
+        // Blue channel least significant bit is set at odd columns.
+        ImageBuffer::from_fn(16, 16, |x, _| image::Rgb([255_u8, 0, x as u8]))
+            .save(&test_image_path)
+            .expect("Something wrong happened saving test image");
+        render_bit_plane(test_image_path.to_str().unwrap(), Channel::Blue, 0,
+                         output_path.to_str().unwrap())
+            .expect("Error rendering bit plane");
+        let plane = image::open(&output_path).expect("Error opening bit plane").to_luma();
+        for (x, _, pixel) in plane.enumerate_pixels() {
+            assert_eq!(if x % 2 == 1 { 255 } else { 0 }, pixel[0]);
+        }
+    }
+
+    #[test]
+    fn test_render_diff() {
+        let test_env = TestEnvironment::new();
+        let cover_path = test_env.path().join("cover.png");
+        let stego_path = test_env.path().join("stego.png");
+        let output_path = test_env.path().join("heat.png");
+        let cover = ImageBuffer::from_fn(16, 16, |x, y| image::Rgb([x as u8, y as u8, 100_u8]));
+        let mut stego = cover.clone();
+        stego.get_pixel_mut(3, 4)[2] ^= 1;
+        stego.get_pixel_mut(5, 6)[1] = 9;
+        cover.save(&cover_path).expect("Something wrong happened saving cover image");
+        stego.save(&stego_path).expect("Something wrong happened saving stego image");
+        let report = render_diff(cover_path.to_str().unwrap(), stego_path.to_str().unwrap(),
+                                 output_path.to_str().unwrap())
+            .expect("Error rendering diff");
+        assert_eq!(DiffReport{modified_pixels: 2, total_pixels: 256, max_pixel_delta: 3}, report);
+        let heatmap = image::open(&output_path).expect("Error opening heatmap").to_rgb();
+        assert_eq!(&Rgb([0, 0, 0]), heatmap.get_pixel(0, 0));
+        assert_eq!(&Rgb([255, 255, 255]), heatmap.get_pixel(5, 6));
+        assert_ne!(&Rgb([0, 0, 0]), heatmap.get_pixel(3, 4));
+    }
+}
@@ -0,0 +1,297 @@
+/// Module to hide data inside a WAV audio file.
+///
+/// Only uncompressed PCM audio is supported, with 8, 16 or 24 bits per sample and one (mono) or
+/// two (stereo) channels. Every sample of every channel is a carrier sample where data is
+/// hidden, in the same order they are stored at file, so hidden data is spread over both
+/// channels of stereo audio.
+///
+/// Only sample bytes are modified. Any other chunk of WAV file, like metadata ones, is kept
+/// untouched.
+///
+/// # Usage example:
+/// ```ignore
+/// let mut host_audio = ContainerAudio::new("song.wav")
+///                         .expect("Error opening host audio");
+/// println!("{} samples of {} bits", host_audio.sample_count(), host_audio.sample_bits());
+/// ```
+use std::fs;
+use std::path::Path;
+
+use crate::*;
+use crate::carrier::Carrier;
+
+const SUPPORTED_EXTENSIONS: [&str; 1] = ["wav"];
+const SUPPORTED_BITS_PER_SAMPLE: [u16; 3] = [8, 16, 24];
+const MAX_CHANNELS: u16 = 2;
+const WAVE_FORMAT_PCM: u16 = 0x0001;
+const WAVE_FORMAT_EXTENSIBLE: u16 = 0xFFFE;
+/// RIFF header size: "RIFF" tag, file size and "WAVE" tag.
+const RIFF_HEADER_LENGTH: usize = 12;
+/// Chunk header size: chunk tag and chunk size.
+const CHUNK_HEADER_LENGTH: usize = 8;
+
+/// Check if this file is supported as a valid host audio.
+///
+/// Actually this function only checks file extension. Valid extensions for audio files are in
+/// this module *SUPPORTED_EXTENSIONS* const list.
+///
+/// # Parameters:
+/// * filename: Host audio filename.
+///
+/// # Returns:
+/// * True if this audio type is supported and false if not.
+pub(crate) fn supported_audio(filename: &str)-> bool {
+    match Path::new(filename).extension().and_then(|extension| extension.to_str()) {
+        Some(extension)=> SUPPORTED_EXTENSIONS.contains(&extension.to_lowercase().as_str()),
+        None=> false,
+    }
+}
+
+/// Where samples are and how they are encoded at a WAV file.
+#[derive(Debug, PartialEq, Copy, Clone)]
+struct WavLayout {
+    channels: u16,
+    bits_per_sample: u16,
+    /// Position of first sample byte at file.
+    data_offset: usize,
+    /// Length in bytes of samples area.
+    data_length: usize,
+}
+
+/// Read a little endian u16 at given position.
+fn read_u16(content: &[u8], position: usize)-> u16 {
+    u16::from_le_bytes([content[position], content[position + 1]])
+}
+
+/// Read a little endian u32 at given position.
+fn read_u32(content: &[u8], position: usize)-> u32 {
+    u32::from_le_bytes([content[position], content[position + 1],
+                        content[position + 2], content[position + 3]])
+}
+
+/// Parse WAV file content looking for its format and samples.
+///
+/// # Parameters:
+/// * content: Whole WAV file content.
+///
+/// # Returns:
+/// * Layout of samples at file.
+/// * An error if file is not a WAV file or if its audio encoding is not supported.
+fn parse_wav(content: &[u8])-> Result<WavLayout> {
+    if content.len() < RIFF_HEADER_LENGTH || &content[0..4] != b"RIFF" || &content[8..12] != b"WAVE" {
+        bail!("File is not a valid WAV file.");
+    }
+    let mut format: Option<(u16, u16)> = None;
+    let mut data: Option<(usize, usize)> = None;
+    let mut position = RIFF_HEADER_LENGTH;
+    while position + CHUNK_HEADER_LENGTH <= content.len() {
+        let chunk_id = &content[position..position + 4];
+        let chunk_length = read_u32(content, position + 4) as usize;
+        let chunk_start = position + CHUNK_HEADER_LENGTH;
+        // Some encoders leave a wrong length at last chunk, so it is truncated to file end.
+        let available_length = std::cmp::min(chunk_length, content.len() - chunk_start);
+        match chunk_id {
+            b"fmt "=> {
+                if available_length < 16 {
+                    bail!("WAV format chunk is too short.");
+                }
+                let mut format_tag = read_u16(content, chunk_start);
+                if format_tag == WAVE_FORMAT_EXTENSIBLE && available_length >= 26 {
+                    // Actual format is given by first two bytes of subformat GUID.
+                    format_tag = read_u16(content, chunk_start + 24);
+                }
+                if format_tag != WAVE_FORMAT_PCM {
+                    bail!("Only uncompressed PCM WAV files are supported.");
+                }
+                format = Some((read_u16(content, chunk_start + 2),
+                               read_u16(content, chunk_start + 14)));
+            },
+            b"data"=> data = Some((chunk_start, available_length)),
+            _=> {},
+        }
+        // Chunks are padded to an even length.
+        position = chunk_start + chunk_length + chunk_length % 2;
+    }
+    let (channels, bits_per_sample) = match format {
+        Some(format)=> format,
+        None=> bail!("WAV file has no format chunk."),
+    };
+    let (data_offset, data_length) = match data {
+        Some(data)=> data,
+        None=> bail!("WAV file has no data chunk."),
+    };
+    if !SUPPORTED_BITS_PER_SAMPLE.contains(&bits_per_sample) {
+        bail!("Only 8, 16 and 24 bits per sample WAV files are supported, but this one has {} bits \
+               per sample.", bits_per_sample);
+    }
+    if channels == 0 || channels > MAX_CHANNELS {
+        bail!("Only mono and stereo WAV files are supported, but this one has {} channels.",
+              channels);
+    }
+    Ok(WavLayout{channels, bits_per_sample, data_offset, data_length})
+}
+
+/// A WAV audio file used to hide data at its samples.
+///
+/// Samples are kept in memory and file is written back when *save()* is called or when this type
+/// is dropped, but only if any sample was modified.
+pub struct ContainerAudio<'a> {
+    content: Vec<u8>,
+    layout: WavLayout,
+    file_pathname: &'a str,
+    /// Set when any sample has been overwritten, so audio must be saved on drop.
+    modified: bool,
+}
+
+impl <'a> ContainerAudio<'a> {
+    /// Open a WAV audio file as a carrier.
+    ///
+    /// # Parameters:
+    /// * file_pathname: Absolute path to WAV file.
+    ///
+    /// # Returns:
+    /// * Audio ready to hide data into or extract data from.
+    /// * An error if file could not be read or its format is not supported.
+    pub fn new(file_pathname: &'a str)-> Result<Self> {
+        if !supported_audio(file_pathname) {
+            bail!(ErrorKind::UnsupportedFormat("Audio".to_owned(), file_pathname.to_owned()));
+        }
+        let content = fs::read(file_pathname)
+            .chain_err(|| format!("Error reading host audio {}", file_pathname))?;
+        let layout = parse_wav(&content)
+            .chain_err(|| format!("Error parsing host audio {}", file_pathname))?;
+        Ok(ContainerAudio{content, layout, file_pathname, modified: false})
+    }
+
+    /// Get position at file of first byte of nth sample.
+    fn sample_offset(&self, position: u64)-> usize {
+        self.layout.data_offset + position as usize * self.bytes_per_sample()
+    }
+
+    fn bytes_per_sample(&self)-> usize {
+        self.layout.bits_per_sample as usize / 8
+    }
+}
+
+impl <'a> Carrier for ContainerAudio<'a> {
+    fn sample_count(&self)-> u64 {
+        (self.layout.data_length / self.bytes_per_sample()) as u64
+    }
+
+    fn sample_bits(&self)-> u8 {
+        self.layout.bits_per_sample as u8
+    }
+
+    /// Samples are stored in little endian order. 8 bits samples are unsigned and wider ones are
+    /// signed, but as only least significant bits are modified sign does not matter.
+    fn read_sample(&self, position: u64)-> u64 {
+        let offset = self.sample_offset(position);
+        self.content[offset..offset + self.bytes_per_sample()].iter().rev()
+            .fold(0_u64, |value, &byte| (value << 8) | byte as u64)
+    }
+
+    fn write_sample(&mut self, position: u64, value: u64) {
+        let offset = self.sample_offset(position);
+        let bytes_per_sample = self.bytes_per_sample();
+        self.content[offset..offset + bytes_per_sample]
+            .copy_from_slice(&value.to_le_bytes()[..bytes_per_sample]);
+        self.modified = true;
+    }
+
+    fn save(&mut self)-> Result<()> {
+        if self.modified {
+            fs::write(self.file_pathname, &self.content)
+                .chain_err(|| format!("Error saving host audio {}", self.file_pathname))?;
+            self.modified = false;
+        }
+        Ok(())
+    }
+}
+
+/// Save to file every change done over audio.
+///
+/// Audio samples are modified in memory so changes should be written before disposing
+/// ContainerAudio. Audio files that have not been modified are not written again.
+impl <'a> Drop for ContainerAudio<'a> {
+    fn drop(&mut self) {
+        self.save().expect("Audio could not be saved");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use test_common::fs::tmp::TestEnvironment;
+
+    /// Build a WAV file content with given format and samples bytes.
+    ///
+    /// A metadata chunk with odd length is placed before data chunk to check it is skipped.
+    fn wav_content(format_tag: u16, channels: u16, bits_per_sample: u16, samples: &[u8])-> Vec<u8> {
+        let mut content = Vec::new();
+        content.extend_from_slice(b"RIFF");
+        content.extend_from_slice(&0_u32.to_le_bytes());
+        content.extend_from_slice(b"WAVE");
+        content.extend_from_slice(b"fmt ");
+        content.extend_from_slice(&16_u32.to_le_bytes());
+        content.extend_from_slice(&format_tag.to_le_bytes());
+        content.extend_from_slice(&channels.to_le_bytes());
+        content.extend_from_slice(&44_100_u32.to_le_bytes());
+        let block_align = channels * bits_per_sample / 8;
+        content.extend_from_slice(&(44_100 * block_align as u32).to_le_bytes());
+        content.extend_from_slice(&block_align.to_le_bytes());
+        content.extend_from_slice(&bits_per_sample.to_le_bytes());
+        content.extend_from_slice(b"LIST");
+        content.extend_from_slice(&3_u32.to_le_bytes());
+        content.extend_from_slice(b"abc\x00");
+        content.extend_from_slice(b"data");
+        content.extend_from_slice(&(samples.len() as u32).to_le_bytes());
+        content.extend_from_slice(samples);
+        let riff_length = (content.len() - 8) as u32;
+        content[4..8].copy_from_slice(&riff_length.to_le_bytes());
+        content
+    }
+
+    #[test]
+    fn test_supported_audio() {
+        assert!(supported_audio("song.wav"));
+        assert!(supported_audio("/tmp/song.WAV"));
+        assert!(!supported_audio("song.mp3"));
+        assert!(!supported_audio("song"));
+    }
+
+    #[test]
+    fn test_parse_wav() {
+        let content = wav_content(WAVE_FORMAT_PCM, 2, 16, &[0; 8]);
+        assert_eq!(WavLayout{channels: 2, bits_per_sample: 16, data_offset: 56, data_length: 8},
+                   parse_wav(&content).unwrap());
+        assert!(parse_wav(&wav_content(3, 2, 16, &[0; 8])).is_err(),
+                "Floating point audio should not be supported.");
+        assert!(parse_wav(&wav_content(WAVE_FORMAT_PCM, 2, 32, &[0; 8])).is_err(),
+                "32 bits audio should not be supported.");
+        assert!(parse_wav(&wav_content(WAVE_FORMAT_PCM, 6, 16, &[0; 12])).is_err(),
+                "Surround audio should not be supported.");
+        assert!(parse_wav(b"RIFF\x00\x00\x00\x00AVI ").is_err());
+    }
+
+    #[test]
+    fn test_read_and_write_samples() {
+        let test_env = TestEnvironment::new();
+        let audio_path = test_env.path().join("test.wav");
+        let samples = [0x01, 0x02, 0x03, 0xfd, 0xfe, 0xff];
+        let content = wav_content(WAVE_FORMAT_PCM, 1, 24, &samples);
+        fs::write(&audio_path, &content).expect("Error writing test audio");
+        {
+            let mut audio = ContainerAudio::new(audio_path.to_str().unwrap())
+                .expect("Error opening test audio");
+            assert_eq!(2, audio.sample_count());
+            assert_eq!(24, audio.sample_bits());
+            assert_eq!(0x03_02_01, audio.read_sample(0));
+            assert_eq!(0xff_fe_fd, audio.read_sample(1));
+            audio.write_sample(1, 0x12_34_56);
+        }
+        let modified_content = fs::read(&audio_path).expect("Error reading modified audio");
+        assert_eq!(content.len(), modified_content.len());
+        assert_eq!(&content[..59], &modified_content[..59]);
+        assert_eq!(&[0x56, 0x34, 0x12], &modified_content[59..]);
+    }
+}
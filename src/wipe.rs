@@ -0,0 +1,209 @@
+/// Module to destroy data hidden inside an image.
+///
+/// Least significant bit planes of every RGB channel are replaced by random noise. Noise keeps
+/// the same proportion of ones that every replaced plane had, so wiped image statistics look like
+/// original ones. That way any data hidden at those planes is destroyed, no matter if it was
+/// hidden by steganer or by any other LSB tool.
+///
+/// # Usage example:
+/// ```ignore
+/// let report = wipe_image("to_publish.png", &WipeOptions::default())
+///                 .expect("Error wiping image");
+/// println!("{}", report);
+/// ```
+use std::fmt;
+
+use rand::Rng;
+
+use crate::*;
+
+/// Maximum bit plane depth that can be wiped at every channel.
+const MAX_WIPE_DEPTH: u8 = 8;
+const CHANNEL_NAMES: [&str; 3] = ["red", "green", "blue"];
+
+/// Options to tune how deep an image is wiped.
+#[derive(Debug, Clone)]
+pub struct WipeOptions {
+    /// How many least significant bit planes are wiped at every channel. Planes used by a
+    /// steganer payload, if any is found, are wiped too even if they are deeper.
+    pub depth: u8,
+}
+
+impl Default for WipeOptions {
+    fn default()-> Self {
+        WipeOptions{depth: 1}
+    }
+}
+
+/// Summary of a wipe operation.
+#[derive(Debug, PartialEq, Clone)]
+pub struct WipeReport {
+    /// How many least significant bit planes were wiped at red, green and blue channels.
+    pub planes: [u8; 3],
+    /// Whether a steganer payload was found before wiping.
+    pub steganer_payload_found: bool,
+    /// How many channel samples changed their value.
+    pub modified_samples: u64,
+}
+
+impl fmt::Display for WipeReport {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.steganer_payload_found {
+            writeln!(f, "Steganer payload found and destroyed.")?;
+        }
+        for (channel, planes) in CHANNEL_NAMES.iter().zip(self.planes.iter()) {
+            writeln!(f, "Wiped {} least significant bits of {} channel.", planes, channel)?;
+        }
+        write!(f, "Modified samples: {}.", self.modified_samples)
+    }
+}
+
+/// Get how many bit planes should be wiped at every channel.
+///
+/// Steganer hides data at the least significant bits of a 24 bits value built from red, green
+/// and blue channels, so blue channel bits are used first, then green ones and finally red ones.
+///
+/// # Parameters:
+/// * depth: Minimum planes to wipe at every channel.
+/// * steganer_bits_per_pixel: Bits per pixel used by a steganer payload, if any was found.
+///
+/// # Returns:
+/// * Planes to wipe at red, green and blue channels.
+fn planes_to_wipe(depth: u8, steganer_bits_per_pixel: Option<u8>)-> [u8; 3] {
+    let used_bits = steganer_bits_per_pixel.unwrap_or(0);
+    let blue_bits = std::cmp::min(used_bits, 8);
+    let green_bits = std::cmp::min(used_bits.saturating_sub(8), 8);
+    let red_bits = std::cmp::min(used_bits.saturating_sub(16), 8);
+    [std::cmp::max(depth, red_bits),
+     std::cmp::max(depth, green_bits),
+     std::cmp::max(depth, blue_bits)]
+}
+
+/// Replace given bit planes with random noise.
+///
+/// # Parameters:
+/// * host_image: Image to wipe.
+/// * planes: How many least significant bit planes to wipe at red, green and blue channels.
+/// * rng: Random number generator to produce noise.
+///
+/// # Returns:
+/// * How many channel samples changed their value.
+fn wipe_planes<R: Rng>(host_image: &mut ContainerImage, planes: [u8; 3], rng: &mut R)-> u64 {
+    let (width, height) = host_image.dimensions();
+    let total_pixels = width as u64 * height as u64;
+    // First count ones at every plane to get noise probabilities.
+    let mut ones = [[0_u64; MAX_WIPE_DEPTH as usize]; 3];
+    for y in 0..height {
+        for x in 0..width {
+            let rgb = host_image.get_rgb(x, y);
+            for (channel, channel_ones) in ones.iter_mut().enumerate() {
+                for (plane, plane_ones) in channel_ones.iter_mut().take(planes[channel] as usize).enumerate() {
+                    *plane_ones += ((rgb[channel] >> plane) & 1) as u64;
+                }
+            }
+        }
+    }
+    let mut probabilities = [[0_f64; MAX_WIPE_DEPTH as usize]; 3];
+    for (channel_probabilities, channel_ones) in probabilities.iter_mut().zip(ones.iter()) {
+        for (probability, plane_ones) in channel_probabilities.iter_mut().zip(channel_ones.iter()) {
+            *probability = *plane_ones as f64 / total_pixels as f64;
+        }
+    }
+    // Now replace planes with noise.
+    let mut modified_samples = 0_u64;
+    for y in 0..height {
+        for x in 0..width {
+            let original_rgb = host_image.get_rgb(x, y);
+            let mut wiped_rgb = original_rgb;
+            for channel in 0..3 {
+                let plane_probabilities = probabilities[channel].iter().take(planes[channel] as usize);
+                for (plane, probability) in plane_probabilities.enumerate() {
+                    let bit = rng.gen_bool(*probability) as u8;
+                    wiped_rgb[channel] = (wiped_rgb[channel] & !(1 << plane)) | (bit << plane);
+                }
+                if wiped_rgb[channel] != original_rgb[channel] { modified_samples += 1; }
+            }
+            if wiped_rgb != original_rgb {
+                host_image.set_rgb(x, y, wiped_rgb);
+            }
+        }
+    }
+    modified_samples
+}
+
+/// Destroy any data hidden at least significant bits of an image.
+///
+/// Image is overwritten with its wiped version.
+///
+/// # Parameters:
+/// * host_file: Absolute path to image file to wipe.
+/// * options: How deep image should be wiped.
+///
+/// # Returns:
+/// * Summary of wiped planes.
+/// * An error if image could not be read or wiped image could not be saved.
+pub fn wipe_image(host_file: &str, options: &WipeOptions)-> Result<WipeReport> {
+    if options.depth == 0 || options.depth > MAX_WIPE_DEPTH {
+        bail!("Wipe depth must be between 1 and {}, but {} was requested.",
+              MAX_WIPE_DEPTH, options.depth);
+    }
+    let mut host_image = ContainerImage::new(host_file)?;
+    let steganer_bits_per_pixel = probe_header(&host_image)
+        .map(|(_, bits_per_pixel)| bits_per_pixel);
+    let planes = planes_to_wipe(options.depth, steganer_bits_per_pixel);
+    let modified_samples = wipe_planes(&mut host_image, planes, &mut rand::thread_rng());
+    host_image.save()?;
+    Ok(WipeReport{planes, steganer_payload_found: steganer_bits_per_pixel.is_some(), modified_samples})
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::ImageBuffer;
+    use rand::SeedableRng;
+    use rand::rngs::StdRng;
+    use test_common::fs::tmp::TestEnvironment;
+
+    #[test]
+    fn test_planes_to_wipe() {
+        assert_eq!([1, 1, 1], planes_to_wipe(1, None));
+        assert_eq!([3, 3, 3], planes_to_wipe(3, None));
+        // Up to 8 bits per pixel steganer only uses blue channel.
+        assert_eq!([1, 1, 5], planes_to_wipe(1, Some(5)));
+        assert_eq!([2, 4, 8], planes_to_wipe(2, Some(12)));
+        assert_eq!([3, 8, 8], planes_to_wipe(1, Some(19)));
+    }
+
+    #[test]
+    fn test_wipe_planes() {
+        let test_env = TestEnvironment::new();
+        let test_image_path = test_env.path().join("test_image.png");
+        // Alternate ones and zeros at least significant bit so noise should be balanced.
+        let test_image = ImageBuffer::from_fn(64, 64, |x, _| {
+            let value = 0b_1010_0000_u8 + (x % 2) as u8;
+            image::Rgb([value, value, value])
+        });
+        test_image.save(&test_image_path)
+            .expect("Something wrong happened saving test image");
+        let mut container = ContainerImage::new(test_image_path.to_str()
+            .expect("Something wrong happened converting test image path to str")).unwrap();
+        let mut rng = StdRng::seed_from_u64(31);
+        let modified_samples = wipe_planes(&mut container, [0, 1, 1], &mut rng);
+        assert!(modified_samples > 0, "No sample was modified after wiping.");
+        let mut blue_ones = 0;
+        for y in 0..64 {
+            for x in 0..64 {
+                let rgb = container.get_rgb(x, y);
+                // Upper planes and unwiped channels must be kept untouched.
+                assert_eq!(0b_1010_0000_u8 + (x % 2) as u8, rgb[0],
+                           "Red channel was modified at ({}, {}).", x, y);
+                assert_eq!(0b_1010_0000_u8, rgb[2] & 0b_1111_1110,
+                           "Blue channel upper planes were modified at ({}, {}).", x, y);
+                blue_ones += (rgb[2] & 1) as u32;
+            }
+        }
+        // Half of 4096 pixels should have its least significant bit set, give or take.
+        assert!(blue_ones > 1800 && blue_ones < 2300,
+                "Wiped plane is not balanced as original was. Found {} ones in 4096 pixels.", blue_ones);
+    }
+}
@@ -0,0 +1,298 @@
+/// Module to hide data inside an uncompressed YUV4MPEG2 (Y4M) video file.
+///
+/// Only 8 bits per sample videos are supported, with 4:2:0, 4:2:2, 4:4:4, 4:4:4 with alpha and
+/// monochrome planar layouts. Every byte of luma and chroma planes of every frame is a carrier
+/// sample.
+///
+/// Samples are interleaved across frames: first sample is first byte of first frame, second
+/// sample is first byte of second frame and so on. That way hidden data is spread over the whole
+/// video instead of being packed into its first frames.
+///
+/// Only plane bytes are modified. Stream and frame headers are kept untouched, so resulting file
+/// is still a valid Y4M video.
+///
+/// # Usage example:
+/// ```ignore
+/// let mut host_video = ContainerVideo::new("clip.y4m")
+///                         .expect("Error opening host video");
+/// println!("{} samples at {} frames", host_video.sample_count(), host_video.frame_count());
+/// ```
+use std::fs;
+use std::path::Path;
+
+use crate::*;
+use crate::carrier::Carrier;
+
+const SUPPORTED_EXTENSIONS: [&str; 1] = ["y4m"];
+const STREAM_MAGIC: &[u8] = b"YUV4MPEG2";
+const FRAME_MAGIC: &[u8] = b"FRAME";
+/// Colorspace used by Y4M files that do not set one.
+const DEFAULT_COLORSPACE: &str = "420";
+
+/// Check if this file is supported as a valid host video.
+///
+/// # Parameters:
+/// * filename: Host video filename.
+///
+/// # Returns:
+/// * True if this video type is supported and false if not.
+pub(crate) fn supported_video(filename: &str)-> bool {
+    match Path::new(filename).extension().and_then(|extension| extension.to_str()) {
+        Some(extension)=> SUPPORTED_EXTENSIONS.contains(&extension.to_lowercase().as_str()),
+        None=> false,
+    }
+}
+
+/// Get size in bytes of every frame of a Y4M video.
+///
+/// # Parameters:
+/// * width: Frame width in pixels.
+/// * height: Frame height in pixels.
+/// * colorspace: Y4M colorspace tag value, like "420jpeg" or "444".
+///
+/// # Returns:
+/// * Bytes of all planes of a frame.
+/// * An error if colorspace is not supported or if frame size does not fit in memory.
+fn frame_length(width: usize, height: usize, colorspace: &str)-> Result<usize> {
+    // Frame size comes from file header, so it can be as big as anyone wants.
+    let luma = width.checked_mul(height);
+    let half_width = width.div_ceil(2);
+    let with_chroma = |chroma_height: usize| half_width.checked_mul(chroma_height)
+        .and_then(|chroma| chroma.checked_mul(2))
+        .and_then(|chroma| luma?.checked_add(chroma));
+    let length = match colorspace {
+        "420" | "420jpeg" | "420paldv" | "420mpeg2"=> with_chroma(height.div_ceil(2)),
+        "422"=> with_chroma(height),
+        "444"=> luma.and_then(|luma| luma.checked_mul(3)),
+        "444alpha"=> luma.and_then(|luma| luma.checked_mul(4)),
+        "mono"=> luma,
+        _=> bail!("Only 8 bits 420, 422, 444, 444alpha and mono Y4M videos are supported, but \
+                   this one has {} colorspace.", colorspace),
+    };
+    match length {
+        Some(length)=> Ok(length),
+        None=> bail!("Y4M frame size {}x{} is too big.", width, height),
+    }
+}
+
+/// Find position of next line feed starting at given position.
+fn find_line_end(content: &[u8], start: usize)-> Result<usize> {
+    match content[start..].iter().position(|&byte| byte == b'\n') {
+        Some(length)=> Ok(start + length),
+        None=> bail!("Y4M header is not terminated."),
+    }
+}
+
+/// Parse Y4M file content looking for its frames.
+///
+/// # Parameters:
+/// * content: Whole Y4M file content.
+///
+/// # Returns:
+/// * Position at file of first plane byte of every frame.
+/// * Size in bytes of every frame.
+/// * An error if file is not a Y4M file, if it has no frames or if its layout is not supported.
+fn parse_y4m(content: &[u8])-> Result<(Vec<usize>, usize)> {
+    if !content.starts_with(STREAM_MAGIC) {
+        bail!("File is not a valid Y4M file.");
+    }
+    let header_end = find_line_end(content, 0)?;
+    let header = String::from_utf8_lossy(&content[STREAM_MAGIC.len()..header_end]);
+    let mut width: Option<usize> = None;
+    let mut height: Option<usize> = None;
+    let mut colorspace = DEFAULT_COLORSPACE.to_owned();
+    for parameter in header.split_whitespace() {
+        // Tag is a single character, but lossy decoding may have made it wider than a byte.
+        let mut characters = parameter.chars();
+        let tag = characters.next();
+        let value = characters.as_str();
+        match tag {
+            Some('W')=> width = value.parse().ok(),
+            Some('H')=> height = value.parse().ok(),
+            Some('C')=> colorspace = value.to_owned(),
+            _=> {},
+        }
+    }
+    let (width, height) = match (width, height) {
+        (Some(width), Some(height)) if width > 0 && height > 0=> (width, height),
+        _=> bail!("Y4M header has no valid frame size."),
+    };
+    let frame_length = frame_length(width, height, &colorspace)?;
+    let mut frame_offsets = Vec::new();
+    let mut position = header_end + 1;
+    while position < content.len() {
+        if !content[position..].starts_with(FRAME_MAGIC) {
+            bail!("Y4M frame {} has no valid header.", frame_offsets.len());
+        }
+        let frame_start = find_line_end(content, position)? + 1;
+        if frame_length > content.len() - frame_start {
+            bail!("Y4M frame {} is truncated.", frame_offsets.len());
+        }
+        frame_offsets.push(frame_start);
+        position = frame_start + frame_length;
+    }
+    if frame_offsets.is_empty() {
+        bail!("Y4M file has no frames.");
+    }
+    Ok((frame_offsets, frame_length))
+}
+
+/// A Y4M video file used to hide data at its frames planes.
+///
+/// Frames are kept in memory and file is written back when *save()* is called or when this type
+/// is dropped, but only if any sample was modified.
+pub struct ContainerVideo<'a> {
+    content: Vec<u8>,
+    frame_offsets: Vec<usize>,
+    frame_length: usize,
+    file_pathname: &'a str,
+    /// Set when any sample has been overwritten, so video must be saved on drop.
+    modified: bool,
+}
+
+impl <'a> ContainerVideo<'a> {
+    /// Open a Y4M video file as a carrier.
+    ///
+    /// # Parameters:
+    /// * file_pathname: Absolute path to Y4M file.
+    ///
+    /// # Returns:
+    /// * Video ready to hide data into or extract data from.
+    /// * An error if file could not be read, has no frames or its format is not supported.
+    pub fn new(file_pathname: &'a str)-> Result<Self> {
+        if !supported_video(file_pathname) {
+            bail!(ErrorKind::UnsupportedFormat("Video".to_owned(), file_pathname.to_owned()));
+        }
+        let content = fs::read(file_pathname)
+            .chain_err(|| format!("Error reading host video {}", file_pathname))?;
+        let (frame_offsets, frame_length) = parse_y4m(&content)
+            .chain_err(|| format!("Error parsing host video {}", file_pathname))?;
+        Ok(ContainerVideo{content, frame_offsets, frame_length, file_pathname, modified: false})
+    }
+
+    /// Get how many frames this video has.
+    pub fn frame_count(&self)-> usize {
+        self.frame_offsets.len()
+    }
+
+    /// Get position at file of nth sample, interleaving samples across frames.
+    fn sample_offset(&self, position: u64)-> usize {
+        let frame_count = self.frame_count() as u64;
+        self.frame_offsets[(position % frame_count) as usize] + (position / frame_count) as usize
+    }
+}
+
+impl <'a> Carrier for ContainerVideo<'a> {
+    fn sample_count(&self)-> u64 {
+        (self.frame_count() * self.frame_length) as u64
+    }
+
+    fn sample_bits(&self)-> u8 {
+        8
+    }
+
+    fn read_sample(&self, position: u64)-> u64 {
+        self.content[self.sample_offset(position)] as u64
+    }
+
+    fn write_sample(&mut self, position: u64, value: u64) {
+        let offset = self.sample_offset(position);
+        self.content[offset] = value as u8;
+        self.modified = true;
+    }
+
+    fn save(&mut self)-> Result<()> {
+        if self.modified {
+            fs::write(self.file_pathname, &self.content)
+                .chain_err(|| format!("Error saving host video {}", self.file_pathname))?;
+            self.modified = false;
+        }
+        Ok(())
+    }
+}
+
+/// Save to file every change done over video.
+impl <'a> Drop for ContainerVideo<'a> {
+    fn drop(&mut self) {
+        self.save().expect("Video could not be saved");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use test_common::fs::tmp::TestEnvironment;
+
+    /// Build a Y4M file content with given frames, filled with their index.
+    fn y4m_content(header: &str, frame_length: usize, frames: u8)-> Vec<u8> {
+        let mut content = format!("{}\n", header).into_bytes();
+        for frame in 0..frames {
+            content.extend_from_slice(b"FRAME\n");
+            content.extend(std::iter::repeat_n(frame, frame_length));
+        }
+        content
+    }
+
+    #[test]
+    fn test_supported_video() {
+        assert!(supported_video("clip.y4m"));
+        assert!(supported_video("/tmp/clip.Y4M"));
+        assert!(!supported_video("clip.mp4"));
+    }
+
+    #[test]
+    fn test_frame_length() {
+        assert_eq!(6 * 4 + 2 * 3 * 2, frame_length(6, 4, "420jpeg").unwrap());
+        assert_eq!(5 * 3 + 2 * 3 * 2, frame_length(5, 3, "420").unwrap());
+        assert_eq!(6 * 4 + 2 * 3 * 4, frame_length(6, 4, "422").unwrap());
+        assert_eq!(3 * 6 * 4, frame_length(6, 4, "444").unwrap());
+        assert_eq!(6 * 4, frame_length(6, 4, "mono").unwrap());
+        assert!(frame_length(6, 4, "420p10").is_err(), "10 bits video should not be supported.");
+        assert!(frame_length(usize::MAX, 2, "mono").is_err(), "Overflowing frames should fail.");
+        assert!(frame_length(usize::MAX / 2, 1, "444").is_err(), "Overflowing frames should fail.");
+    }
+
+    #[test]
+    fn test_parse_y4m() {
+        let header = "YUV4MPEG2 W4 H2 F25:1 Ip A1:1 C420jpeg XYSCSS=420JPEG";
+        let content = y4m_content(header, 12, 3);
+        let header_length = header.len() + 1;
+        assert_eq!((vec![header_length + 6, header_length + 24, header_length + 42], 12),
+                   parse_y4m(&content).unwrap());
+        // Colorspace defaults to 4:2:0.
+        let (frame_offsets, frame_length) = parse_y4m(&y4m_content("YUV4MPEG2 W4 H2", 12, 1)).unwrap();
+        assert_eq!((1, 12), (frame_offsets.len(), frame_length));
+        assert!(parse_y4m(&content[..content.len() - 1]).is_err(), "Truncated frame should fail.");
+        assert!(parse_y4m(b"YUV4MPEG2 H2\n").is_err(), "Missing width should fail.");
+        assert!(parse_y4m(b"RIFF").is_err());
+        assert!(parse_y4m(b"YUV4MPEG2 W2 H2\n").is_err(), "Video without frames should fail.");
+        // Non ASCII parameters are ignored instead of splitting a character apart.
+        let mut content = b"YUV4MPEG2 W2 H2 \xff\nFRAME\n".to_vec();
+        content.extend_from_slice(&[0; 6]);
+        assert_eq!((vec![content.len() - 6], 6), parse_y4m(&content).unwrap());
+        // Frame size products must not overflow.
+        let mut content = b"YUV4MPEG2 W99999999999 H99999999999\nFRAME\n".to_vec();
+        content.extend_from_slice(&[0; 6]);
+        assert!(parse_y4m(&content).is_err(), "Oversized frames should fail.");
+    }
+
+    #[test]
+    fn test_samples_interleaved_across_frames() {
+        let test_env = TestEnvironment::new();
+        let video_path = test_env.path().join("test.y4m");
+        let content = y4m_content("YUV4MPEG2 W2 H2 Cmono", 4, 3);
+        fs::write(&video_path, &content).expect("Error writing test video");
+        {
+            let mut video = ContainerVideo::new(video_path.to_str().unwrap())
+                .expect("Error opening test video");
+            assert_eq!(12, video.sample_count());
+            assert_eq!(vec![0, 1, 2, 0], (0..4).map(|i| video.read_sample(i)).collect::<Vec<u64>>());
+            video.write_sample(4, 0xff);
+        }
+        let modified_content = fs::read(&video_path).expect("Error reading modified video");
+        // Fifth sample is second byte of second frame.
+        let second_frame = "YUV4MPEG2 W2 H2 Cmono\n".len() + 2 * "FRAME\n".len() + 4;
+        assert_eq!(0xff, modified_content[second_frame + 1]);
+        assert_eq!(content.len(), modified_content.len());
+    }
+}
@@ -1,9 +1,17 @@
 use std::env::current_dir;
 //use std::fs::read;
+use std::io::{Read, Seek, SeekFrom, Write};
 use std::path::Path;
 
 use steganer::_run;
+use steganer::_run_with_exit_code;
 use steganer::_create_configuration;
+use steganer::Steganer;
+use steganer::{analyze_image, dump_image, extract_bytes_from_image, extract_from_animation, extract_from_audio, extract_from_carrier, extract_from_image, extract_from_image_with_threads, extract_from_text, hide_bytes_into_image, hide_into_carrier, hide_into_image, hide_into_image_with_report, hide_into_image_with_threads, hide_into_video, image_capacity, probe_image,
+               scan_image, text_capacity, verify_image, wipe_image, Carrier, CapacityOptions, Channel, ContainerAudio, ContainerImage, DumpOptions,
+               Finding, HiddenReader, HiddenWriter, Operation, ProbeStatus, TextEncoding, Verdict, WipeOptions};
+use image::AnimationDecoder;
+use num::rational::Ratio;
 use test_common::fs::{ops::copy_files, crypto::hash_file, tmp::TestEnvironment};
 
 const SOURCE_FOLDER: &str = "tests/resources/";
@@ -107,4 +115,613 @@ fn test_incorrect_hiding() {
     for image in incorrect_images {
         hide_test(image.as_str());
     }
-}
\ No newline at end of file
+}
+
+#[test]
+fn test_image_capacity() {
+    let host_file_path = Path::new(SOURCE_FOLDER).join(format!("{}.png", HOST_FILE_NAME_SUFFIX));
+    let capacity = image_capacity(host_file_path.to_str()
+                                      .expect("Host file name has not valid unicode characters."),
+                                  &CapacityOptions::default())
+        .expect("Error getting test image capacity");
+    // Lenna test image is 512x512 pixels.
+    assert_eq!((512, 512), (capacity.width, capacity.height));
+    assert_eq!(786_336, capacity.max_payload_bytes(),
+               "Maximum payload was not what we were expecting. Expected {} but got {}",
+               786_336, capacity.max_payload_bytes());
+}
+
+/// Copy file to hide and a host image to a temporary folder.
+///
+/// # Returns:
+/// * TestEnvironment: Handle to temporary folder. Keep it in scope, if it leaves from scope then
+///   temporary folder is removed.
+/// * String: Absolute path to copied file to hide.
+/// * String: Absolute path to copied host image.
+fn setup_test_files(host_file: &str)-> (TestEnvironment, String, String) {
+    let test_folder = TestEnvironment::new();
+    let file_hidden_path = Path::new(SOURCE_FOLDER).join(HIDDEN_FILE);
+    let host_file_path = Path::new(SOURCE_FOLDER).join(host_file);
+    copy_files(vec![file_hidden_path.to_str().unwrap(), host_file_path.to_str().unwrap()],
+               test_folder.path().to_str()
+                   .expect("Test folder path contains non valid unicode characters."))
+        .expect("Error copying test files.");
+    let test_hidden_file = test_folder.path().join(HIDDEN_FILE).into_os_string().into_string()
+        .expect("Hidden file name has no valid unicode characters");
+    let test_host_file = test_folder.path().join(host_file).into_os_string().into_string()
+        .expect("Host file name has no valid unicode characters");
+    (test_folder, test_hidden_file, test_host_file)
+}
+
+/// Create a grayscale PNG and a truncated copy of it.
+///
+/// # Parameters:
+/// * folder: Where to write both images.
+///
+/// # Returns:
+/// * Absolute paths to grayscale image and to truncated image.
+fn create_grayscale_images(folder: &Path)-> (String, String) {
+    let grayscale_path = folder.join("grayscale.png");
+    let truncated_path = folder.join("truncated.png");
+    image::ImageBuffer::from_fn(64, 64, |x, y| image::Luma([(x * 3 + y) as u8]))
+        .save(&grayscale_path)
+        .expect("Error saving grayscale test image");
+    let content = std::fs::read(&grayscale_path).expect("Error reading grayscale test image");
+    std::fs::write(&truncated_path, &content[..content.len() / 2])
+        .expect("Error writing truncated test image");
+    (grayscale_path.to_str().unwrap().to_owned(), truncated_path.to_str().unwrap().to_owned())
+}
+
+#[test]
+fn test_probe_image() {
+    let (_test_folder, test_hidden_file, test_host_file) =
+        setup_test_files(format!("{}.png", HOST_FILE_NAME_SUFFIX).as_str());
+    // Original image should have no payload.
+    let clean_report = probe_image(test_host_file.as_str())
+        .expect("Error probing clean image");
+    assert_eq!(ProbeStatus::NoPayload, clean_report.status,
+               "Clean image was not reported as payload free.");
+    // Once something is hidden it should be found.
+    let hiding_config = _create_configuration(test_hidden_file.as_str(),
+                                              test_host_file.as_str(),
+                                              false);
+    _run(&hiding_config).expect("Error hiding test file");
+    let report = probe_image(test_host_file.as_str())
+        .expect("Error probing image with hidden data");
+    assert_eq!(ProbeStatus::PayloadFound, report.status,
+               "Hidden data was not found.");
+    let payload = report.payload.expect("No payload details were reported.");
+    let hidden_file_size = std::fs::metadata(&test_hidden_file)
+        .expect("Error reading hidden file metadata").len();
+    assert_eq!(hidden_file_size, payload.size as u64,
+               "Reported payload size is not what we were expecting. Expected {} but got {}",
+               hidden_file_size, payload.size);
+}
+
+#[test]
+fn test_probe_unusual_images() {
+    let test_folder = TestEnvironment::new();
+    let (grayscale_file, truncated_file) = create_grayscale_images(test_folder.path());
+    let report = probe_image(grayscale_file.as_str()).expect("Error probing grayscale image");
+    assert_eq!(ProbeStatus::NoPayload, report.status);
+    let mut info_config = _create_configuration("", grayscale_file.as_str(), false);
+    info_config.operation = Operation::Info;
+    assert_eq!(ProbeStatus::NoPayload.exit_code(), _run_with_exit_code(&info_config).unwrap());
+    assert!(probe_image(truncated_file.as_str()).is_err(), "Truncated image was not reported.");
+}
+
+#[test]
+fn test_grayscale_image_capacity() {
+    let test_folder = TestEnvironment::new();
+    let (grayscale_file, truncated_file) = create_grayscale_images(test_folder.path());
+    let capacity = image_capacity(grayscale_file.as_str(), &CapacityOptions::default())
+        .expect("Error getting grayscale image capacity");
+    // Grayscale image is 64x64 pixels, hidden at its RGB conversion.
+    assert_eq!(12_192, capacity.max_payload_bytes());
+    let data: Vec<u8> = (0..capacity.max_payload_bytes()).map(|i| (i % 253) as u8).collect();
+    hide_bytes_into_image(&data, grayscale_file.as_str())
+        .expect("Error hiding as much data as capacity reported");
+    assert_eq!(data, extract_bytes_from_image(grayscale_file.as_str()).unwrap());
+    assert!(image_capacity(truncated_file.as_str(), &CapacityOptions::default()).is_err(),
+            "Truncated image was not reported.");
+}
+
+#[test]
+fn test_verify_image() {
+    let (_test_folder, test_hidden_file, test_host_file) =
+        setup_test_files(format!("{}.bmp", HOST_FILE_NAME_SUFFIX).as_str());
+    assert!(!verify_image(test_hidden_file.as_str(), test_host_file.as_str())
+                .expect("Error verifying clean image"),
+            "Clean image was verified as host of hidden file.");
+    hide_into_image(test_hidden_file.as_str(), test_host_file.as_str())
+        .expect("Error hiding test file");
+    assert!(verify_image(test_hidden_file.as_str(), test_host_file.as_str())
+                .expect("Error verifying image with hidden data"),
+            "Hidden file was not found inside host image.");
+    let other_file = Path::new(SOURCE_FOLDER).join("genesis.txt");
+    assert!(!verify_image(other_file.to_str().unwrap(), test_host_file.as_str())
+                .expect("Error verifying image with hidden data"),
+            "A different file was verified as hidden inside host image.");
+}
+
+#[test]
+fn test_bytes_hiding() {
+    let (_test_folder, test_hidden_file, test_host_file) =
+        setup_test_files(format!("{}.png", HOST_FILE_NAME_SUFFIX).as_str());
+    let data = std::fs::read(&test_hidden_file).expect("Error reading test file");
+    hide_bytes_into_image(&data, test_host_file.as_str()).expect("Error hiding test bytes");
+    assert!(verify_image(test_hidden_file.as_str(), test_host_file.as_str())
+                .expect("Error verifying image with hidden data"),
+            "Hidden bytes differ from hidden file content.");
+    assert_eq!(data, extract_bytes_from_image(test_host_file.as_str())
+                         .expect("Error extracting hidden bytes"));
+}
+
+#[test]
+fn test_threaded_hiding() {
+    let (_test_folder, test_hidden_file, test_host_file) =
+        setup_test_files(format!("{}.png", HOST_FILE_NAME_SUFFIX).as_str());
+    let threaded_host_file = format!("{}.threaded.png", test_host_file);
+    std::fs::copy(&test_host_file, &threaded_host_file).expect("Error copying test host file");
+    hide_into_image(test_hidden_file.as_str(), test_host_file.as_str())
+        .expect("Error hiding test file");
+    hide_into_image_with_threads(test_hidden_file.as_str(), threaded_host_file.as_str(), 4)
+        .expect("Error hiding test file with threads");
+    assert_eq!(image::open(&test_host_file).unwrap().raw_pixels(),
+               image::open(&threaded_host_file).unwrap().raw_pixels(),
+               "Threaded hiding got a different image.");
+    let recovered_file = format!("{}.recovered", test_hidden_file);
+    extract_from_image_with_threads(recovered_file.as_str(), threaded_host_file.as_str(), 0)
+        .expect("Error extracting test file with threads");
+    assert_eq!(std::fs::read(&test_hidden_file).unwrap(), std::fs::read(&recovered_file).unwrap(),
+               "Threaded extraction got a different file.");
+}
+
+#[test]
+fn test_hidden_reader() {
+    let (_test_folder, test_hidden_file, test_host_file) =
+        setup_test_files(format!("{}.png", HOST_FILE_NAME_SUFFIX).as_str());
+    hide_into_image(test_hidden_file.as_str(), test_host_file.as_str())
+        .expect("Error hiding test file");
+    let hidden_data = std::fs::read(&test_hidden_file).unwrap();
+    let host_image = ContainerImage::new(test_host_file.as_str()).expect("Error opening host image");
+    let mut reader = HiddenReader::new(&host_image).expect("Error reading hidden data");
+    let mut recovered: Vec<u8> = Vec::new();
+    std::io::copy(&mut reader, &mut recovered).expect("Error copying hidden data");
+    assert_eq!(hidden_data, recovered, "Hidden reader got a different file.");
+    let middle = hidden_data.len() / 2;
+    reader.seek(SeekFrom::Start(middle as u64)).expect("Error seeking hidden data");
+    let mut block = [0_u8; 16];
+    reader.read_exact(&mut block).expect("Error reading hidden data range");
+    assert_eq!(&hidden_data[middle..middle + 16], &block[..]);
+}
+
+#[test]
+fn test_hidden_writer() {
+    let (_test_folder, test_hidden_file, test_host_file) =
+        setup_test_files(format!("{}.png", HOST_FILE_NAME_SUFFIX).as_str());
+    {
+        let mut host_image = ContainerImage::new(test_host_file.as_str()).expect("Error opening host image");
+        let mut writer = HiddenWriter::new(&mut host_image);
+        let mut hidden_file = std::fs::File::open(&test_hidden_file).unwrap();
+        std::io::copy(&mut hidden_file, &mut writer).expect("Error streaming hidden data");
+        writer.finish().expect("Error finishing hidden data");
+    }
+    let recovered_file = format!("{}.recovered", test_hidden_file);
+    extract_from_image(recovered_file.as_str(), test_host_file.as_str())
+        .expect("Error extracting streamed file");
+    assert_eq!(std::fs::read(&test_hidden_file).unwrap(), std::fs::read(&recovered_file).unwrap(),
+               "Streamed hiding got a different file.");
+}
+
+#[test]
+fn test_hide_into_image_with_report() {
+    let (_test_folder, test_hidden_file, test_host_file) =
+        setup_test_files(format!("{}.png", HOST_FILE_NAME_SUFFIX).as_str());
+    let report = hide_into_image_with_report(test_hidden_file.as_str(), test_host_file.as_str())
+        .expect("Error hiding test file");
+    assert!(report.modified_samples > 0, "No sample was modified after hiding.");
+    assert!(report.mse > 0.0 && report.psnr.is_finite());
+    // Hiding at least significant bits should be almost unnoticeable.
+    assert!(report.psnr > 40.0, "PSNR is too low: {}", report.psnr);
+    assert!(report.ssim > 0.99, "SSIM is too low: {}", report.ssim);
+    assert!(verify_image(test_hidden_file.as_str(), test_host_file.as_str())
+                .expect("Error verifying image with hidden data"),
+            "Hidden file was not found inside host image after generating report.");
+}
+
+#[test]
+fn test_analyze_image() {
+    let (_test_folder, _, test_host_file) =
+        setup_test_files(format!("{}.png", HOST_FILE_NAME_SUFFIX).as_str());
+    let clean_report = analyze_image(test_host_file.as_str())
+        .expect("Error analyzing clean image");
+    assert_eq!(Verdict::Clean, clean_report.verdict);
+    // Genesis text fills about a quarter of blue channel least significant bits.
+    let big_hidden_file = Path::new(SOURCE_FOLDER).join("genesis.txt");
+    hide_into_image(big_hidden_file.to_str().unwrap(), test_host_file.as_str())
+        .expect("Error hiding test file");
+    let report = analyze_image(test_host_file.as_str())
+        .expect("Error analyzing image with hidden data");
+    assert_eq!(Verdict::HiddenDataDetected, report.verdict);
+    assert!((report.estimated_rate - 0.25).abs() < 0.05,
+            "Estimated embedding rate was {}", report.estimated_rate);
+}
+
+#[test]
+fn test_analyze_unusual_images() {
+    let test_folder = TestEnvironment::new();
+    let (grayscale_file, truncated_file) = create_grayscale_images(test_folder.path());
+    let report = analyze_image(grayscale_file.as_str()).expect("Error analyzing grayscale image");
+    assert_eq!(3, report.channels.len());
+    assert!(analyze_image(truncated_file.as_str()).is_err(), "Truncated image was not reported.");
+}
+
+#[test]
+fn test_dump_image() {
+    let (test_folder, test_hidden_file, test_host_file) =
+        setup_test_files(format!("{}.png", HOST_FILE_NAME_SUFFIX).as_str());
+    hide_into_image(test_hidden_file.as_str(), test_host_file.as_str())
+        .expect("Error hiding test file");
+    let dump_file = test_folder.path().join("dump.bin");
+    let options = DumpOptions{channels: vec![Channel::Blue], ..DumpOptions::default()};
+    let dumped_bytes = dump_image(test_host_file.as_str(), &options, dump_file.to_str().unwrap())
+        .expect("Error dumping image");
+    // 512x512 pixels, one bit each.
+    assert_eq!(32768, dumped_bytes);
+    // Small files are hidden at one bit per pixel, so dump starts with steganer header and
+    // hidden file follows it.
+    let hidden_content = std::fs::read(&test_hidden_file).expect("Error reading hidden file");
+    let dumped_content = std::fs::read(&dump_file).expect("Error reading dump file");
+    assert_eq!((hidden_content.len() as u32).to_be_bytes(), dumped_content[..4]);
+    assert_eq!(hidden_content[..], dumped_content[4..4 + hidden_content.len()]);
+}
+
+#[test]
+fn test_dump_unusual_images() {
+    let test_folder = TestEnvironment::new();
+    let (grayscale_file, truncated_file) = create_grayscale_images(test_folder.path());
+    let dump_file = test_folder.path().join("dump.bin");
+    hide_bytes_into_image(b"Meet at noon", grayscale_file.as_str()).expect("Error hiding test data");
+    let options = DumpOptions{channels: vec![Channel::Blue], ..DumpOptions::default()};
+    // 64x64 pixels, one bit each.
+    assert_eq!(512, dump_image(grayscale_file.as_str(), &options, dump_file.to_str().unwrap())
+        .expect("Error dumping grayscale image"));
+    let dumped_content = std::fs::read(&dump_file).expect("Error reading dump file");
+    assert_eq!(b"Meet at noon", &dumped_content[4..16]);
+    assert!(dump_image(truncated_file.as_str(), &options, dump_file.to_str().unwrap()).is_err(),
+            "Truncated image was not reported.");
+}
+
+#[test]
+fn test_scan_image() {
+    let (_test_folder, test_hidden_file, test_host_file) =
+        setup_test_files(format!("{}.png", HOST_FILE_NAME_SUFFIX).as_str());
+    let clean_report = scan_image(test_host_file.as_str()).expect("Error scanning clean image");
+    assert!(clean_report.candidates.is_empty(), "Candidates found at a clean image.");
+    hide_into_image(test_hidden_file.as_str(), test_host_file.as_str())
+        .expect("Error hiding test file");
+    let report = scan_image(test_host_file.as_str()).expect("Error scanning image");
+    let best_candidate = report.candidates.first().expect("No candidate found");
+    // Steganer hides small files at blue channel least significant bit, after a size header.
+    assert_eq!(DumpOptions{channels: vec![Channel::Blue], ..DumpOptions::default()}, best_candidate.options);
+    assert_eq!(4, best_candidate.offset);
+    assert_eq!(Finding::Text, best_candidate.finding);
+    assert!(best_candidate.preview.starts_with("Lorem ipsum"));
+}
+
+#[test]
+fn test_wipe_unusual_images() {
+    let test_folder = TestEnvironment::new();
+    let (grayscale_file, truncated_file) = create_grayscale_images(test_folder.path());
+    hide_bytes_into_image(b"Meet at noon", grayscale_file.as_str()).expect("Error hiding test data");
+    let report = wipe_image(grayscale_file.as_str(), &WipeOptions::default())
+        .expect("Error wiping grayscale image");
+    assert!(report.steganer_payload_found, "Hidden data was not found before wiping.");
+    assert_ne!(ProbeStatus::PayloadFound, probe_image(grayscale_file.as_str()).unwrap().status);
+    assert!(wipe_image(truncated_file.as_str(), &WipeOptions::default()).is_err(),
+            "Truncated image was not reported.");
+}
+
+#[test]
+fn test_scan_unusual_images() {
+    let test_folder = TestEnvironment::new();
+    let (grayscale_file, truncated_file) = create_grayscale_images(test_folder.path());
+    hide_bytes_into_image(b"Lorem ipsum dolor sit amet, consectetur adipiscing elit.",
+                          grayscale_file.as_str())
+        .expect("Error hiding test data");
+    let report = scan_image(grayscale_file.as_str()).expect("Error scanning grayscale image");
+    let best_candidate = report.candidates.first().expect("No candidate found");
+    assert_eq!(Finding::Text, best_candidate.finding);
+    assert!(scan_image(truncated_file.as_str()).is_err(), "Truncated image was not reported.");
+}
+
+/// Write a PCM WAV file with a sine wave at every channel.
+///
+/// # Parameters:
+/// * path: Where to write WAV file.
+/// * channels: How many channels audio has.
+/// * bits_per_sample: Sample width, 8, 16 or 24 bits.
+/// * frames: How many samples every channel has.
+fn create_test_wav(path: &Path, channels: u16, bits_per_sample: u16, frames: u32) {
+    let bytes_per_sample = (bits_per_sample / 8) as u32;
+    let data_length = frames * channels as u32 * bytes_per_sample;
+    let mut content = Vec::new();
+    content.extend_from_slice(b"RIFF");
+    content.extend_from_slice(&(36 + data_length).to_le_bytes());
+    content.extend_from_slice(b"WAVEfmt ");
+    content.extend_from_slice(&16_u32.to_le_bytes());
+    content.extend_from_slice(&1_u16.to_le_bytes());
+    content.extend_from_slice(&channels.to_le_bytes());
+    content.extend_from_slice(&8_000_u32.to_le_bytes());
+    content.extend_from_slice(&(8_000 * channels as u32 * bytes_per_sample).to_le_bytes());
+    content.extend_from_slice(&(channels * bits_per_sample / 8).to_le_bytes());
+    content.extend_from_slice(&bits_per_sample.to_le_bytes());
+    content.extend_from_slice(b"data");
+    content.extend_from_slice(&data_length.to_le_bytes());
+    for frame in 0..frames {
+        for channel in 0..channels {
+            let amplitude = ((frame as f64 * (channel + 1) as f64 * 0.05).sin() * 0.8 *
+                (1_u32 << (bits_per_sample - 1)) as f64) as i32;
+            // 8 bits samples are unsigned.
+            let sample = if bits_per_sample == 8 { amplitude + 128 } else { amplitude };
+            content.extend_from_slice(&sample.to_le_bytes()[..bytes_per_sample as usize]);
+        }
+    }
+    std::fs::write(path, content).expect("Error writing test WAV file");
+}
+
+#[test]
+fn test_audio_hiding() {
+    for &(channels, bits_per_sample) in [(1, 8), (2, 16), (1, 24)].iter() {
+        let (test_folder, test_hidden_file, _) =
+            setup_test_files(format!("{}.png", HOST_FILE_NAME_SUFFIX).as_str());
+        let host_path = test_folder.path().join("song.wav");
+        let output_path = test_folder.path().join("out.wav");
+        let recovered_path = test_folder.path().join(FILE_RECOVERED);
+        create_test_wav(&host_path, channels, bits_per_sample, 1500);
+        let host_content = std::fs::read(&host_path).expect("Error reading host audio");
+        let mut hiding_config = _create_configuration(test_hidden_file.as_str(),
+                                                      host_path.to_str().unwrap(), false);
+        hiding_config.output_file = String::from(output_path.to_str().unwrap());
+        _run(&hiding_config).expect("Error hiding into audio");
+        // Only output audio is modified and it keeps its size.
+        let output_content = std::fs::read(&output_path).expect("Error reading output audio");
+        assert_eq!(host_content, std::fs::read(&host_path).unwrap());
+        assert_eq!(host_content.len(), output_content.len());
+        assert_ne!(host_content, output_content);
+        extract_from_audio(recovered_path.to_str().unwrap(), output_path.to_str().unwrap())
+            .expect("Error extracting from audio");
+        let original_file_hash = hash_file(test_hidden_file.as_str())
+            .expect("Something wrong happened when calculating hash for source file.");
+        let recovered_file_hash = hash_file(recovered_path.to_str().unwrap())
+            .expect("Something wrong happened when calculating hash for destination file.");
+        assert_eq!(original_file_hash.as_ref(), recovered_file_hash.as_ref(),
+                   "Recovered file content is not the same as original file content with {} \
+                    channels and {} bits per sample.", channels, bits_per_sample);
+    }
+}
+
+#[test]
+fn test_audio_container() {
+    let test_folder = TestEnvironment::new();
+    let host_path = test_folder.path().join("song.wav");
+    create_test_wav(&host_path, 2, 16, 1500);
+    let data: Vec<u8> = (0..500_u32).map(|i| (i * 7) as u8).collect();
+    {
+        let mut host_audio = ContainerAudio::new(host_path.to_str().unwrap()).expect("Error opening host audio");
+        let mut writer = HiddenWriter::new(&mut host_audio);
+        writer.write_all(&data).expect("Error streaming hidden data");
+        writer.finish().expect("Error finishing hidden data");
+    }
+    let host_audio = ContainerAudio::new(host_path.to_str().unwrap()).expect("Error opening host audio");
+    let mut recovered: Vec<u8> = Vec::new();
+    HiddenReader::new(&host_audio).expect("Error reading hidden data")
+        .read_to_end(&mut recovered).expect("Error copying hidden data");
+    assert_eq!(data, recovered, "Audio container got a different file.");
+}
+
+/// Create a 4:2:0 Y4M video with a moving gradient at every frame.
+fn create_test_y4m(path: &Path, width: usize, height: usize, frames: usize) {
+    let mut content = format!("YUV4MPEG2 W{} H{} F25:1 Ip A1:1 C420jpeg\n", width, height).into_bytes();
+    let chroma_length = 2 * width.div_ceil(2) * height.div_ceil(2);
+    for frame in 0..frames {
+        content.extend_from_slice(b"FRAME\n");
+        content.extend((0..width * height).map(|i| ((i % width + i / width + frame * 3) % 256) as u8));
+        content.extend(std::iter::repeat_n(128_u8, chroma_length));
+    }
+    std::fs::write(path, content).expect("Error writing test Y4M file");
+}
+
+#[test]
+fn test_video_hiding() {
+    let (test_folder, test_hidden_file, _) =
+        setup_test_files(format!("{}.png", HOST_FILE_NAME_SUFFIX).as_str());
+    let host_path = test_folder.path().join("clip.y4m");
+    let recovered_path = test_folder.path().join(FILE_RECOVERED);
+    create_test_y4m(&host_path, 16, 16, 8);
+    let host_content = std::fs::read(&host_path).expect("Error reading host video");
+    hide_into_video(test_hidden_file.as_str(), host_path.to_str().unwrap())
+        .expect("Error hiding into video");
+    let stego_content = std::fs::read(&host_path).expect("Error reading stego video");
+    assert_eq!(host_content.len(), stego_content.len());
+    // Payload is spread over every frame.
+    let frame_length = 16 * 16 + 2 * 8 * 8;
+    let header_length = host_content.len() - 8 * (frame_length + "FRAME\n".len());
+    for frame in 0..8 {
+        let frame_start = header_length + frame * (frame_length + 6) + 6;
+        assert_ne!(&host_content[frame_start..frame_start + frame_length],
+                   &stego_content[frame_start..frame_start + frame_length],
+                   "Frame {} was not used to hide data.", frame);
+    }
+    let extracting_config = _create_configuration(recovered_path.to_str().unwrap(),
+                                                  host_path.to_str().unwrap(), true);
+    _run(&extracting_config).expect("Error extracting from video");
+    assert_eq!(std::fs::read(&test_hidden_file).unwrap(), std::fs::read(&recovered_path).unwrap());
+}
+
+/// Create an animated GIF with a different gradient and delay at every frame.
+fn create_test_gif(path: &Path, width: u32, height: u32, frames: u32) {
+    let file = std::fs::File::create(path).expect("Error creating test GIF file");
+    let mut encoder = image::gif::Encoder::new(file);
+    let frames = (0..frames).map(|frame| {
+        let buffer = image::RgbaImage::from_fn(width, height, |x, y| {
+            image::Rgba([(x * 4 + frame * 40) as u8, (y * 8) as u8, ((x + y) * 2) as u8, 255])
+        });
+        image::Frame::from_parts(buffer, 0, 0, Ratio::from_integer((frame + 1) as u16 * 10))
+    });
+    encoder.encode_frames(frames).expect("Error encoding test GIF file");
+}
+
+/// Get delay of every frame of a GIF file.
+fn gif_delays(path: &Path)-> Vec<Ratio<u16>> {
+    let file = std::fs::File::open(path).expect("Error opening GIF file");
+    image::gif::Decoder::new(file).expect("Error decoding GIF file")
+        .into_frames()
+        .collect_frames().expect("Error decoding GIF frames")
+        .iter()
+        .map(|frame| frame.delay())
+        .collect()
+}
+
+#[test]
+fn test_animation_hiding() {
+    let (test_folder, test_hidden_file, _) =
+        setup_test_files(format!("{}.png", HOST_FILE_NAME_SUFFIX).as_str());
+    let host_path = test_folder.path().join("animation.gif");
+    let output_path = test_folder.path().join("output.gif");
+    let recovered_path = test_folder.path().join(FILE_RECOVERED);
+    create_test_gif(&host_path, 64, 32, 3);
+    let mut hiding_config = _create_configuration(test_hidden_file.as_str(),
+                                                  host_path.to_str().unwrap(), false);
+    hiding_config.output_file = String::from(output_path.to_str().unwrap());
+    _run(&hiding_config).expect("Error hiding into animated GIF");
+    assert_ne!(std::fs::read(&host_path).unwrap(), std::fs::read(&output_path).unwrap());
+    // Output is still a valid GIF with the same frames and timing.
+    assert_eq!(gif_delays(&host_path), gif_delays(&output_path));
+    extract_from_animation(recovered_path.to_str().unwrap(), output_path.to_str().unwrap())
+        .expect("Error extracting from animated GIF");
+    assert_eq!(std::fs::read(&test_hidden_file).unwrap(), std::fs::read(&recovered_path).unwrap());
+}
+
+/// Carrier defined outside steganer, keeping 12 bits samples in memory.
+struct ThirdPartyCarrier {
+    samples: Vec<u16>,
+    saves: u32,
+}
+
+impl Carrier for ThirdPartyCarrier {
+    fn sample_count(&self)-> u64 { self.samples.len() as u64 }
+
+    fn sample_bits(&self)-> u8 { 12 }
+
+    fn read_sample(&self, position: u64)-> u64 { self.samples[position as usize] as u64 }
+
+    fn write_sample(&mut self, position: u64, value: u64) {
+        self.samples[position as usize] = (value & 0xfff) as u16;
+    }
+
+    fn save(&mut self)-> steganer::Result<()> {
+        self.saves += 1;
+        Ok(())
+    }
+}
+
+#[test]
+fn test_steganer_builder() {
+    let (_test_folder, test_hidden_file, test_host_file) =
+        setup_test_files(format!("{}.png", HOST_FILE_NAME_SUFFIX).as_str());
+    let output_file = format!("{}.output.png", test_host_file);
+    let steganer = Steganer::builder()
+        .carrier(test_host_file.as_str())
+        .output(output_file.as_str())
+        .bits_per_pixel(1)
+        .threads(2)
+        .build()
+        .expect("Error building steganer");
+    let capacity = steganer.capacity().expect("Error getting capacity");
+    assert_eq!(1, capacity.levels.len());
+    assert_eq!((512 * 512 - 32) / 8, capacity.max_payload_bytes());
+    steganer.hide(test_hidden_file.as_str()).expect("Error hiding test file");
+    let recovered_file = format!("{}.recovered", test_hidden_file);
+    Steganer::builder()
+        .carrier(output_file.as_str())
+        .build()
+        .expect("Error building steganer")
+        .extract(recovered_file.as_str())
+        .expect("Error extracting test file");
+    assert_eq!(std::fs::read(&test_hidden_file).unwrap(), std::fs::read(&recovered_file).unwrap(),
+               "Steganer builder got a different file.");
+    // A file bigger than 1 bit per pixel capacity must not be hidden at that level.
+    let big_file = format!("{}.big", test_hidden_file);
+    std::fs::write(&big_file, vec![0x5a; capacity.max_payload_bytes() as usize + 1]).unwrap();
+    assert!(steganer.hide(big_file.as_str()).is_err(), "Bits per pixel limit was not honored.");
+}
+
+#[test]
+fn test_third_party_carrier() {
+    let (test_folder, test_hidden_file, _) =
+        setup_test_files(format!("{}.png", HOST_FILE_NAME_SUFFIX).as_str());
+    let recovered_path = test_folder.path().join(FILE_RECOVERED);
+    let mut carrier = ThirdPartyCarrier{samples: (0..2000).map(|i| (i * 7 % 4096) as u16).collect(),
+                                        saves: 0};
+    let bits_per_sample = hide_into_carrier(test_hidden_file.as_str(), &mut carrier)
+        .expect("Error hiding into third party carrier");
+    // 430 bytes into 1968 usable samples.
+    assert_eq!(2, bits_per_sample);
+    assert_eq!(1, carrier.saves);
+    extract_from_carrier(recovered_path.to_str().unwrap(), &carrier)
+        .expect("Error extracting from third party carrier");
+    assert_eq!(std::fs::read(&test_hidden_file).unwrap(), std::fs::read(&recovered_path).unwrap());
+}
+
+#[test]
+fn test_text_hiding() {
+    let (test_folder, test_hidden_file, test_host_file) = setup_test_files("genesis.txt");
+    let secret_path = test_folder.path().join("secret.txt");
+    std::fs::write(&secret_path, "Meet at noon").expect("Error writing secret file");
+    let output_path = test_folder.path().join("output.txt");
+    let recovered_path = test_folder.path().join(FILE_RECOVERED);
+    // A multiline text can hide a file as trailing whitespace, but a single line text like
+    // loren.txt can only hide a short message between its words.
+    let cases = [(TextEncoding::Whitespace, test_hidden_file.as_str(), test_host_file.as_str()),
+                 (TextEncoding::ZeroWidth, secret_path.to_str().unwrap(), test_hidden_file.as_str())];
+    for &(encoding, file_to_hide, host_file) in cases.iter() {
+        let host_text = std::fs::read_to_string(host_file).expect("Error reading host text");
+        let mut hiding_config = _create_configuration(file_to_hide, host_file, false);
+        hiding_config.output_file = String::from(output_path.to_str().unwrap());
+        hiding_config.text_encoding = encoding;
+        _run(&hiding_config).expect("Error hiding into text");
+        // Only hiding characters are added, so visible text is the same.
+        let output_text = std::fs::read_to_string(&output_path).expect("Error reading output text");
+        let visible_output: String = output_text
+            .split('\n')
+            .map(|line| line.trim_end_matches([' ', '\t']))
+            .collect::<Vec<&str>>()
+            .join("\n")
+            .chars()
+            .filter(|c| !['\u{200B}', '\u{200C}', '\u{200D}'].contains(c))
+            .collect();
+        assert_ne!(host_text, output_text);
+        assert_eq!(host_text, visible_output, "Visible text changed with {} encoding.", encoding);
+        extract_from_text(recovered_path.to_str().unwrap(), output_path.to_str().unwrap(), encoding)
+            .expect("Error extracting from text");
+        assert_eq!(std::fs::read(file_to_hide).unwrap(), std::fs::read(&recovered_path).unwrap(),
+                   "Recovered file content is not the same as original with {} encoding.", encoding);
+    }
+}
+
+#[test]
+fn test_text_capacity() {
+    let (_test_folder, test_hidden_file, test_host_file) = setup_test_files("genesis.txt");
+    let capacity = text_capacity(test_host_file.as_str()).expect("Error getting text capacity");
+    assert_eq!(216, capacity.lines);
+    // Every line hides 32 bits and first line is used by header.
+    assert_eq!(860, capacity.whitespace_bytes);
+    // loren.txt is a single line, so header does not even fit as trailing whitespace.
+    let capacity = text_capacity(test_hidden_file.as_str()).expect("Error getting text capacity");
+    assert_eq!(1, capacity.lines);
+    assert_eq!(0, capacity.whitespace_bytes);
+    assert_eq!((capacity.word_gaps * 2 - 32) / 8, capacity.zero_width_bytes);
+    assert!(capacity.zero_width_bytes > 0);
+}